@@ -1,6 +1,6 @@
 use crate::app::recipe_window::RecipeWindowGUI;
 use copypasta::{ClipboardContext, ClipboardProvider};
-use egui::{Context, Ui, Widget};
+use egui::{Checkbox, Context, Ui, Widget};
 #[cfg(not(target_arch = "wasm32"))]
 use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
@@ -8,18 +8,26 @@ use serde::{Deserialize, Serialize};
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
 
-use crate::app::recipe_graph::RecipeGraph;
-use crate::app::recipe_window::arrow_flow::ArrowFlow;
+use crate::app::recipe_graph::linear_system::SolverStatus;
+use crate::app::recipe_graph::{CalculationMode, RecipeGraph};
+use crate::app::recipe_window::arrow_flow::{ArrowEdit, ArrowFlow, ArrowUsageState};
 use crate::app::recipe_window::compound_recipe_window::CompoundRecipeWindow;
-use crate::app::recipe_window::resource_sink::ResourceSink;
-use crate::app::recipe_window::resources_sources::ResourceSource;
+use crate::app::recipe_window::resource_merger::ResourceMerger;
+use crate::app::recipe_window::resource_sink::{self, ResourceSink};
+use crate::app::recipe_window::resource_splitter::ResourceSplitter;
+use crate::app::recipe_window::resources_sources::{self, ResourceSource};
 use crate::app::recipe_window::simple_recipe_window::SimpleRecipeWindow;
-use crate::utils::{id_init, Io};
+use crate::app::recipe_window::RecipeWindowType;
+use crate::app::resources::time_settings::{TimeSettings, TimeUnit};
+use crate::app::resources::transport::Game;
+use crate::app::resources::{RatePer, Unit};
+use crate::utils::{formatting, gen_id, id_init, Io};
+use commons::resource_catalog::UNTAGGED;
 use commons::CommonsManager;
 use eframe::Frame;
 use error::ShowError;
 use log::info;
-use resources::resource_flow::ManageResourceFlow;
+use resources::resource_flow::{ManageResourceFlow, ResourceFlow};
 use std::time::Duration;
 
 pub mod commons;
@@ -35,9 +43,21 @@ pub(crate) mod resources;
 pub struct FactoryManagementApp {
     new_recipe_title: String,
     new_resource_source: String,
+    new_resource_source_unit: Unit,
+
+    ///the resource summaries are split by category
+    group_by_category: bool,
+
+    ///only this category is shown in the resource summaries
+    category_filter: Option<String>,
+
     current_graph: RecipeGraph,
     commons: CommonsManager,
     active_arrow: Option<ArrowFlow>,
+
+    ///anchored arrow being moved by the active arrow, put back if the move is cancelled
+    #[serde(skip)]
+    moved_arrow: Option<ArrowFlow>,
 }
 
 impl Default for FactoryManagementApp {
@@ -46,8 +66,12 @@ impl Default for FactoryManagementApp {
             // Example stuff:
             new_recipe_title: "Hello World!".to_owned(),
             new_resource_source: "".to_string(),
+            new_resource_source_unit: Unit::Piece,
+            group_by_category: false,
+            category_filter: None,
             commons: Default::default(),
             active_arrow: None,
+            moved_arrow: None,
             current_graph: RecipeGraph::new(),
         }
     }
@@ -65,7 +89,10 @@ impl FactoryManagementApp {
         if let Some(storage) = cc.storage {
             let mut loaded: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
             info!("Initial calculations after load from app storage");
-            loaded.current_graph.calculate();
+            loaded
+                .current_graph
+                .calculate(&loaded.commons.time_settings);
+            loaded.register_resources();
             return loaded;
         }
 
@@ -76,8 +103,13 @@ impl FactoryManagementApp {
     fn reload(&mut self, other: Self) {
         self.new_recipe_title = other.new_recipe_title;
         self.new_resource_source = other.new_resource_source;
+        self.new_resource_source_unit = other.new_resource_source_unit;
         self.current_graph = other.current_graph;
         self.active_arrow = other.active_arrow;
+        self.moved_arrow = None;
+        self.commons.resources = other.commons.resources;
+        self.commons.time_settings = other.commons.time_settings;
+        self.commons.recalculate = true;
     }
 
     fn top_panel(&mut self, ctx: &Context, _frame: &mut Frame, error: bool) {
@@ -197,8 +229,19 @@ impl FactoryManagementApp {
             self.sources_and_sinks_adding(ui);
 
             ui.separator();
+            self.calculation_settings(ui);
+            self.time_settings(ui);
+            self.transport_tiers(ui);
+
+            self.resource_catalog(ui);
+
+            ui.separator();
+            self.summary_settings(ui);
             self.resource_usage(ui);
             self.resource_generation(ui);
+            self.resource_excess(ui);
+            self.power_balance(ui);
+            self.transport_capacity(ui);
 
             ui.separator();
             self.recipes_list(ui);
@@ -251,49 +294,619 @@ impl FactoryManagementApp {
     }
 
     fn resource_generation(&mut self, ui: &mut Ui) {
+        let settings = &self.commons.time_settings;
+        let flows: Vec<ResourceFlow<f32, f32>> = self
+            .current_graph
+            .sinks
+            .iter()
+            .filter_map(|sink| sink.sink.as_ref())
+            .map(|sink| sink.total_in(settings).displayed(settings))
+            .collect();
         ui.collapsing("Resource generated", |ui| {
-            for sink in self.current_graph.sinks.iter() {
-                if let Some(sink) = sink.sink.as_ref() {
-                    let flow = sink.total_in();
-                    ui.horizontal(|ui| {
-                        ui.label(format!(
-                            "{}: {} {}",
-                            flow.resource.name, flow.amount, flow.rate
-                        ));
-                    });
-                }
-            }
+            self.flows_summary(ui, "Generated", &flows);
         });
     }
 
+    fn resource_excess(&mut self, ui: &mut Ui) {
+        let settings = &self.commons.time_settings;
+        let excess = self.current_graph.excess_flows(settings);
+        let header = match excess.is_empty() {
+            true => egui::RichText::new("Resource excess"),
+            false => egui::RichText::new("⚠ Resource excess").color(egui::Color32::GOLD),
+        };
+        egui::CollapsingHeader::new(header)
+            .id_source("Resource excess")
+            .show(ui, |ui| {
+                for (title, flow) in excess.iter() {
+                    let flow = flow.displayed(settings);
+                    ui.label(format!(
+                        "{title}: {} {} Per {}",
+                        flow.resource.name,
+                        formatting::float_format(flow.amount, 3),
+                        flow.rate.name(settings)
+                    ));
+                }
+            })
+            .header_response
+            .on_hover_text("Outputs creating more than what is taken, they would back up in game");
+    }
+
     fn resource_usage(&mut self, ui: &mut Ui) {
+        let settings = &self.commons.time_settings;
+        let flows: Vec<ResourceFlow<f32, f32>> = self
+            .current_graph
+            .sources
+            .iter()
+            .map(|source| source.output.total_out(settings).displayed(settings))
+            .collect();
         ui.collapsing("Resource usage", |ui| {
-            for source in self.current_graph.sources.iter() {
-                let flow = source.output.total_out();
-                ui.horizontal(|ui| {
+            self.flows_summary(ui, "Used", &flows);
+        });
+    }
+
+    fn summary_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.group_by_category, "Group by category");
+            let selected = self
+                .category_filter
+                .clone()
+                .unwrap_or_else(|| "Every category".to_string());
+            egui::ComboBox::from_id_source("Category filter")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.category_filter, None, "Every category");
+                    let mut categories = self.commons.resources.tags();
+                    categories.push(UNTAGGED.to_string());
+                    for category in categories {
+                        let text = category.clone();
+                        ui.selectable_value(&mut self.category_filter, Some(category), text);
+                    }
+                });
+        });
+    }
+
+    /// Show flows of the summaries, grouped and filtered by category if asked
+    ///
+    /// # Arguments
+    ///
+    /// * `ui`: where to show the flows
+    /// * `id_source`: makes the groups unique
+    /// * `flows`: the flows, in the display rate
+    ///
+    /// returns: ()
+    fn flows_summary(&self, ui: &mut Ui, id_source: &str, flows: &[ResourceFlow<f32, f32>]) {
+        let settings = &self.commons.time_settings;
+        let groups =
+            self.commons
+                .resources
+                .group_flows(flows, self.category_filter.as_deref(), settings);
+        if !self.group_by_category {
+            //a filter keeps a single group, so no flow is shown twice
+            let shown: Vec<&ResourceFlow<f32, f32>> = match self.category_filter {
+                None => flows.iter().collect(),
+                Some(_) => groups.iter().flat_map(|group| group.flows.iter()).collect(),
+            };
+            for flow in shown {
+                Self::flow_label(ui, flow, settings);
+            }
+            return;
+        }
+        for group in groups.iter() {
+            let subtotals: Vec<String> = group
+                .subtotals
+                .iter()
+                .map(|(unit, amount)| {
+                    format!(
+                        "{} {}{}",
+                        formatting::float_format(*amount, 3),
+                        unit.symbol(),
+                        group.rate.to_shortened_string(settings)
+                    )
+                })
+                .collect();
+            egui::CollapsingHeader::new(format!("{}: {}", group.category, subtotals.join(", ")))
+                .id_source((id_source, &group.category))
+                .show(ui, |ui| {
+                    for flow in group.flows.iter() {
+                        Self::flow_label(ui, flow, settings);
+                    }
+                });
+        }
+    }
+
+    fn flow_label(ui: &mut Ui, flow: &ResourceFlow<f32, f32>, settings: &TimeSettings) {
+        ui.label(format!(
+            "{}: {} {} Per {}",
+            flow.resource.name,
+            formatting::float_format(flow.amount, 3),
+            flow.resource.unit.symbol(),
+            flow.rate.name(settings)
+        ));
+    }
+
+    fn power_balance(&mut self, ui: &mut Ui) {
+        let settings = &self.commons.time_settings;
+        let usage = self.current_graph.power_usage(settings);
+        let generation = self.current_graph.power_generation(settings);
+        let color = match self.current_graph.power_deficit(settings) {
+            true => egui::Color32::RED,
+            false => ui.visuals().text_color(),
+        };
+        egui::CollapsingHeader::new(
+            egui::RichText::new(format!(
+                "Power: {} / {}",
+                formatting::power_format(usage, 3),
+                formatting::power_format(generation, 3)
+            ))
+            .color(color),
+        )
+        .id_source("Power balance")
+        .show(ui, |ui| {
+            for recipe in self.current_graph.simple_recipes.iter() {
+                let recipe = &recipe.inner_recipe;
+                let (usage, generation) = (
+                    recipe.power_usage(settings),
+                    recipe.power_generation(settings),
+                );
+                if usage > 0.0 {
                     ui.label(format!(
-                        "{}: {} {}",
-                        flow.resource.name, flow.amount, flow.rate
+                        "{}: -{}",
+                        recipe.get_title(),
+                        formatting::power_format(usage, 3)
                     ));
+                } else if generation > 0.0 {
+                    ui.label(format!(
+                        "{}: +{}",
+                        recipe.get_title(),
+                        formatting::power_format(generation, 3)
+                    ));
+                }
+            }
+        })
+        .header_response
+        .on_hover_text("Power used / power generated");
+    }
+
+    fn calculation_settings(&mut self, ui: &mut Ui) {
+        let mut mode = self.current_graph.mode;
+        egui::ComboBox::from_label("Calculation")
+            .selected_text(mode.to_string())
+            .show_ui(ui, |ui| {
+                for m in [
+                    CalculationMode::Propagation,
+                    CalculationMode::Linear,
+                    CalculationMode::Target,
+                ] {
+                    ui.selectable_value(&mut mode, m, m.to_string());
+                }
+            });
+        if mode != self.current_graph.mode {
+            self.current_graph.mode = mode;
+            self.commons.recalculate = true;
+        }
+        let linear = self.current_graph.mode == CalculationMode::Linear;
+        if ui
+            .add_enabled(
+                linear,
+                Checkbox::new(&mut self.current_graph.exact, "Exact machine counts"),
+            )
+            .on_hover_text(
+                "Linear mode only: solve the machine counts with exact fractions. \
+                 The flows and the other modes still use floating numbers, and the amounts \
+                 are read as fractions with a denominator up to 1 000 000. \
+                 If a fraction gets too big, the counts are solved with floating numbers.",
+            )
+            .on_disabled_hover_text("Only available in the linear mode")
+            .changed()
+        {
+            self.commons.recalculate = true;
+        }
+
+        if self.current_graph.solver_status != SolverStatus::NotSolved {
+            let status = self.current_graph.solver_status;
+            let color = match status {
+                SolverStatus::Feasible => egui::Color32::GREEN,
+                SolverStatus::UnderConstrained(_) | SolverStatus::NotSolved => {
+                    egui::Color32::YELLOW
+                }
+                SolverStatus::OverConstrained | SolverStatus::Overflow => egui::Color32::RED,
+            };
+            ui.label(egui::RichText::new(format!("Status: {status}")).color(color));
+        }
+        if self.current_graph.exact_overflow {
+            ui.label(
+                egui::RichText::new("⚠ Exact counts overflowed, floating numbers used")
+                    .color(egui::Color32::YELLOW),
+            )
+            .on_hover_text("A fraction of the resolution got too big to be exact");
+        }
+
+        for recipes in self.current_graph.loops.iter() {
+            ui.label(format!("🔁 Loop: {}", recipes.join(" → ")))
+                .on_hover_text("Loops are always calculated as a linear system");
+        }
+    }
+
+    fn time_settings(&mut self, ui: &mut Ui) {
+        let mut remove_unit = false;
+        let settings = &mut self.commons.time_settings;
+        let mut changed = false;
+        egui::CollapsingHeader::new("Time settings").show(ui, |ui| {
+            let mut display_rate = settings.display_rate;
+            egui::ComboBox::from_label("Display rate")
+                .selected_text(
+                    display_rate.map_or("Flow rate".to_string(), |rate| rate.name(settings)),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut display_rate, None, "Flow rate");
+                    for rate in settings.rates() {
+                        ui.selectable_value(&mut display_rate, Some(rate), rate.name(settings));
+                    }
+                })
+                .response
+                .on_hover_text("Every flow is shown in this rate");
+            if display_rate != settings.display_rate {
+                settings.display_rate = display_rate;
+                changed = true;
+            }
+            ui.horizontal(|ui| {
+                ui.label("Ticks per second:");
+                changed |= egui::DragValue::new(&mut settings.ticks_per_second)
+                    .clamp_range(0.001..=f32::MAX)
+                    .ui(ui)
+                    .changed();
+            });
+            for unit in settings.units.iter_mut() {
+                ui.horizontal(|ui| {
+                    changed |= ui.text_edit_singleline(&mut unit.name).changed();
+                    changed |= egui::DragValue::new(&mut unit.seconds)
+                        .clamp_range(0.001..=f32::MAX)
+                        .suffix(" s")
+                        .ui(ui)
+                        .changed();
+                });
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add time unit").clicked() {
+                    settings.units.push(TimeUnit {
+                        name: "Day".to_string(),
+                        seconds: 86400.0,
+                    });
+                    changed = true;
+                }
+                remove_unit = !settings.units.is_empty()
+                    && ui
+                        .button("🗑")
+                        .on_hover_text("Remove the last time unit, if nothing uses it")
+                        .clicked();
+            });
+        });
+        if remove_unit {
+            changed |= self.remove_time_unit();
+        }
+        if changed {
+            self.commons.recalculate = true;
+        }
+    }
+
+    /// Remove the last custom time unit, it's kept if a flow, a target or a limit still uses it
+    ///
+    /// returns: `bool` flag indicating the unit was removed
+    fn remove_time_unit(&mut self) -> bool {
+        let settings = &self.commons.time_settings;
+        let unit = match settings.units.len().checked_sub(1) {
+            None => return false,
+            Some(index) => RatePer::Custom(index),
+        };
+        if self.current_graph.uses_rate(unit) {
+            self.commons.add_error(ShowError::new(format!(
+                "The time unit {} is still used by the graph",
+                unit.name(settings)
+            )));
+            return false;
+        }
+        let settings = &mut self.commons.time_settings;
+        settings.units.pop();
+        if settings.display_rate == Some(unit) {
+            settings.display_rate = None;
+        }
+        true
+    }
+
+    fn transport_tiers(&mut self, ui: &mut Ui) {
+        let transport = &mut self.current_graph.transport;
+        let settings = &self.commons.time_settings;
+        let mut changed = false;
+        let mut removed = None;
+        egui::CollapsingHeader::new("Transport tiers").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let mut game = transport.game;
+                egui::ComboBox::from_id_source("Transport game")
+                    .selected_text(game.map_or("Custom", Game::name))
+                    .show_ui(ui, |ui| {
+                        for g in Game::all() {
+                            ui.selectable_value(&mut game, Some(g), g.name());
+                        }
+                    });
+                if let Some(game) = game {
+                    if ui
+                        .button("Load tiers")
+                        .on_hover_text("Replace the tiers by the ones of the game")
+                        .clicked()
+                    {
+                        transport.load_game(game);
+                        changed = true;
+                    }
+                }
+            });
+            for (index, tier) in transport.tiers_mut().iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    changed |= egui::TextEdit::singleline(&mut tier.name)
+                        .desired_width(120.0)
+                        .ui(ui)
+                        .changed();
+                    changed |= egui::DragValue::new(&mut tier.capacity)
+                        .clamp_range(0.001..=f32::MAX)
+                        .ui(ui)
+                        .changed();
+                    changed |=
+                        recipe_window::unit_combo(ui, ("Tier unit", index), &mut tier.unit, None);
+                    let mut rate = tier.rate;
+                    egui::ComboBox::from_id_source(("Tier rate", index))
+                        .selected_text(rate.to_shortened_string(settings))
+                        .show_ui(ui, |ui| {
+                            for r in RatePer::all(settings) {
+                                ui.selectable_value(&mut rate, r, r.name(settings));
+                            }
+                        });
+                    if rate != tier.rate {
+                        tier.rate = rate;
+                        changed = true;
+                    }
+                    if ui.button("🗑").on_hover_text("Remove the tier").clicked() {
+                        removed = Some(tier.id);
+                    }
+                });
+            }
+            if ui.button("Add tier").clicked() {
+                transport.add("Belt", 15.0, Unit::Piece, RatePer::Second);
+                transport.game = None;
+                changed = true;
+            }
+        });
+        if let Some(id) = removed {
+            transport.remove(id);
+            changed = true;
+        }
+        if changed {
+            self.commons.recalculate = true;
+        }
+    }
+
+    fn transport_capacity(&mut self, ui: &mut Ui) {
+        let arrows = self.current_graph.over_capacity_arrows();
+        if arrows.is_empty() {
+            return;
+        }
+        egui::CollapsingHeader::new(
+            egui::RichText::new(format!("⚠ {} links over capacity", arrows.len()))
+                .color(egui::Color32::YELLOW),
+        )
+        .id_source("Transport capacity")
+        .show(ui, |ui| {
+            for arrow in arrows {
+                if let Ok(text) = arrow.generate_tooltip(&self.commons.time_settings) {
+                    ui.label(text);
+                }
+            }
+        })
+        .header_response
+        .on_hover_text("Links carrying more than their transport tier, with the lanes needed");
+    }
+
+    ///Add the resources of the graph missing from the catalog
+    fn register_resources(&mut self) {
+        for resource in self.current_graph.resources() {
+            self.commons.resources.register(&resource);
+        }
+    }
+
+    fn resource_catalog(&mut self, ui: &mut Ui) {
+        let mut renamed = None;
+        let mut unit_changed = None;
+        let mut tag_changed = None;
+        let tags = self.commons.resources.tags();
+        egui::CollapsingHeader::new("Resources").show(ui, |ui| {
+            for entry in self.commons.resources.entries() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(entry.color32(), "⏺");
+                    let id = ui.id().with(("Resource name", entry.id));
+                    let mut name = ui
+                        .data_mut(|d| d.get_temp::<String>(id))
+                        .unwrap_or_else(|| entry.name.clone());
+                    let response = ui.text_edit_singleline(&mut name);
+                    if response.lost_focus() && name != entry.name {
+                        renamed = Some((entry.id, name.clone()));
+                    }
+                    if response.has_focus() {
+                        ui.data_mut(|d| d.insert_temp(id, name));
+                    } else {
+                        ui.data_mut(|d| d.remove::<String>(id));
+                    }
+                    let mut unit = entry.unit;
+                    let dimension = Some(unit.dimension());
+                    if recipe_window::unit_combo(
+                        ui,
+                        ("Resource unit", entry.id),
+                        &mut unit,
+                        dimension,
+                    ) {
+                        unit_changed = Some((entry.id, unit));
+                    }
+                    let hover = match entry.tags.is_empty() {
+                        true => "No category".to_string(),
+                        false => entry.tags.join(", "),
+                    };
+                    ui.menu_button("🏷", |ui| {
+                        for tag in tags.iter() {
+                            let mut tagged = entry.tags.contains(tag);
+                            if ui.checkbox(&mut tagged, tag).changed() {
+                                tag_changed = Some((entry.id, tag.clone(), tagged));
+                            }
+                        }
+                        let id = ui.id().with(("New category", entry.id));
+                        let mut new_tag = ui
+                            .data_mut(|d| d.get_temp::<String>(id))
+                            .unwrap_or_default();
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut new_tag);
+                            if ui.button("Add").clicked() {
+                                tag_changed = Some((entry.id, new_tag.clone(), true));
+                                new_tag.clear();
+                            }
+                        });
+                        ui.data_mut(|d| d.insert_temp(id, new_tag));
+                    })
+                    .response
+                    .on_hover_text(hover);
                 });
             }
         });
+
+        if let Some((id, tag, tagged)) = tag_changed {
+            self.commons.resources.set_tag(id, &tag, tagged);
+        }
+
+        if let Some((id, unit)) = unit_changed {
+            self.commons.resources.set_unit(id, unit);
+        }
+
+        if let Some((id, name)) = renamed {
+            match self.commons.resources.rename(id, &name) {
+                Ok((old, new)) => {
+                    self.current_graph.rename_resource(&old, &new);
+                    if let Some(arrow) = self.active_arrow.as_mut() {
+                        arrow.resource.rename(&old, &new);
+                    }
+                    self.commons.recalculate = true;
+                }
+                Err(e) => self.commons.add_error(e),
+            }
+        }
     }
 
     fn sources_and_sinks_adding(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
             ui.label("New resource source:");
-            ui.text_edit_singleline(&mut self.new_resource_source);
+            self.commons
+                .resources
+                .autocomplete(ui, &mut self.new_resource_source, "resource name");
+            let known = self
+                .commons
+                .resources
+                .find(&self.new_resource_source)
+                .map(|entry| entry.unit);
+            if let Some(known) = known {
+                if known.dimension() != self.new_resource_source_unit.dimension() {
+                    self.new_resource_source_unit = known;
+                }
+            }
+            recipe_window::unit_combo(
+                ui,
+                "New source unit",
+                &mut self.new_resource_source_unit,
+                known.map(Unit::dimension),
+            );
         });
 
         if ui.button("Create source").clicked() {
-            let source = ResourceSource::new(self.new_resource_source.clone());
+            let resource = self
+                .commons
+                .resources
+                .resolve(&self.new_resource_source, self.new_resource_source_unit);
+            let source = ResourceSource::from_definition(resource);
             self.current_graph.sources.push(source);
         }
         if ui.button("Create sink").clicked() {
             let sink = ResourceSink::new();
             self.current_graph.sinks.push(sink);
         }
+        ui.horizontal(|ui| {
+            if ui
+                .button("Create outpost")
+                .on_hover_text("A source window offering several resources")
+                .clicked()
+            {
+                let resource = self
+                    .commons
+                    .resources
+                    .resolve(&self.new_resource_source, self.new_resource_source_unit);
+                let mut source = ResourceSource::from_definition(resource);
+                source.group = Some(gen_id("Outpost".to_string()));
+                self.current_graph.sources.push(source);
+            }
+            if ui
+                .button("Create storage")
+                .on_hover_text("A sink window taking several resources")
+                .clicked()
+            {
+                let sink = ResourceSink::in_group(gen_id("Storage".to_string()));
+                self.current_graph.sinks.push(sink);
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Create splitter").clicked() {
+                let resource = self
+                    .commons
+                    .resources
+                    .resolve(&self.new_resource_source, self.new_resource_source_unit);
+                let splitter = ResourceSplitter::new(resource);
+                self.current_graph.splitters.push(splitter);
+            }
+            if ui.button("Create merger").clicked() {
+                let resource = self
+                    .commons
+                    .resources
+                    .resolve(&self.new_resource_source, self.new_resource_source_unit);
+                let merger = ResourceMerger::new(resource);
+                self.current_graph.mergers.push(merger);
+            }
+        });
+    }
+
+    ///Show the outposts and storages, the windows grouping several sources or sinks
+    fn groups_windows(&mut self, ctx: &Context, error: bool) {
+        let mut outposts: Vec<egui::Id> = vec![];
+        for group in self.current_graph.sources.iter().filter_map(|s| s.group) {
+            if !outposts.contains(&group) {
+                outposts.push(group);
+            }
+        }
+        for group in outposts {
+            resources_sources::show_group(
+                &mut self.current_graph.sources,
+                group,
+                &mut self.commons,
+                ctx,
+                !error,
+            );
+        }
+
+        let mut storages: Vec<egui::Id> = vec![];
+        for group in self.current_graph.sinks.iter().filter_map(|s| s.group) {
+            if !storages.contains(&group) {
+                storages.push(group);
+            }
+        }
+        for group in storages {
+            resource_sink::show_group(
+                &mut self.current_graph.sinks,
+                group,
+                &mut self.commons,
+                ctx,
+                !error,
+            );
+        }
     }
 
     fn recipe_adding(&mut self, ui: &mut Ui) {
@@ -330,21 +943,30 @@ impl FactoryManagementApp {
             self.current_graph
                 .compound_recipes
                 .retain_mut(|compound_recipe| compound_recipe.show(&mut self.commons, ctx, !error));
+            self.current_graph.sources.retain_mut(|source| {
+                source.group.is_some() || source.show(&mut self.commons, ctx, !error)
+            });
+            self.current_graph.sinks.retain_mut(|sink| {
+                sink.group.is_some() || sink.show(&mut self.commons, ctx, !error)
+            });
+            self.groups_windows(ctx, error);
             self.current_graph
-                .sources
-                .retain_mut(|source| source.show(&mut self.commons, ctx, !error));
+                .splitters
+                .retain_mut(|splitter| splitter.show(&mut self.commons, ctx, !error));
             self.current_graph
-                .sinks
-                .retain_mut(|sink| sink.show(&mut self.commons, ctx, !error));
+                .mergers
+                .retain_mut(|merger| merger.show(&mut self.commons, ctx, !error));
 
             self.arrow_management(ui, ctx, error);
         });
     }
 
-    fn arrow_management(&mut self, _ui: &mut Ui, ctx: &Context, error: bool) {
+    fn arrow_management(&mut self, ui: &mut Ui, ctx: &Context, error: bool) {
         self.current_graph
             .arrows
             .retain_mut(|arrow| arrow.show(&mut self.commons, ctx, !error));
+        self.arrow_edition(ui, error);
+        self.overflow_sink_creation();
         if self.active_arrow.is_some() {
             let active = self
                 .active_arrow
@@ -352,34 +974,55 @@ impl FactoryManagementApp {
                 .unwrap()
                 .show(&mut self.commons, ctx, !error);
 
-            if active {
-                if self.commons.clicked_place_arrow_info.is_some() {
-                    let (resource, id, flow_index, recipe_type) =
-                        self.commons.clicked_place_arrow_info.as_ref().unwrap();
-                    let err = self.active_arrow.as_mut().unwrap().put_end(
-                        resource.clone(),
-                        *id,
-                        *recipe_type,
-                        *flow_index,
-                    );
-
-                    match err {
-                        Ok(_) => {
-                            //connect arrow
-                            self.current_graph
-                                .arrows
-                                .push(self.active_arrow.as_ref().unwrap().clone());
-                        }
-                        Err(e) => self.commons.add_error(ShowError::new(e.str())),
-                    };
-
-                    self.active_arrow = None;
-                    self.commons.arrow_active = false;
-                    self.commons.recalculate = true;
-                    self.commons.clicked_place_arrow_info = None;
+            if active && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.cancel_arrow();
+            } else if active {
+                let placed = match self.active_arrow.as_ref().unwrap().state {
+                    ArrowUsageState::ActiveStart => {
+                        self.commons.clicked_start_arrow_info.take().map(
+                            |(resource, id, _, flow_index, recipe_type)| {
+                                self.active_arrow.as_mut().unwrap().put_start(
+                                    resource,
+                                    id,
+                                    recipe_type,
+                                    flow_index,
+                                )
+                            },
+                        )
+                    }
+                    ArrowUsageState::Active | ArrowUsageState::Anchored => {
+                        self.commons.clicked_place_arrow_info.take().map(
+                            |(resource, id, flow_index, recipe_type)| {
+                                self.active_arrow.as_mut().unwrap().put_end(
+                                    resource,
+                                    id,
+                                    recipe_type,
+                                    flow_index,
+                                )
+                            },
+                        )
+                    }
+                };
+
+                match placed {
+                    None => {}
+                    Some(Ok(_)) => {
+                        //connect arrow
+                        self.current_graph
+                            .arrows
+                            .push(self.active_arrow.take().unwrap());
+                        self.moved_arrow = None;
+                        self.commons.arrow_active = false;
+                        self.commons.recalculate = true;
+                    }
+                    Some(Err(e)) => {
+                        self.commons.add_error(ShowError::new(e.str()));
+                        self.cancel_arrow();
+                    }
                 }
             } else {
                 self.active_arrow = None;
+                self.moved_arrow = None;
                 self.commons.arrow_active = false;
             }
         } else if self.commons.clicked_start_arrow_info.is_some() {
@@ -395,9 +1038,91 @@ impl FactoryManagementApp {
             self.commons.clicked_start_arrow_info = None;
         }
     }
+
+    ///Apply the changes asked from the menus of the anchored arrows
+    fn arrow_edition(&mut self, ui: &mut Ui, error: bool) {
+        let edits: Vec<(egui::Id, ArrowEdit)> = self
+            .current_graph
+            .arrows
+            .iter()
+            .filter_map(|arrow| {
+                arrow
+                    .interact(
+                        ui,
+                        !error,
+                        &self.current_graph.transport,
+                        &self.commons.time_settings,
+                    )
+                    .map(|edit| (arrow.id, edit))
+            })
+            .collect();
+
+        for (id, edit) in edits {
+            let position = match self.current_graph.arrows.iter().position(|a| a.id == id) {
+                None => continue,
+                Some(position) => position,
+            };
+            match edit {
+                ArrowEdit::Remove => {
+                    self.current_graph.arrows.remove(position);
+                }
+                ArrowEdit::Priority(priority) => {
+                    self.current_graph.arrows[position].priority = priority;
+                }
+                ArrowEdit::Overflow(overflow) => {
+                    self.current_graph.arrows[position].overflow = overflow;
+                }
+                ArrowEdit::Tier(tier) => {
+                    self.current_graph.arrows[position].tier = tier;
+                }
+                ArrowEdit::MoveStart | ArrowEdit::MoveEnd => {
+                    if self.active_arrow.is_some() {
+                        continue;
+                    }
+                    let arrow = self.current_graph.arrows.remove(position);
+                    let mut moved = arrow.clone();
+                    moved.detach(edit);
+                    self.moved_arrow = Some(arrow);
+                    self.active_arrow = Some(moved);
+                }
+            }
+            self.commons.recalculate = true;
+        }
+    }
+
+    ///Drop the active arrow, an arrow being moved goes back where it was
+    fn cancel_arrow(&mut self) {
+        self.active_arrow = None;
+        if let Some(arrow) = self.moved_arrow.take() {
+            self.current_graph.arrows.push(arrow);
+        }
+        self.commons.arrow_active = false;
+        self.commons.clicked_place_arrow_info = None;
+        self.commons.recalculate = true;
+    }
+
+    ///Create the overflow sink asked by an output and connect it
+    fn overflow_sink_creation(&mut self) {
+        if let Some((resource, id, layer, flow_index, recipe_type)) =
+            self.commons.overflow_sink_request.take()
+        {
+            let sink = ResourceSink::overflow_sink();
+            let mut arrow = ArrowFlow::new(resource.clone(), id, recipe_type, layer, flow_index);
+            match arrow.put_end(Some(resource), sink.id, RecipeWindowType::Sink, 0) {
+                Ok(_) => {
+                    self.current_graph.arrows.push(arrow);
+                    self.current_graph.sinks.push(sink);
+                }
+                Err(e) => self.commons.add_error(ShowError::new(e.str())),
+            }
+            self.commons.recalculate = true;
+        }
+    }
+
     fn compounds_recipes(&mut self, ui: &mut Ui) {
         if ui.button("Create Compound Recipe").clicked() {
-            let compound_graph = CompoundRecipeWindow::new(self.current_graph.clone());
+            let compound_graph =
+                CompoundRecipeWindow::new(self.current_graph.clone(), &self.commons.time_settings);
             self.current_graph = RecipeGraph::new();
             self.current_graph.compound_recipes.push(compound_graph);
         }
@@ -420,9 +1145,17 @@ impl eframe::App for FactoryManagementApp {
 
         self.central_panel(ctx, error);
 
+        if self.commons.check_arrows {
+            for e in self.current_graph.check_arrows() {
+                self.commons.add_error(e);
+            }
+            self.commons.check_arrows = false;
+        }
+
         if self.commons.recalculate {
             self.update_flows();
-            self.current_graph.calculate();
+            self.current_graph.calculate(&self.commons.time_settings);
+            self.register_resources();
             self.commons.recalculate = false;
         }
     }
@@ -469,31 +1202,32 @@ impl FactoryManagementApp {
 
                 if ui.button("Calculate").clicked() {
                     info!("Calculate button pressed");
-                    self.current_graph.calculate();
+                    self.current_graph.calculate(&self.commons.time_settings);
                 }
             });
         }
     }
 
     fn update_flows(&mut self) {
+        let settings = &self.commons.time_settings;
         for recipe in self.current_graph.simple_recipes.iter_mut() {
             recipe
                 .inner_recipe
-                .update_flow(Io::Input)
+                .update_flow(Io::Input, settings)
                 .expect("Update failure input");
             recipe
                 .inner_recipe
-                .update_flow(Io::Output)
+                .update_flow(Io::Output, settings)
                 .expect("Update failure output");
         }
         for recipe in self.current_graph.simple_recipes.iter_mut() {
             recipe
                 .inner_recipe
-                .update_flow(Io::Input)
+                .update_flow(Io::Input, settings)
                 .expect("Update failure input");
             recipe
                 .inner_recipe
-                .update_flow(Io::Output)
+                .update_flow(Io::Output, settings)
                 .expect("Update failure output");
         }
     }
@@ -1,11 +1,14 @@
 pub mod recipes_lists;
+pub mod resource_catalog;
 
 use crate::app::commons::recipes_lists::SavedRecipes;
+use crate::app::commons::resource_catalog::ResourceCatalog;
 use crate::app::coordinates_info::CoordinatesInfo;
 use crate::app::error::ShowError;
 use crate::app::recipe_window::base_recipe_window::RecipeWindowUser;
 use crate::app::recipe_window::simple_recipe_window::SimpleRecipeWindow;
 use crate::app::recipe_window::RecipeWindowType;
+use crate::app::resources::time_settings::TimeSettings;
 use crate::app::resources::ResourceDefinition;
 use egui::Context;
 use std::collections::{HashMap, VecDeque};
@@ -37,6 +40,20 @@ pub struct CommonsManager {
     #[serde(skip)]
     pub recalculate: bool,
 
+    /// A flow changed its resource, the arrows must be checked again
+    #[serde(skip)]
+    pub check_arrows: bool,
+
+    /// Output whose excess must be routed to a new overflow sink
+    #[serde(skip)]
+    pub overflow_sink_request: Option<(
+        ResourceDefinition,
+        egui::Id,
+        egui::LayerId,
+        usize,
+        RecipeWindowType,
+    )>,
+
     /// List of error popups to keep
     #[serde(skip)]
     pub show_errors: VecDeque<ShowError>,
@@ -47,6 +64,12 @@ pub struct CommonsManager {
 
     ///list of saved recipes
     pub saved_recipes: SavedRecipes,
+
+    ///resources of the project
+    pub resources: ResourceCatalog,
+
+    ///length of a tick and custom time units of the project, every rate conversion uses them
+    pub time_settings: TimeSettings,
 }
 
 impl CommonsManager {
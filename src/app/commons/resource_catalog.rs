@@ -0,0 +1,455 @@
+use crate::app::error::ShowError;
+use crate::app::resources::resource_flow::ResourceFlow;
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{RatePer, ResourceDefinition, Unit};
+use egui::Ui;
+use log::debug;
+
+///Colours given in turn to the new resources of the catalog
+const PALETTE: [[u8; 3]; 8] = [
+    [231, 76, 60],
+    [52, 152, 219],
+    [46, 204, 113],
+    [241, 196, 15],
+    [155, 89, 182],
+    [230, 126, 34],
+    [26, 188, 156],
+    [149, 165, 166],
+];
+
+///Maximum number of suggestions shown by the autocompletion
+const MAX_SUGGESTIONS: usize = 8;
+
+///Categories proposed before the user creates their own
+pub(crate) const DEFAULT_TAGS: [&str; 5] = ["Ore", "Intermediate", "Fluid", "Fuel", "Waste"];
+
+///Group of the resources without a tag
+pub(crate) const UNTAGGED: &str = "Untagged";
+
+///Stable identifier of a resource of the catalog, it survives renaming
+#[derive(serde::Deserialize, serde::Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceId(u64);
+
+///A resource known by the project
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    pub(crate) id: ResourceId,
+
+    ///display name, unique in the catalog without considering the case
+    pub(crate) name: String,
+
+    pub(crate) unit: Unit,
+
+    ///colour used to show the resource
+    pub(crate) color: [u8; 3],
+
+    ///categories of the resource, like ore or fluid
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+}
+
+impl CatalogEntry {
+    pub(crate) fn definition(&self) -> ResourceDefinition {
+        ResourceDefinition {
+            name: self.name.clone(),
+            unit: self.unit,
+        }
+    }
+
+    pub(crate) fn color32(&self) -> egui::Color32 {
+        let [r, g, b] = self.color;
+        egui::Color32::from_rgb(r, g, b)
+    }
+}
+
+///Flows of a category with their total
+#[derive(Debug, Clone)]
+pub(crate) struct FlowGroup {
+    pub(crate) category: String,
+
+    pub(crate) flows: Vec<ResourceFlow<f32, f32>>,
+
+    ///sum of the flows for each dimension, in its base unit
+    pub(crate) subtotals: Vec<(Unit, f32)>,
+
+    ///rate of the subtotals
+    pub(crate) rate: RatePer,
+}
+
+///Every resource of the project, so the same resource is always typed the same way
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ResourceCatalog {
+    entries: Vec<CatalogEntry>,
+    next_id: u64,
+}
+
+///Key of a name to compare them, the case and surrounding spaces are ignored
+fn name_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+impl ResourceCatalog {
+    pub(crate) fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    ///Entry of a name, whatever its case
+    pub(crate) fn find(&self, name: &str) -> Option<&CatalogEntry> {
+        let key = name_key(name);
+        self.entries
+            .iter()
+            .find(|entry| name_key(&entry.name) == key)
+    }
+
+    /// Definition of a resource typed by the user, the resource is added to the catalog if it's
+    /// new
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: name typed, "iron plate" gives the existing "Iron Plate"
+    /// * `unit`: unit of the flow, replaced by the unit of the catalog if they don't measure the
+    ///   same thing
+    ///
+    /// returns: ResourceDefinition
+    pub(crate) fn resolve(&mut self, name: &str, unit: Unit) -> ResourceDefinition {
+        if let Some(entry) = self.find(name) {
+            let mut resource = entry.definition();
+            if unit.dimension() == resource.unit.dimension() {
+                resource.unit = unit;
+            }
+            return resource;
+        }
+        let resource = ResourceDefinition {
+            name: name.trim().to_string(),
+            unit,
+        };
+        self.register(&resource);
+        resource
+    }
+
+    ///Add a resource met in a graph, nothing is done if it's already known
+    pub(crate) fn register(&mut self, resource: &ResourceDefinition) {
+        if self.find(&resource.name).is_none() && !resource.name.trim().is_empty() {
+            self.insert(resource.name.clone(), resource.unit);
+        }
+    }
+
+    fn insert(&mut self, name: String, unit: Unit) {
+        debug!("New resource in the catalog: {name}");
+        let id = ResourceId(self.next_id);
+        self.next_id += 1;
+        let color = PALETTE[self.entries.len() % PALETTE.len()];
+        self.entries.push(CatalogEntry {
+            id,
+            name,
+            unit,
+            color,
+            tags: Vec::new(),
+        });
+    }
+
+    /// Rename a resource of the catalog
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: the resource to rename
+    /// * `name`: its new name, no other resource can have it
+    ///
+    /// returns: the old and new definitions of the resource, to update the graphs
+    pub(crate) fn rename(
+        &mut self,
+        id: ResourceId,
+        name: &str,
+    ) -> Result<(ResourceDefinition, ResourceDefinition), ShowError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ShowError::new("A resource needs a name".to_string()));
+        }
+        if let Some(other) = self.find(name) {
+            if other.id != id {
+                return Err(ShowError::new(format!(
+                    "The resource {} already exists",
+                    other.name
+                )));
+            }
+        }
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| ShowError::new("Unknown resource".to_string()))?;
+        let old = entry.definition();
+        entry.name = name.to_string();
+        Ok((old, entry.definition()))
+    }
+
+    /// Change the unit new flows of a resource are created with
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: the resource
+    /// * `unit`: its new unit, measuring the same thing as the old one
+    ///
+    /// returns: ()
+    pub(crate) fn set_unit(&mut self, id: ResourceId, unit: Unit) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+            if entry.unit.dimension() == unit.dimension() {
+                entry.unit = unit;
+            }
+        }
+    }
+
+    /// Add a category to a resource or remove it
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: the resource
+    /// * `tag`: the category, its case is ignored
+    /// * `tagged`: the resource must have the category
+    ///
+    /// returns: ()
+    pub(crate) fn set_tag(&mut self, id: ResourceId, tag: &str, tagged: bool) {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return;
+        }
+        let tag = self
+            .tags()
+            .into_iter()
+            .find(|known| name_key(known) == name_key(tag))
+            .unwrap_or_else(|| tag.to_string());
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+            entry.tags.retain(|t| *t != tag);
+            if tagged {
+                entry.tags.push(tag);
+                entry.tags.sort();
+            }
+        }
+    }
+
+    ///Every category, the default ones and the ones given to resources
+    pub(crate) fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect();
+        for tag in self.entries.iter().flat_map(|entry| entry.tags.iter()) {
+            if !tags.iter().any(|known| name_key(known) == name_key(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+
+    /// Sort flows by the categories of their resources, a resource with many categories is in
+    /// each of their groups
+    ///
+    /// # Arguments
+    ///
+    /// * `flows`: the flows to sort
+    /// * `filter`: only the group of this category is kept if given
+    /// * `settings`: the time settings the subtotals are converted with
+    ///
+    /// returns: the groups in the order of the categories, the untagged flows last
+    pub(crate) fn group_flows(
+        &self,
+        flows: &[ResourceFlow<f32, f32>],
+        filter: Option<&str>,
+        settings: &TimeSettings,
+    ) -> Vec<FlowGroup> {
+        let mut categories = self.tags();
+        categories.push(UNTAGGED.to_string());
+        categories
+            .into_iter()
+            .filter(|category| filter.map_or(true, |f| f == category))
+            .filter_map(|category| {
+                let flows: Vec<ResourceFlow<f32, f32>> = flows
+                    .iter()
+                    .filter(|flow| {
+                        let tags = self
+                            .find(&flow.resource.name)
+                            .map(|entry| entry.tags.as_slice())
+                            .unwrap_or_default();
+                        match category == UNTAGGED {
+                            true => tags.is_empty(),
+                            false => tags.contains(&category),
+                        }
+                    })
+                    .cloned()
+                    .collect();
+                (!flows.is_empty()).then(|| Self::subtotal(category, flows, settings))
+            })
+            .collect()
+    }
+
+    ///Group of flows with their sums, the sums are in the longest rate of the flows
+    fn subtotal(
+        category: String,
+        flows: Vec<ResourceFlow<f32, f32>>,
+        settings: &TimeSettings,
+    ) -> FlowGroup {
+        let rate = flows
+            .iter()
+            .map(|flow| flow.rate)
+            .min_by(|a, b| a.per_hour(settings).total_cmp(&b.per_hour(settings)))
+            .unwrap_or(RatePer::Minute);
+        let mut subtotals: Vec<(Unit, f32)> = Vec::new();
+        for flow in flows.iter() {
+            let unit = flow.resource.unit.dimension().base_unit();
+            let amount = flow.amount_in(unit, rate, settings).unwrap_or_default();
+            match subtotals.iter_mut().find(|(u, _)| *u == unit) {
+                Some((_, total)) => *total += amount,
+                None => subtotals.push((unit, amount)),
+            }
+        }
+        subtotals.sort_by_key(|(unit, _)| unit.dimension());
+        FlowGroup {
+            category,
+            flows,
+            subtotals,
+            rate,
+        }
+    }
+
+    ///Entries whose name contains the text typed, the ones starting with it first
+    pub(crate) fn suggestions(&self, text: &str) -> Vec<&CatalogEntry> {
+        let key = name_key(text);
+        let mut suggestions: Vec<&CatalogEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| name_key(&entry.name).contains(&key))
+            .collect();
+        suggestions.sort_by_key(|entry| !name_key(&entry.name).starts_with(&key));
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+
+    /// Text edit of a resource name proposing the resources of the catalog
+    ///
+    /// # Arguments
+    ///
+    /// * `ui`: where to show the edit
+    /// * `name`: name being typed, replaced by the suggestion picked
+    /// * `hint`: hint shown while the edit is empty
+    ///
+    /// returns: the response of the text edit
+    pub(crate) fn autocomplete(
+        &self,
+        ui: &mut Ui,
+        name: &mut String,
+        hint: &str,
+    ) -> egui::Response {
+        let response = egui::TextEdit::singleline(name)
+            .hint_text(hint)
+            .show(ui)
+            .response;
+        let popup_id = response.id.with("Suggestions");
+        let suggestions = self.suggestions(name);
+        let exact = suggestions.len() == 1 && suggestions[0].name == *name;
+        if response.has_focus() && !suggestions.is_empty() && !exact {
+            ui.memory_mut(|m| m.open_popup(popup_id));
+        }
+        egui::popup::popup_below_widget(ui, popup_id, &response, |ui| {
+            for entry in suggestions {
+                let text = egui::RichText::new(format!("⏺ {}", entry.name)).color(entry.color32());
+                if ui.selectable_label(false, text).clicked() {
+                    *name = entry.name.clone();
+                }
+            }
+        });
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::commons::resource_catalog::{ResourceCatalog, UNTAGGED};
+    use crate::app::resources::resource_flow::ResourceFlow;
+    use crate::app::resources::time_settings::TimeSettings;
+    use crate::app::resources::{RatePer, Unit};
+    use crate::utils::test_env;
+
+    //-------------------Tests-------------------
+
+    #[test]
+    fn test_resolve() {
+        test_env::setup();
+        let mut catalog = ResourceCatalog::default();
+        let plate = catalog.resolve("Iron Plate", Unit::Piece);
+        let same = catalog.resolve(" iron plate", Unit::Kg);
+        assert_eq!(plate, same);
+        assert_eq!(same.name, "Iron Plate");
+        assert_eq!(same.unit, Unit::Piece);
+        assert_eq!(catalog.entries().len(), 1);
+        let stacks = catalog.resolve("iron plate", Unit::Stack(100));
+        assert_eq!(stacks.name, "Iron Plate");
+        assert_eq!(stacks.unit, Unit::Stack(100));
+
+        catalog.resolve("Copper Plate", Unit::Piece);
+        let suggestions: Vec<&str> = catalog
+            .suggestions("PLATE")
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(suggestions, vec!["Iron Plate", "Copper Plate"]);
+        let suggestions = catalog.suggestions("cop");
+        assert_eq!(suggestions[0].name, "Copper Plate");
+    }
+
+    #[test]
+    fn test_rename() {
+        test_env::setup();
+        let mut catalog = ResourceCatalog::default();
+        catalog.resolve("Iron Plate", Unit::Piece);
+        let copper = catalog.find("Copper").map(|entry| entry.id);
+        assert!(copper.is_none());
+        catalog.resolve("Copper", Unit::Piece);
+        let id = catalog.find("copper").unwrap().id;
+
+        assert!(catalog.rename(id, "iron plate").is_err());
+        assert!(catalog.rename(id, " ").is_err());
+        let (old, new) = catalog.rename(id, "Copper Plate").unwrap();
+        assert_eq!(old.name, "Copper");
+        assert_eq!(new.name, "Copper Plate");
+        assert_eq!(catalog.find("copper plate").unwrap().id, id);
+    }
+
+    #[test]
+    fn test_group_flows() {
+        test_env::setup();
+        let mut catalog = ResourceCatalog::default();
+        let ore = catalog.resolve("Iron Ore", Unit::Piece);
+        let water = catalog.resolve("Water", Unit::Liter);
+        let slag = catalog.resolve("Slag", Unit::Piece);
+        let plate = catalog.resolve("Iron Plate", Unit::Piece);
+        let id = |catalog: &ResourceCatalog, name: &str| catalog.find(name).unwrap().id;
+        catalog.set_tag(id(&catalog, "Iron Ore"), "ore", true);
+        catalog.set_tag(id(&catalog, "Water"), "Fluid", true);
+        catalog.set_tag(id(&catalog, "Slag"), "Ore", true);
+        catalog.set_tag(id(&catalog, "Slag"), "Slag waste", true);
+        assert_eq!(catalog.find("Iron Ore").unwrap().tags, vec!["Ore"]);
+        assert!(catalog.tags().contains(&"Slag waste".to_string()));
+
+        let mut buckets = water.clone();
+        buckets.unit = Unit::Bucket;
+        let flows = vec![
+            ResourceFlow::new(&ore, 1.0, 10.0, RatePer::Minute),
+            ResourceFlow::new(&slag, 1.0, 2.0, RatePer::Minute),
+            ResourceFlow::new(&water, 1.0, 500.0, RatePer::Minute),
+            ResourceFlow::new(&buckets, 1.0, 1.0, RatePer::Minute),
+            ResourceFlow::new(&plate, 1.0, 5.0, RatePer::Minute),
+        ];
+        let settings = TimeSettings::default();
+        let groups = catalog.group_flows(&flows, None, &settings);
+        let categories: Vec<&str> = groups.iter().map(|g| g.category.as_str()).collect();
+        assert_eq!(categories, vec!["Ore", "Fluid", "Slag waste", UNTAGGED]);
+        assert_eq!(groups[0].subtotals, vec![(Unit::Piece, 12.0)]);
+        assert_eq!(groups[1].subtotals, vec![(Unit::Liter, 1500.0)]);
+        assert_eq!(groups[3].flows[0].resource, plate);
+
+        let groups = catalog.group_flows(&flows, Some("Fluid"), &settings);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].flows.len(), 2);
+
+        catalog.set_tag(id(&catalog, "Slag"), "ORE", false);
+        assert_eq!(catalog.find("Slag").unwrap().tags, vec!["Slag waste"]);
+    }
+}
@@ -1,17 +1,56 @@
+use crate::app::error::ShowError;
 use crate::app::recipe_window::arrow_flow::ArrowFlow;
 use crate::app::recipe_window::base_recipe_window::RecipeWindowUser;
 use crate::app::recipe_window::compound_recipe_window::CompoundRecipeWindow;
+use crate::app::recipe_window::resource_merger::ResourceMerger;
 use crate::app::recipe_window::resource_sink::ResourceSink;
+use crate::app::recipe_window::resource_splitter::{ResourceSplitter, SplitMode};
 use crate::app::recipe_window::resources_sources::ResourceSource;
 use crate::app::recipe_window::simple_recipe_window::SimpleRecipeWindow;
 use crate::app::recipe_window::RecipeWindowType;
 use crate::app::resources::recipe_input_resource::RecipeInputResource;
 use crate::app::resources::recipe_output_resource::RecipeOutputResource;
 use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
-use crate::app::resources::ManageFlow;
-use log::{debug, error, info, trace};
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::transport::TransportLibrary;
+use crate::app::resources::{ManageFlow, RatePer, ResourceDefinition};
+use crate::utils::rational::Rational;
+use crate::utils::Io;
+use eframe::emath::Numeric;
+use linear_system::{LinearSystem, Scalar, SolverStatus};
+use log::{debug, error, info, trace, warn};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::LinkedList;
+use std::fmt::{Display, Formatter};
+use strongly_connected::{is_loop, strongly_connected_components};
+
+pub(crate) mod linear_system;
+mod strongly_connected;
+
+///The way the flows of a graph are calculated
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum CalculationMode {
+    ///Single forward pass from the sources followed by a back propagation
+    #[default]
+    Propagation,
+
+    ///Steady state rates solved as a linear system, every resource balances at every node
+    Linear,
+
+    ///Planned backwards from the targets of the sinks down to the sources
+    Target,
+}
+
+impl Display for CalculationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalculationMode::Propagation => write!(f, "Propagation"),
+            CalculationMode::Linear => write!(f, "Linear system"),
+            CalculationMode::Target => write!(f, "Target"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub(crate) struct RecipeGraph {
@@ -20,6 +59,38 @@ pub(crate) struct RecipeGraph {
     pub sources: Vec<ResourceSource>,
     pub sinks: Vec<ResourceSink>,
     pub arrows: Vec<ArrowFlow>,
+
+    ///nodes dividing a flow between several outputs
+    #[serde(default)]
+    pub splitters: Vec<ResourceSplitter>,
+
+    ///nodes joining several flows in one
+    #[serde(default)]
+    pub mergers: Vec<ResourceMerger>,
+
+    ///calculation mode used
+    #[serde(default)]
+    pub mode: CalculationMode,
+
+    ///linear mode only, solve the machine counts with exact rationals, the flows stay floats
+    #[serde(default)]
+    pub exact: bool,
+
+    ///the exact machine counts overflowed at the last calculation, they were solved with floats
+    #[serde(skip)]
+    pub exact_overflow: bool,
+
+    ///transport tiers the arrows can use
+    #[serde(default)]
+    pub transport: TransportLibrary,
+
+    ///status of the last linear calculation
+    #[serde(skip)]
+    pub solver_status: SolverStatus,
+
+    ///titles of the recipes of every loop found by the last calculation
+    #[serde(skip)]
+    pub loops: Vec<Vec<String>>,
 }
 
 impl RecipeGraph {
@@ -30,6 +101,356 @@ impl RecipeGraph {
             sources: vec![],
             sinks: vec![],
             arrows: vec![],
+            splitters: vec![],
+            mergers: vec![],
+            mode: CalculationMode::default(),
+            exact: false,
+            exact_overflow: false,
+            transport: TransportLibrary::default(),
+            solver_status: SolverStatus::default(),
+            loops: vec![],
+        }
+    }
+
+    ///Every output of the recipes creating more than what is taken, with the recipe title
+    pub(crate) fn excess_flows(
+        &self,
+        settings: &TimeSettings,
+    ) -> Vec<(String, ResourceFlow<f32, f32>)> {
+        let recipes = self
+            .simple_recipes
+            .iter()
+            .map(|recipe| &recipe.inner_recipe)
+            .chain(
+                self.compound_recipes
+                    .iter()
+                    .map(|recipe| &recipe.inner_recipe),
+            );
+        let mut excess = vec![];
+        for recipe in recipes {
+            for index in 0..recipe.outputs.len() {
+                if let Some(flow) = recipe.output_excess(index, settings) {
+                    excess.push((recipe.get_title(), flow));
+                }
+            }
+        }
+        excess
+    }
+
+    ///Total power in watts used by the simple recipes of the graph
+    pub(crate) fn power_usage(&self, settings: &TimeSettings) -> f32 {
+        self.simple_recipes
+            .iter()
+            .map(|recipe| recipe.inner_recipe.power_usage(settings))
+            .sum()
+    }
+
+    ///Total power in watts made by the generators of the graph
+    pub(crate) fn power_generation(&self, settings: &TimeSettings) -> f32 {
+        self.simple_recipes
+            .iter()
+            .map(|recipe| recipe.inner_recipe.power_generation(settings))
+            .sum()
+    }
+
+    ///Indicate the graph has generators that don't cover the power used
+    pub(crate) fn power_deficit(&self, settings: &TimeSettings) -> bool {
+        let has_generators = self
+            .simple_recipes
+            .iter()
+            .any(|recipe| recipe.inner_recipe.power_balance_per_machine(settings) > 0.0);
+        has_generators
+            && self.power_usage(settings) > self.power_generation(settings) * (1.0 + 1e-4)
+    }
+
+    ///Indicate a rate is still used by a flow, a target, a limit or a transport tier of the graph
+    pub(crate) fn uses_rate(&self, rate: RatePer) -> bool {
+        self.rates().contains(&rate)
+    }
+
+    ///Every rate of the flows, targets, limits and transport tiers of the graph, the graphs of the
+    /// compound recipes included. A rate can appear many times.
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates: Vec<RatePer> = self
+            .simple_recipes
+            .iter()
+            .flat_map(|recipe| recipe.inner_recipe.rates())
+            .collect();
+        rates.extend(
+            self.compound_recipes
+                .iter()
+                .flat_map(CompoundRecipeWindow::rates),
+        );
+        rates.extend(self.sources.iter().flat_map(ResourceSource::rates));
+        rates.extend(self.sinks.iter().flat_map(ResourceSink::rates));
+        rates.extend(self.splitters.iter().flat_map(ResourceSplitter::rates));
+        rates.extend(self.mergers.iter().flat_map(ResourceMerger::rates));
+        rates.extend(
+            self.arrows
+                .iter()
+                .filter_map(|arrow| arrow.flow.as_ref().map(|flow| flow.rate)),
+        );
+        rates.extend(self.transport.tiers().iter().map(|tier| tier.rate));
+        rates
+    }
+
+    ///Every resource used by the graph, a resource can appear many times
+    pub(crate) fn resources(&self) -> Vec<ResourceDefinition> {
+        let mut resources: Vec<ResourceDefinition> = self
+            .simple_recipes
+            .iter()
+            .flat_map(|recipe| recipe.inner_recipe.resources())
+            .collect();
+        resources.extend(
+            self.compound_recipes
+                .iter()
+                .flat_map(|recipe| recipe.inner_recipe.resources()),
+        );
+        resources.extend(self.sources.iter().map(|source| source.output.resource()));
+        resources.extend(
+            self.sinks
+                .iter()
+                .filter_map(|sink| sink.sink.as_ref().map(|sink| sink.resource())),
+        );
+        resources
+    }
+
+    /// Replace a resource by another everywhere in the graph, when a resource is renamed
+    ///
+    /// # Arguments
+    ///
+    /// * `old`: the resource replaced
+    /// * `new`: the replacing resource
+    ///
+    /// returns: ()
+    pub(crate) fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        info!("Rename the resource {old} into {new}");
+        for recipe in self.simple_recipes.iter_mut() {
+            recipe.inner_recipe.rename_resource(old, new);
+        }
+        for recipe in self.compound_recipes.iter_mut() {
+            recipe.rename_resource(old, new);
+        }
+        for source in self.sources.iter_mut() {
+            source.output.rename_resource(old, new);
+        }
+        for sink in self.sinks.iter_mut() {
+            if let Some(sink) = sink.sink.as_mut() {
+                sink.rename_resource(old, new);
+            }
+        }
+        for splitter in self.splitters.iter_mut() {
+            splitter.rename_resource(old, new);
+        }
+        for merger in self.mergers.iter_mut() {
+            merger.rename_resource(old, new);
+        }
+        for arrow in self.arrows.iter_mut() {
+            arrow.resource.rename(old, new);
+        }
+    }
+
+    /// Check the arrows again after flows changed their resource, or were removed or flipped. An
+    /// arrow takes the resource of the flow it starts from, and it's removed if its end can't take
+    /// that resource or if one of its flows is gone.
+    ///
+    /// returns: the errors telling the user which arrows were removed
+    pub(crate) fn check_arrows(&mut self) -> Vec<ShowError> {
+        let mut errors = Vec::new();
+        let removed = self.sync_arrows();
+        if removed > 0 {
+            errors.push(ShowError::new(format!(
+                "{removed} arrow(s) lost their flow, removed or flipped, and have been removed"
+            )));
+        }
+        for mut arrow in std::mem::take(&mut self.arrows) {
+            if let Some(start) = self.start_resource(&arrow) {
+                arrow.resource = start;
+            }
+            if let Some(end) = self.end_resource(&arrow) {
+                if !end.is_compatible(&arrow.resource) {
+                    error!("Arrow of {} into {} removed", arrow.resource, end);
+                    errors.push(ShowError::new(format!(
+                        "{} can't flow into an input of {} anymore, the arrow between them has \
+                        been removed",
+                        arrow.resource, end
+                    )));
+                    continue;
+                }
+            }
+            self.arrows.push(arrow);
+        }
+        errors
+    }
+
+    /// Find the flows of the arrows from their ids, so the arrows follow the flows moved in
+    /// their recipes. The arrows of old saves get the ids of the flows at their indices, and the
+    /// arrows whose flow is gone are removed.
+    ///
+    /// returns: the number of arrows removed
+    pub(crate) fn sync_arrows(&mut self) -> usize {
+        let mut arrows = std::mem::take(&mut self.arrows);
+        let count = arrows.len();
+        arrows.retain_mut(|arrow| {
+            let start = self.recipe_flows(
+                arrow.start_flow_type,
+                Some(arrow.start_flow_window),
+                &Io::Output,
+            );
+            let end = match arrow.end_flow_type {
+                Some(end_type) => self.recipe_flows(end_type, arrow.end_flow_window, &Io::Input),
+                None => None,
+            };
+            let kept = follow_flow(&mut arrow.start_flow_id, &mut arrow.start_flow_index, start)
+                && follow_flow(&mut arrow.end_flow_id, &mut arrow.end_flow_index, end)
+                && self.port_exists(
+                    arrow.start_flow_type,
+                    Some(arrow.start_flow_window),
+                    arrow.start_flow_index,
+                    &Io::Output,
+                )
+                && arrow.end_flow_type.map_or(true, |end_type| {
+                    self.port_exists(
+                        end_type,
+                        arrow.end_flow_window,
+                        arrow.end_flow_index,
+                        &Io::Input,
+                    )
+                });
+            if !kept {
+                debug!("Arrow of {} removed with its flow", arrow.resource);
+            }
+            kept
+        });
+        self.arrows = arrows;
+        count - self.arrows.len()
+    }
+
+    /// Check a splitter or a merger still has the input or output an arrow is connected to
+    ///
+    /// # Arguments
+    ///
+    /// * `window_type`: type of the window
+    /// * `window`: id of the window
+    /// * `index`: index of the input or output
+    /// * `dir`: an input or an output
+    ///
+    /// returns: `bool` flag, true for the other windows
+    fn port_exists(
+        &self,
+        window_type: RecipeWindowType,
+        window: Option<egui::Id>,
+        index: usize,
+        dir: &Io,
+    ) -> bool {
+        let ports = match (window_type, dir) {
+            (RecipeWindowType::Splitter, Io::Output) => self
+                .splitters
+                .iter()
+                .find(|splitter| Some(splitter.id) == window)
+                .map(|splitter| splitter.outputs.len()),
+            (RecipeWindowType::Merger, Io::Input) => self
+                .mergers
+                .iter()
+                .find(|merger| Some(merger.id) == window)
+                .map(|merger| merger.inputs.len()),
+            _ => None,
+        };
+        ports.map_or(true, |ports| index < ports)
+    }
+
+    /// Inputs or outputs of a recipe of the graph
+    ///
+    /// # Arguments
+    ///
+    /// * `window_type`: type of the window
+    /// * `window`: id of the window
+    /// * `dir`: the inputs or the outputs
+    ///
+    /// returns: None for the sources and sinks, which have a single flow, or if the window is gone
+    fn recipe_flows(
+        &self,
+        window_type: RecipeWindowType,
+        window: Option<egui::Id>,
+        dir: &Io,
+    ) -> Option<&[ManageFlow<f32>]> {
+        let window = window?;
+        let recipe = match window_type {
+            RecipeWindowType::SimpleRecipe => {
+                &self
+                    .simple_recipes
+                    .iter()
+                    .find(|recipe| recipe.inner_recipe.id == window)?
+                    .inner_recipe
+            }
+            RecipeWindowType::CompoundRecipe => {
+                &self
+                    .compound_recipes
+                    .iter()
+                    .find(|recipe| recipe.inner_recipe.id == window)?
+                    .inner_recipe
+            }
+            RecipeWindowType::Source
+            | RecipeWindowType::Sink
+            | RecipeWindowType::Splitter
+            | RecipeWindowType::Merger => return None,
+        };
+        match dir {
+            Io::Input => Some(&recipe.inputs),
+            Io::Output => Some(&recipe.outputs),
+        }
+    }
+
+    ///Resource of the flow an arrow starts from, None if the flow doesn't exist
+    fn start_resource(&mut self, arrow: &ArrowFlow) -> Option<ResourceDefinition> {
+        let (flow_index, window_type, window_index) = self.get_start_point(arrow);
+        let window_index = window_index?;
+        match window_type {
+            RecipeWindowType::SimpleRecipe => self.simple_recipes[window_index]
+                .inner_recipe
+                .outputs
+                .get(flow_index)
+                .map(ManageFlow::resource),
+            RecipeWindowType::CompoundRecipe => self.compound_recipes[window_index]
+                .inner_recipe
+                .outputs
+                .get(flow_index)
+                .map(ManageFlow::resource),
+            RecipeWindowType::Source => Some(self.sources[window_index].output.resource()),
+            RecipeWindowType::Sink => None,
+            RecipeWindowType::Splitter => self.splitters[window_index]
+                .outputs
+                .get(flow_index)
+                .map(|output| output.resource()),
+            RecipeWindowType::Merger => Some(self.mergers[window_index].output.resource()),
+        }
+    }
+
+    ///Resource of the flow an arrow ends in, None if the flow doesn't exist or takes anything
+    fn end_resource(&mut self, arrow: &ArrowFlow) -> Option<ResourceDefinition> {
+        let (flow_index, window_type, window_index) = self.get_endpoint(arrow);
+        let window_index = window_index?;
+        match window_type {
+            RecipeWindowType::SimpleRecipe => self.simple_recipes[window_index]
+                .inner_recipe
+                .inputs
+                .get(flow_index)
+                .map(ManageFlow::resource),
+            RecipeWindowType::CompoundRecipe => self.compound_recipes[window_index]
+                .inner_recipe
+                .inputs
+                .get(flow_index)
+                .map(ManageFlow::resource),
+            RecipeWindowType::Sink => self.sinks[window_index]
+                .sink
+                .as_ref()
+                .map(|sink| sink.resource()),
+            RecipeWindowType::Source => None,
+            RecipeWindowType::Splitter => Some(self.splitters[window_index].input.resource()),
+            RecipeWindowType::Merger => self.mergers[window_index]
+                .inputs
+                .get(flow_index)
+                .map(|input| input.resource()),
         }
     }
 
@@ -37,132 +458,466 @@ impl RecipeGraph {
         self.simple_recipes.clear();
         self.sources.clear();
         self.sinks.clear();
+        self.splitters.clear();
+        self.mergers.clear();
         self.arrows.clear();
     }
 
-    pub fn calculate(&mut self) {
+    pub fn calculate(&mut self, settings: &TimeSettings) {
         info!("==================Calculate==================");
+        self.sync_arrows();
 
         self.reset_flows();
+        for recipe in self.simple_recipes.iter_mut() {
+            recipe.inner_recipe.exact_machines = None;
+        }
+        for arrow in self.arrows.iter_mut() {
+            arrow.flow = None;
+        }
+
+        let helpers = self.arrow_helpers();
+        self.find_loops(&helpers);
+        self.solver_status = SolverStatus::NotSolved;
+        self.exact_overflow = false;
 
-        let mut calculate_helper = self.make_helpers();
+        match self.mode {
+            CalculationMode::Propagation | CalculationMode::Target if !self.loops.is_empty() => {
+                info!("The graph has loops, they are solved as a linear system");
+                self.linear_calculation(settings);
+            }
+            CalculationMode::Propagation => {
+                self.junctions_demand(&helpers, settings);
+                let mut calculate_helper = self.make_helpers();
 
-        self.perform_calculation(&mut calculate_helper);
+                self.perform_calculation(&mut calculate_helper, settings);
 
-        self.back_propagation(&mut calculate_helper);
+                self.back_propagation(&mut calculate_helper, settings);
+            }
+            CalculationMode::Linear => self.linear_calculation(settings),
+            CalculationMode::Target => self.target_calculation(&helpers, settings),
+        }
+        self.fill_arrow_flows(&helpers, settings);
+        self.arrows_load(&helpers, settings);
+        self.check_capacities(settings);
+        self.check_source_limits(settings);
+
+        let deficit = self.power_deficit(settings);
+        if deficit {
+            error!("The generators don't make enough power");
+        }
+        for recipe in self.simple_recipes.iter_mut() {
+            recipe.inner_recipe.stable_power = !deficit;
+        }
     }
 
     fn make_helpers(&mut self) -> LinkedList<FlowCalculatorType> {
+        let helpers = self.arrow_helpers();
+        let order = self.topological_order(&helpers);
+
         let mut sources_helpers = LinkedList::new();
         let mut sinks_helpers = LinkedList::new();
-        let mut simple_recipes_helpers = vec![(0, LinkedList::new()); self.simple_recipes.len()];
-        let mut compound_recipes_helpers =
-            vec![(0, LinkedList::new()); self.compound_recipes.len()];
+        //helpers ending at every node with its rank, the sources and sinks ones stay empty
+        let mut nodes_helpers: Vec<(usize, LinkedList<FlowCalculatorType>)> = order
+            .iter()
+            .map(|rank| (*rank, LinkedList::new()))
+            .collect();
 
         trace!("build relationships from arrows");
-        for arrow in self.arrows.clone().iter() {
-            let (start_flow_index, start_type, start_window_index) = self.get_start_point(arrow);
+        for helper in helpers {
+            match helper.start_type {
+                RecipeWindowType::Source => {
+                    sources_helpers.push_back(FlowCalculatorType::Helper(helper));
+                }
+                RecipeWindowType::Sink => {
+                    error!("Starting an arrow flow at a sink, this isn't normal")
+                }
+                _ => match helper.end_type {
+                    RecipeWindowType::Source => {
+                        error!("Ending an arrow flow at a source, this doesn't shouldn't happen")
+                    }
+                    RecipeWindowType::Sink => {
+                        sinks_helpers.push_back(FlowCalculatorType::Helper(helper))
+                    }
+                    _ => {
+                        let node = self.node_number(helper.end_type, helper.end_window_index);
+                        nodes_helpers[node]
+                            .1
+                            .push_back(FlowCalculatorType::Helper(helper));
+                    }
+                },
+            }
+        }
 
-            let (end_flow_index, end_type, end_window_index) = self.get_endpoint(arrow);
+        self.concatenate_helpers(sources_helpers, sinks_helpers, nodes_helpers)
+    }
 
-            if let Some(start_window_index) = start_window_index {
-                if let Some(end_window_index) = end_window_index {
-                    let helper = FlowCalculatorHelper {
-                        start_window_index,
-                        start_flow_index,
-                        start_type,
-                        end_window_index,
-                        end_flow_index,
-                        end_type,
-                    };
+    ///Number of a window among all the nodes of the graph: simple recipes, compound recipes,
+    /// sources, sinks, splitters then mergers
+    fn node_number(&self, window_type: RecipeWindowType, index: usize) -> usize {
+        let simple = self.simple_recipes.len();
+        let compound = simple + self.compound_recipes.len();
+        let sources = compound + self.sources.len();
+        let sinks = sources + self.sinks.len();
+        let splitters = sinks + self.splitters.len();
+        match window_type {
+            RecipeWindowType::SimpleRecipe => index,
+            RecipeWindowType::CompoundRecipe => simple + index,
+            RecipeWindowType::Source => compound + index,
+            RecipeWindowType::Sink => sources + index,
+            RecipeWindowType::Splitter => sinks + index,
+            RecipeWindowType::Merger => splitters + index,
+        }
+    }
 
-                    match start_type {
-                        RecipeWindowType::SimpleRecipe => {
-                            let source_order = simple_recipes_helpers[start_window_index].0;
-                            Self::connect_resources_helpers_ends(
-                                &mut simple_recipes_helpers,
-                                &mut compound_recipes_helpers,
-                                &mut sinks_helpers,
-                                end_type,
-                                end_window_index,
-                                helper,
-                                source_order,
-                            );
-                        }
-                        RecipeWindowType::Source => {
-                            sources_helpers.push_back(FlowCalculatorType::Helper(helper));
-                        }
-                        RecipeWindowType::Sink => {
-                            error!("Starting an arrow flow at a sink, this isn't normal")
-                        }
-                        RecipeWindowType::CompoundRecipe => {
-                            let source_order = compound_recipes_helpers[start_window_index].0;
-                            Self::connect_resources_helpers_ends(
-                                &mut simple_recipes_helpers,
-                                &mut compound_recipes_helpers,
-                                &mut sinks_helpers,
-                                end_type,
-                                end_window_index,
-                                helper,
-                                source_order,
-                            );
-                        }
+    ///Type and index of the window of a node, the opposite of `node_number`
+    fn node_window(&self, node: usize) -> (RecipeWindowType, usize) {
+        let simple = self.simple_recipes.len();
+        let compound = simple + self.compound_recipes.len();
+        let sources = compound + self.sources.len();
+        let sinks = sources + self.sinks.len();
+        let splitters = sinks + self.splitters.len();
+        if node < simple {
+            (RecipeWindowType::SimpleRecipe, node)
+        } else if node < compound {
+            (RecipeWindowType::CompoundRecipe, node - simple)
+        } else if node < sources {
+            (RecipeWindowType::Source, node - compound)
+        } else if node < sinks {
+            (RecipeWindowType::Sink, node - sources)
+        } else if node < splitters {
+            (RecipeWindowType::Splitter, node - sinks)
+        } else {
+            (RecipeWindowType::Merger, node - splitters)
+        }
+    }
+
+    ///Number of nodes of the graph
+    fn nodes_count(&self) -> usize {
+        self.simple_recipes.len()
+            + self.compound_recipes.len()
+            + self.sources.len()
+            + self.sinks.len()
+            + self.splitters.len()
+            + self.mergers.len()
+    }
+
+    ///List for every node the nodes it feeds
+    fn adjacency(&self, helpers: &[FlowCalculatorHelper]) -> Vec<Vec<usize>> {
+        let nodes = self.nodes_count();
+        let mut adjacency = vec![vec![]; nodes];
+        for h in helpers.iter() {
+            let start = self.node_number(h.start_type, h.start_window_index);
+            let end = self.node_number(h.end_type, h.end_window_index);
+            if !adjacency[start].contains(&end) {
+                adjacency[start].push(end);
+            }
+        }
+        adjacency
+    }
+
+    ///Rank of every node, a node always comes after the nodes feeding it unless they form a loop
+    fn topological_order(&self, helpers: &[FlowCalculatorHelper]) -> Vec<usize> {
+        let adjacency = self.adjacency(helpers);
+        let mut order = vec![0; adjacency.len()];
+        for (rank, component) in strongly_connected_components(&adjacency).iter().enumerate() {
+            for node in component.iter() {
+                order[*node] = rank;
+            }
+        }
+        order
+    }
+
+    ///Find the loops of the graph and flag the recipes that are part of one
+    fn find_loops(&mut self, helpers: &[FlowCalculatorHelper]) {
+        let adjacency = self.adjacency(helpers);
+        let simple = self.simple_recipes.len();
+        let compound = simple + self.compound_recipes.len();
+
+        for recipe in self.simple_recipes.iter_mut() {
+            recipe.inner_recipe.in_loop = false;
+        }
+        for recipe in self.compound_recipes.iter_mut() {
+            recipe.inner_recipe.in_loop = false;
+        }
+        self.loops.clear();
+
+        for component in strongly_connected_components(&adjacency) {
+            if !is_loop(&component, &adjacency) {
+                continue;
+            }
+            let mut titles = vec![];
+            for node in component {
+                let recipe = if node < simple {
+                    &mut self.simple_recipes[node].inner_recipe
+                } else if node < compound {
+                    &mut self.compound_recipes[node - simple].inner_recipe
+                } else {
+                    match self.node_window(node).0 {
+                        //the flows going round can pass through them
+                        RecipeWindowType::Splitter | RecipeWindowType::Merger => {}
+                        _ => error!("Only recipes, splitters and mergers can be part of a loop"),
                     }
-                }
+                    continue;
+                };
+                recipe.in_loop = true;
+                titles.push(recipe.get_title());
+            }
+            info!("Loop found: {}", titles.join(" → "));
+            self.loops.push(titles);
+        }
+    }
+
+    /// Give their flow to the arrows the calculation didn't set one. An arrow alone at one of its
+    /// ends carries the whole flow of that end, the flow of the others isn't known.
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph
+    /// * `settings`: time settings the amounts are converted with
+    fn fill_arrow_flows(&mut self, helpers: &[FlowCalculatorHelper], settings: &TimeSettings) {
+        for helper in helpers.iter() {
+            if self.arrows[helper.arrow_index].flow.is_some() {
+                continue;
+            }
+            let end = (
+                helper.end_type,
+                helper.end_window_index,
+                helper.end_flow_index,
+            );
+            let start = (
+                helper.start_type,
+                helper.start_window_index,
+                helper.start_flow_index,
+            );
+            let flow = if arrows_into(helpers, end).len() == 1 {
+                self.end_flow(helper).map(|input| input.total_in(settings))
+            } else if arrows_from(helpers, start).len() == 1 {
+                self.start_flow(helper)
+                    .map(|output| output.total_out(settings))
+            } else {
+                None
+            };
+            self.arrows[helper.arrow_index].flow = flow;
+        }
+    }
+
+    /// Find how loaded every arrow is: its share of the largest flow of the graph, and whether the
+    /// input at its end gets less than it needs
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph
+    /// * `settings`: time settings the amounts are converted with
+    fn arrows_load(&mut self, helpers: &[FlowCalculatorHelper], settings: &TimeSettings) {
+        let largest = self
+            .arrows
+            .iter()
+            .filter_map(|arrow| {
+                arrow
+                    .flow
+                    .as_ref()
+                    .map(|flow| hourly_amount(flow, settings))
+            })
+            .fold(0.0, f64::max);
+        for arrow in self.arrows.iter_mut() {
+            arrow.throughput = match (&arrow.flow, largest > 0.0) {
+                (Some(flow), true) => (hourly_amount(flow, settings) / largest) as f32,
+                _ => 0.0,
+            };
+            arrow.under_supplied = false;
+            arrow.starved = false;
+        }
+        for helper in helpers.iter() {
+            let under_supplied = self
+                .end_flow(helper)
+                .map_or(false, |input| !input.is_enough(settings));
+            let shared = arrows_from(
+                helpers,
+                (
+                    helper.start_type,
+                    helper.start_window_index,
+                    helper.start_flow_index,
+                ),
+            )
+            .len()
+                > 1;
+            let demand = self.end_demand(helper, settings);
+            let arrow = &mut self.arrows[helper.arrow_index];
+            let carried = arrow
+                .flow
+                .as_ref()
+                .map_or(0.0, |flow| hourly_amount(flow, settings));
+            arrow.under_supplied = under_supplied;
+            arrow.starved = shared && carried < demand * (1.0 - 1e-3);
+        }
+    }
+
+    ///Find how many lanes of its transport tier every arrow needs for its flow
+    fn check_capacities(&mut self, settings: &TimeSettings) {
+        for arrow in self.arrows.iter_mut() {
+            arrow.lanes = None;
+            let tier = match arrow.tier.and_then(|id| self.transport.get(id)) {
+                None => continue,
+                Some(tier) => tier,
+            };
+            if !tier.carries(arrow.resource.unit) {
+                warn!("{} can't carry {}", tier.name, arrow.resource);
+                continue;
+            }
+            let amount = arrow
+                .flow
+                .as_ref()
+                .map_or(0.0, |flow| hourly_amount(flow, settings));
+            let lanes = tier.lanes(amount, settings);
+            if lanes > 1 {
+                warn!(
+                    "Flow of {} above the capacity of {}, {lanes} lanes needed",
+                    arrow.resource, tier.name
+                );
             }
+            arrow.lanes = Some((tier.name.clone(), lanes));
         }
+    }
 
-        Self::concatenate_helpers(
-            sources_helpers,
-            sinks_helpers,
-            simple_recipes_helpers,
-            compound_recipes_helpers,
-        )
+    ///Arrows carrying more than one lane of their transport tier
+    pub(crate) fn over_capacity_arrows(&self) -> Vec<&ArrowFlow> {
+        self.arrows
+            .iter()
+            .filter(|arrow| arrow.over_capacity())
+            .collect()
     }
 
-    fn connect_resources_helpers_ends(
-        simple_recipes_helpers: &mut [(usize, LinkedList<FlowCalculatorType>)],
-        compound_recipes_helpers: &mut [(usize, LinkedList<FlowCalculatorType>)],
-        sinks_helpers: &mut LinkedList<FlowCalculatorType>,
-        end_type: RecipeWindowType,
-        end_window_index: usize,
-        helper: FlowCalculatorHelper,
-        source_order: usize,
-    ) {
-        match end_type {
+    ///Output an arrow starts from
+    fn start_flow(&self, helper: &FlowCalculatorHelper) -> Option<&RecipeOutputResource<f32>> {
+        let flows = match helper.start_type {
             RecipeWindowType::SimpleRecipe => {
-                let mut end_order = simple_recipes_helpers[end_window_index].0;
-
-                //if the source is higher than end
-                if source_order >= end_order {
-                    //change the end order
-                    end_order = source_order + 1usize;
-                }
+                &self.simple_recipes[helper.start_window_index]
+                    .inner_recipe
+                    .outputs
+            }
+            RecipeWindowType::CompoundRecipe => {
+                &self.compound_recipes[helper.start_window_index]
+                    .inner_recipe
+                    .outputs
+            }
+            RecipeWindowType::Source => {
+                return Some(&self.sources[helper.start_window_index].output)
+            }
+            RecipeWindowType::Sink => return None,
+            RecipeWindowType::Splitter => {
+                return self.splitters[helper.start_window_index]
+                    .outputs
+                    .get(helper.start_flow_index)
+            }
+            RecipeWindowType::Merger => {
+                return Some(&self.mergers[helper.start_window_index].output)
+            }
+        };
+        match flows.get(helper.start_flow_index) {
+            Some(ManageFlow::RecipeOutput(output)) => Some(output),
+            _ => None,
+        }
+    }
 
-                //add the helper to the end point
-                simple_recipes_helpers[end_window_index]
-                    .1
-                    .push_back(FlowCalculatorType::Helper(helper));
-                //add order to the list
-                simple_recipes_helpers[end_window_index].0 = end_order;
+    ///Input an arrow ends in
+    fn end_flow(&self, helper: &FlowCalculatorHelper) -> Option<&RecipeInputResource<f32>> {
+        let flows = match helper.end_type {
+            RecipeWindowType::SimpleRecipe => {
+                &self.simple_recipes[helper.end_window_index]
+                    .inner_recipe
+                    .inputs
             }
             RecipeWindowType::CompoundRecipe => {
-                let mut end_order: usize = compound_recipes_helpers[end_window_index].0;
+                &self.compound_recipes[helper.end_window_index]
+                    .inner_recipe
+                    .inputs
+            }
+            RecipeWindowType::Sink => return self.sinks[helper.end_window_index].sink.as_ref(),
+            RecipeWindowType::Source => return None,
+            RecipeWindowType::Splitter => {
+                return Some(&self.splitters[helper.end_window_index].input)
+            }
+            RecipeWindowType::Merger => {
+                return self.mergers[helper.end_window_index]
+                    .inputs
+                    .get(helper.end_flow_index)
+            }
+        };
+        match flows.get(helper.end_flow_index) {
+            Some(ManageFlow::RecipeInput(input)) => Some(input),
+            _ => None,
+        }
+    }
 
-                if source_order >= end_order {
-                    end_order = source_order + 1usize;
+    /// Input an arrow ends in, to push a flow in it
+    ///
+    /// # Arguments
+    ///
+    /// * `helper`: the arrow
+    /// * `needed`: flow needed by a sink without input yet
+    ///
+    /// returns: the input, None if the arrow doesn't end in an input
+    fn end_flow_mut(
+        &mut self,
+        helper: &FlowCalculatorHelper,
+        needed: &ResourceFlow<f32, f32>,
+    ) -> Option<&mut RecipeInputResource<f32>> {
+        match helper.end_type {
+            RecipeWindowType::SimpleRecipe => {
+                match &mut self.simple_recipes[helper.end_window_index]
+                    .inner_recipe
+                    .inputs[helper.end_flow_index]
+                {
+                    ManageFlow::RecipeInput(i) => Some(i),
+                    ManageFlow::RecipeOutput(_) => None,
                 }
-
-                compound_recipes_helpers[end_window_index]
-                    .1
-                    .push_back(FlowCalculatorType::Helper(helper));
-                compound_recipes_helpers[end_window_index].0 = end_order;
             }
-            RecipeWindowType::Source => {
-                error!("Ending an arrow flow at a source, this doesn't shouldn't happen")
+            RecipeWindowType::CompoundRecipe => {
+                match &mut self.compound_recipes[helper.end_window_index]
+                    .inner_recipe
+                    .inputs[helper.end_flow_index]
+                {
+                    ManageFlow::RecipeInput(i) => Some(i),
+                    ManageFlow::RecipeOutput(_) => None,
+                }
+            }
+            RecipeWindowType::Sink => {
+                let sink = &mut self.sinks[helper.end_window_index];
+                Some(sink.sink.get_or_insert_with(|| {
+                    RecipeInputResource::new(needed.resource.clone(), needed.clone())
+                }))
+            }
+            RecipeWindowType::Source => None,
+            RecipeWindowType::Splitter => Some(&mut self.splitters[helper.end_window_index].input),
+            RecipeWindowType::Merger => self.mergers[helper.end_window_index]
+                .inputs
+                .get_mut(helper.end_flow_index),
+        }
+    }
+
+    ///Build a helper for every arrow with both ends found in the graph
+    fn arrow_helpers(&mut self) -> Vec<FlowCalculatorHelper> {
+        let mut helpers = vec![];
+        for (arrow_index, arrow) in self.arrows.clone().iter().enumerate() {
+            let (start_flow_index, start_type, start_window_index) = self.get_start_point(arrow);
+
+            let (end_flow_index, end_type, end_window_index) = self.get_endpoint(arrow);
+
+            if let (Some(start_window_index), Some(end_window_index)) =
+                (start_window_index, end_window_index)
+            {
+                helpers.push(FlowCalculatorHelper {
+                    arrow_index,
+                    start_window_index,
+                    start_flow_index,
+                    start_type,
+                    end_window_index,
+                    end_flow_index,
+                    end_type,
+                });
             }
-            RecipeWindowType::Sink => sinks_helpers.push_back(FlowCalculatorType::Helper(helper)),
         }
+        helpers
     }
 
     fn get_endpoint(&mut self, arrow: &ArrowFlow) -> (usize, RecipeWindowType, Option<usize>) {
@@ -182,6 +937,11 @@ impl RecipeGraph {
                 .compound_recipes
                 .iter()
                 .position(|recipe| recipe.inner_recipe.id == end_id),
+            RecipeWindowType::Splitter => self
+                .splitters
+                .iter()
+                .position(|splitter| splitter.id == end_id),
+            RecipeWindowType::Merger => self.mergers.iter().position(|merger| merger.id == end_id),
         };
         (end_flow_index, end_type, end_window_index)
     }
@@ -207,36 +967,35 @@ impl RecipeGraph {
                 .compound_recipes
                 .iter()
                 .position(|recipe| recipe.inner_recipe.id == start_id),
+            RecipeWindowType::Splitter => self
+                .splitters
+                .iter()
+                .position(|splitter| splitter.id == start_id),
+            RecipeWindowType::Merger => {
+                self.mergers.iter().position(|merger| merger.id == start_id)
+            }
         };
         (source_flow_index, source_type, source_window_index)
     }
 
     fn concatenate_helpers(
+        &self,
         sources_helpers: LinkedList<FlowCalculatorType>,
         mut sinks_helpers: LinkedList<FlowCalculatorType>,
-        mut recipes_helpers: Vec<(usize, LinkedList<FlowCalculatorType>)>,
-        mut compound_recipes_helpers: Vec<(usize, LinkedList<FlowCalculatorType>)>,
+        mut nodes_helpers: Vec<(usize, LinkedList<FlowCalculatorType>)>,
     ) -> LinkedList<FlowCalculatorType> {
         trace!("Concatenate helpers");
 
         let mut calculate_helper = sources_helpers;
 
-        for (i, (_, list)) in recipes_helpers.iter_mut().enumerate() {
-            list.push_back(FlowCalculatorType::EndRecipe(
-                i,
-                RecipeWindowType::SimpleRecipe,
-            ));
-        }
-        for (i, (_, list)) in compound_recipes_helpers.iter_mut().enumerate() {
-            list.push_back(FlowCalculatorType::EndRecipe(
-                i,
-                RecipeWindowType::CompoundRecipe,
-            ));
+        for (node, (_, list)) in nodes_helpers.iter_mut().enumerate() {
+            match self.node_window(node) {
+                (RecipeWindowType::Source | RecipeWindowType::Sink, _) => {}
+                (window_type, i) => list.push_back(FlowCalculatorType::EndRecipe(i, window_type)),
+            }
         }
-        recipes_helpers.append(&mut compound_recipes_helpers);
-        recipes_helpers.sort_by(|helper1, helper2| helper1.0.partial_cmp(&helper2.0).unwrap());
-        println!("Hey! {recipes_helpers:?}");
-        for (_i, (_, list)) in recipes_helpers.iter_mut().enumerate() {
+        nodes_helpers.sort_by(|helper1, helper2| helper1.0.partial_cmp(&helper2.0).unwrap());
+        for (_, list) in nodes_helpers.iter_mut() {
             calculate_helper.append(list);
         }
         calculate_helper.append(&mut sinks_helpers);
@@ -271,216 +1030,296 @@ impl RecipeGraph {
                 f.reset();
             }
         }
+
+        for splitter in self.splitters.iter_mut() {
+            splitter.reset();
+        }
+        for merger in self.mergers.iter_mut() {
+            merger.reset();
+        }
         trace!("Done resetting!")
     }
 
     ///Perform the calculation from the calculate helpers
-    fn perform_calculation(&mut self, calculate_helper: &mut LinkedList<FlowCalculatorType>) {
+    fn perform_calculation(
+        &mut self,
+        calculate_helper: &mut LinkedList<FlowCalculatorType>,
+        settings: &TimeSettings,
+    ) {
         trace!("Perform Calculation");
+        let helpers: Vec<FlowCalculatorHelper> = calculate_helper
+            .iter()
+            .filter_map(|calculate_helper| match calculate_helper {
+                FlowCalculatorType::Helper(h) => Some(*h),
+                FlowCalculatorType::EndRecipe(_, _) => None,
+            })
+            .collect();
         for calculate_helper in calculate_helper.iter_mut() {
             match calculate_helper {
-                FlowCalculatorType::Helper(h) => match h.start_type {
-                    RecipeWindowType::SimpleRecipe => {
-                        let source = &mut self.simple_recipes[h.start_window_index];
-                        let source_flow = &mut source.inner_recipe.outputs[h.start_flow_index];
-                        match source_flow {
-                            ManageFlow::RecipeInput(_) => {
-                                error!("Source flows shouldn't be a RecipeInput")
-                            }
-                            ManageFlow::RecipeOutput(o) => {
-                                //source is simple recipe
-                                let used_flow = o.created.clone();
-                                let added_source = o.add_out_flow(used_flow.clone());
-
-                                let end_flow = match h.end_type {
-                                    RecipeWindowType::SimpleRecipe => {
-                                        let end = &mut self.simple_recipes[h.end_window_index];
-                                        let f = match &mut end.inner_recipe.inputs[h.end_flow_index]
-                                        {
-                                            ManageFlow::RecipeInput(r) => r,
-                                            ManageFlow::RecipeOutput(_) => {
-                                                panic!("Never happens")
-                                            }
-                                        };
-                                        Some(f)
-                                    }
-                                    RecipeWindowType::Source => None,
-                                    RecipeWindowType::Sink => {
-                                        let end = &mut self.sinks[h.end_window_index];
-                                        let f = end.sink.as_mut();
-                                        match f {
-                                            None => {
-                                                let f = RecipeInputResource::new(
-                                                    used_flow.resource.clone(),
-                                                    used_flow.clone(),
-                                                );
-                                                end.sink = Some(f);
-                                                let f = end.sink.as_mut().unwrap();
-                                                Some(f)
-                                            }
-                                            Some(f) => Some(f),
-                                        }
-                                    }
-                                    RecipeWindowType::CompoundRecipe => {
-                                        let end = &mut self.compound_recipes[h.end_window_index];
-                                        let f = match &mut end.inner_recipe.inputs[h.end_flow_index]
-                                        {
-                                            ManageFlow::RecipeInput(r) => r,
-                                            ManageFlow::RecipeOutput(_) => {
-                                                panic!("Never happens")
-                                            }
-                                        };
-                                        Some(f)
-                                    }
+                FlowCalculatorType::Helper(h) => {
+                    let created = self
+                        .start_flow(h)
+                        .map_or(0.0, |output| hourly_amount(&output.created, settings));
+                    let allocated = self.allocated(&helpers, h, created, settings);
+                    let part = match created > 0.0 {
+                        true => (allocated / created) as f32,
+                        false => 0.0,
+                    };
+                    match h.start_type {
+                        RecipeWindowType::SimpleRecipe | RecipeWindowType::CompoundRecipe => {
+                            let outputs = match h.start_type {
+                                RecipeWindowType::SimpleRecipe => {
+                                    &mut self.simple_recipes[h.start_window_index]
+                                        .inner_recipe
+                                        .outputs
+                                }
+                                _ => {
+                                    &mut self.compound_recipes[h.start_window_index]
+                                        .inner_recipe
+                                        .outputs
                                 }
-                                .unwrap();
+                            };
+                            let (used_flow, added_source) = match &mut outputs[h.start_flow_index] {
+                                ManageFlow::RecipeInput(_) => {
+                                    error!("Source flows shouldn't be a RecipeInput");
+                                    continue;
+                                }
+                                ManageFlow::RecipeOutput(o) => {
+                                    //the arrows from the same output share what it creates
+                                    let used_flow = o.created.clone() * part;
+                                    let added_source = o.add_out_flow(used_flow.clone());
+                                    (used_flow, added_source)
+                                }
+                            };
 
-                                let added_input = end_flow.add_in_flow(used_flow);
+                            self.arrows[h.arrow_index].flow = Some(used_flow.clone());
+                            let added_input = match self.end_flow_mut(h, &used_flow) {
+                                None => false,
+                                Some(end_flow) => end_flow.add_in_flow(used_flow),
+                            };
 
-                                if !(added_source && added_input) {
-                                    error!("added_source:{added_source} added_inputs{added_input}");
-                                }
+                            if !(added_source && added_input) {
+                                error!("added_source:{added_source} added_inputs{added_input}");
                             }
                         }
-                    }
-                    RecipeWindowType::Source => {
-                        //source is a source
-                        let source = &mut self.sources[h.start_window_index];
-                        let end_flow = match h.end_type {
-                            RecipeWindowType::SimpleRecipe => {
-                                let end = &mut self.simple_recipes[h.end_window_index];
-                                let f = match &mut end.inner_recipe.inputs[h.end_flow_index] {
-                                    ManageFlow::RecipeInput(r) => r,
-                                    ManageFlow::RecipeOutput(_) => {
-                                        panic!("Never happens")
-                                    }
-                                };
-                                Some(f)
-                            }
-                            RecipeWindowType::Source => None,
-                            RecipeWindowType::Sink => {
-                                let end = &mut self.sinks[h.end_window_index];
-                                let f = end.sink.as_mut().unwrap();
-                                Some(f)
-                            }
-                            RecipeWindowType::CompoundRecipe => {
-                                let end = &mut self.compound_recipes[h.end_window_index];
-                                let f = match &mut end.inner_recipe.inputs[h.end_flow_index] {
-                                    ManageFlow::RecipeInput(r) => r,
-                                    ManageFlow::RecipeOutput(_) => {
-                                        panic!("Never happens")
-                                    }
-                                };
-                                Some(f)
-                            }
-                        };
-
-                        //connect the end to the source when the source is a source
-                        if let Some(end_flow) = end_flow {
-                            if source.limited_output {
-                                let resource = source.output.created.resource.clone();
-                                let used_flow = ResourceFlow::new(
-                                    &resource,
-                                    1,
-                                    source.limit_amount,
+                        RecipeWindowType::Source => {
+                            //source is a source
+                            let limit = self.sources[h.start_window_index].hourly_limit(settings);
+                            let limited = match limit > 0.0 {
+                                true => {
+                                    (self.allocated(&helpers, h, limit, settings) / limit) as f32
+                                }
+                                false => 1.0,
+                            };
+                            let source = &self.sources[h.start_window_index];
+                            //the arrows from a limited source share its limit
+                            let limited_flow = source.limited_output.then(|| {
+                                ResourceFlow::new(
+                                    &source.output.created.resource,
+                                    1.0,
+                                    source.limit_amount * limited,
                                     source.limit_rate,
-                                );
-                                add_flows(&mut source.output, end_flow, used_flow);
-                            } else {
-                                let used_flow = end_flow.needed.clone();
-                                debug!("Flow used for output of a source {}", used_flow);
-                                add_flows(&mut source.output, end_flow, used_flow);
+                                )
+                            });
+                            let resource = source.output.resource();
+                            let end_flow = match self.end_flow_mut(h, &hourly_flow(&resource, 0.0))
+                            {
+                                None => continue,
+                                Some(end_flow) => end_flow,
+                            };
+
+                            //connect the end to the source when the source is a source
+                            let used_flow = match limited_flow {
+                                Some(used_flow) => used_flow,
+                                None => {
+                                    let used_flow = end_flow.needed.clone();
+                                    debug!("Flow used for output of a source {}", used_flow);
+                                    used_flow
+                                }
+                            };
+                            let added_input = end_flow.add_in_flow(used_flow.clone());
+                            let added_source = self.sources[h.start_window_index]
+                                .output
+                                .add_out_flow(used_flow.clone());
+                            self.arrows[h.arrow_index].flow = Some(used_flow);
+
+                            if !(added_source && added_input) {
+                                error!("added_source:{added_source} added_inputs{added_input}");
                             }
                         }
+                        RecipeWindowType::Sink => {}
+                        RecipeWindowType::Splitter | RecipeWindowType::Merger => {
+                            self.apply_arrow_flow(h, allocated, settings);
+                        }
                     }
-                    RecipeWindowType::Sink => {}
-                    RecipeWindowType::CompoundRecipe => {
-                        let source = &mut self.compound_recipes[h.start_window_index];
-                        let source_flow = &mut source.inner_recipe.outputs[h.start_flow_index];
-                        match source_flow {
-                            ManageFlow::RecipeInput(_) => {
-                                error!("Source flow shouldn't be a RecipeInput")
-                            }
-                            ManageFlow::RecipeOutput(o) => {
-                                let used_flow = o.created.clone();
-                                let added_source = o.add_out_flow(used_flow.clone());
-
-                                let end_flow = match h.end_type {
-                                    RecipeWindowType::SimpleRecipe => {
-                                        let end = &mut self.simple_recipes[h.end_window_index];
-                                        let f = match &mut end.inner_recipe.inputs[h.end_flow_index]
-                                        {
-                                            ManageFlow::RecipeInput(r) => r,
-                                            ManageFlow::RecipeOutput(_) => {
-                                                panic!("Never happens")
-                                            }
-                                        };
-                                        Some(f)
-                                    }
-                                    RecipeWindowType::Source => None,
-                                    RecipeWindowType::Sink => {
-                                        let end = &mut self.sinks[h.end_window_index];
-                                        let f = end.sink.as_mut();
-                                        match f {
-                                            None => {
-                                                let f = RecipeInputResource::new(
-                                                    used_flow.resource.clone(),
-                                                    used_flow.clone(),
-                                                );
-                                                end.sink = Some(f);
-                                                let f = end.sink.as_mut().unwrap();
-                                                Some(f)
-                                            }
-                                            Some(f) => Some(f),
-                                        }
-                                    }
-                                    RecipeWindowType::CompoundRecipe => {
-                                        let end = &mut self.compound_recipes[h.end_window_index];
-                                        let f = match &mut end.inner_recipe.inputs[h.end_flow_index]
-                                        {
-                                            ManageFlow::RecipeInput(r) => r,
-                                            ManageFlow::RecipeOutput(_) => {
-                                                panic!("Never happens")
-                                            }
-                                        };
-                                        Some(f)
-                                    }
-                                }
-                                .unwrap();
-                                let added_input = end_flow.add_in_flow(used_flow);
-
-                                if !(added_source && added_input) {
-                                    error!("added_source:{added_source} added_inputs{added_input}");
-                                }
-                            }
-                        }
-                    }
-                },
+                }
                 FlowCalculatorType::EndRecipe(i, recipe_type) => match recipe_type {
                     RecipeWindowType::SimpleRecipe => {
-                        self.simple_recipes[*i].internal_calculation();
+                        self.simple_recipes[*i].internal_calculation(settings);
                     }
                     RecipeWindowType::CompoundRecipe => {
-                        self.compound_recipes[*i].internal_calculation();
+                        self.compound_recipes[*i].internal_calculation(settings);
                     }
                     RecipeWindowType::Source => {}
                     RecipeWindowType::Sink => {}
+                    RecipeWindowType::Splitter => {
+                        let demands = self.splitter_demands(*i, &helpers, settings);
+                        self.splitters[*i].internal_calculation(&demands, settings);
+                    }
+                    RecipeWindowType::Merger => self.mergers[*i].internal_calculation(settings),
                 },
             }
         }
     }
 
+    /// Part of what an output supplies going through an arrow, the arrows from the output are
+    /// served by priority and the overflow arrows get what is left
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph
+    /// * `helper`: the arrow
+    /// * `supply`: amount the output supplies per hour in base units
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: the amount carried by the arrow per hour in base units
+    fn allocated(
+        &self,
+        helpers: &[FlowCalculatorHelper],
+        helper: &FlowCalculatorHelper,
+        supply: f64,
+        settings: &TimeSettings,
+    ) -> f64 {
+        let siblings = arrows_from(
+            helpers,
+            (
+                helper.start_type,
+                helper.start_window_index,
+                helper.start_flow_index,
+            ),
+        );
+        let claims: Vec<Claim> = siblings
+            .iter()
+            .map(|a| {
+                let arrow = &self.arrows[helpers[*a].arrow_index];
+                Claim {
+                    priority: arrow.priority,
+                    overflow: arrow.overflow,
+                    demand: self.end_demand(&helpers[*a], settings),
+                }
+            })
+            .collect();
+        let parts = allocate(supply, &claims);
+        siblings
+            .iter()
+            .position(|a| helpers[*a].arrow_index == helper.arrow_index)
+            .map_or(supply, |position| parts[position])
+    }
+
+    /// Amount the arrows from every output of a splitter need
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: index of the splitter
+    /// * `helpers`: the arrows of the graph
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: the demand of every output per hour in base units, None for the outputs without
+    /// arrow
+    fn splitter_demands(
+        &self,
+        index: usize,
+        helpers: &[FlowCalculatorHelper],
+        settings: &TimeSettings,
+    ) -> Vec<Option<f64>> {
+        (0..self.splitters[index].outputs.len())
+            .map(|output| {
+                let arrows = arrows_from(helpers, (RecipeWindowType::Splitter, index, output));
+                match arrows.is_empty() {
+                    true => None,
+                    false => Some(
+                        arrows
+                            .iter()
+                            .map(|a| self.end_demand(&helpers[*a], settings))
+                            .sum(),
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    ///Amount needed at the end of an arrow per hour in base units, a sink without target needs
+    /// nothing
+    fn end_demand(&self, helper: &FlowCalculatorHelper, settings: &TimeSettings) -> f64 {
+        match helper.end_type {
+            RecipeWindowType::Sink => {
+                let sink = &self.sinks[helper.end_window_index];
+                match sink.targeted {
+                    true => sink.hourly_target(settings),
+                    false => 0.0,
+                }
+            }
+            _ => self
+                .end_flow(helper)
+                .map_or(0.0, |input| hourly_amount(&input.needed, settings)),
+        }
+    }
+
+    /// Ask the splitters and mergers what the nodes after them need, so the sources feeding them
+    /// know how much to deliver. The nodes are walked from the sinks to the sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: ()
+    fn junctions_demand(&mut self, helpers: &[FlowCalculatorHelper], settings: &TimeSettings) {
+        let components = strongly_connected_components(&self.adjacency(helpers));
+        for node in components.iter().rev().flatten() {
+            match self.node_window(*node) {
+                (RecipeWindowType::Splitter, index) => {
+                    let demands = self.splitter_demands(index, helpers, settings);
+                    let splitter = &mut self.splitters[index];
+                    let needed = splitter.needed_for(&demands);
+                    splitter.input.needed = hourly_flow(&splitter.resource(), needed);
+                }
+                (RecipeWindowType::Merger, index) => {
+                    let demand: f64 = arrows_from(helpers, (RecipeWindowType::Merger, index, 0))
+                        .iter()
+                        .map(|a| self.end_demand(&helpers[*a], settings))
+                        .sum();
+                    let connected: Vec<usize> = (0..self.mergers[index].inputs.len())
+                        .filter(|input| {
+                            !arrows_into(helpers, (RecipeWindowType::Merger, index, *input))
+                                .is_empty()
+                        })
+                        .collect();
+                    let merger = &mut self.mergers[index];
+                    let resource = merger.resource();
+                    for input in connected.iter() {
+                        merger.inputs[*input].needed =
+                            hourly_flow(&resource, demand / connected.len() as f64);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Get the rate to limit a flow in back propagation according to the end point of a helper
     ///
     /// # Arguments
     ///
     /// * `helper`: the helper to read
+    /// * `settings`: time settings the flows are compared with
     ///
     /// returns: rate of limitation, optional amount information
     fn get_back_rate(
         &mut self,
         helper: FlowCalculatorType,
-    ) -> (f32, Option<ResourceFlow<usize, f32>>) {
+        settings: &TimeSettings,
+    ) -> (f32, Option<ResourceFlow<f32, f32>>) {
         match helper {
             FlowCalculatorType::Helper(h) => match h.end_type {
                 RecipeWindowType::SimpleRecipe => {
@@ -492,15 +1331,16 @@ impl RecipeGraph {
                             debug!(
                                 "get back rate Resource: {}\n inputs! {}{}\n outputs! {}{}",
                                 i.resource().name,
-                                i.total_in().amount,
-                                i.total_in().rate.to_shortened_string(),
-                                i.total_out().amount,
-                                i.total_out().rate.to_shortened_string()
+                                i.total_in(settings).amount,
+                                i.total_in(settings).rate.to_shortened_string(settings),
+                                i.total_out(settings).amount,
+                                i.total_out(settings).rate.to_shortened_string(settings)
                             );
-                            let rate_res = i.total_out() / i.total_in();
+                            let rate_res =
+                                i.total_out(settings).ratio(&i.total_in(settings), settings);
                             match rate_res {
                                 Ok(rate) => {
-                                    return (rate, Some(i.total_out()));
+                                    return (rate, Some(i.total_out(settings)));
                                 }
                                 Err(error) => {
                                     error!("Flow back propagation can't be calculated due to a '{}' on rate calculation", error.str())
@@ -518,9 +1358,10 @@ impl RecipeGraph {
 
                     match end_flow {
                         ManageFlow::RecipeInput(i) => {
-                            if i.is_more_than_enough() {
-                                let rate = i.total_out().amount / i.total_in().amount;
-                                return (rate, Some(i.total_out() * rate));
+                            if i.is_more_than_enough(settings) {
+                                let rate =
+                                    i.total_out(settings).amount / i.total_in(settings).amount;
+                                return (rate, Some(i.total_out(settings) * rate));
                             }
                         }
                         ManageFlow::RecipeOutput(_) => {
@@ -531,14 +1372,18 @@ impl RecipeGraph {
                 RecipeWindowType::Source => {
                     error!("End of a flow shouldn't be a Source")
                 }
-                RecipeWindowType::Sink => {}
+                RecipeWindowType::Sink | RecipeWindowType::Splitter | RecipeWindowType::Merger => {}
             },
             FlowCalculatorType::EndRecipe(_, _) => {}
         };
         (1.0, None)
     }
 
-    fn back_propagation(&mut self, calculate_helper: &mut LinkedList<FlowCalculatorType>) {
+    fn back_propagation(
+        &mut self,
+        calculate_helper: &mut LinkedList<FlowCalculatorType>,
+        settings: &TimeSettings,
+    ) {
         debug!("Back propagation");
         //reverse
         let mut reverse_helper = LinkedList::new();
@@ -551,7 +1396,7 @@ impl RecipeGraph {
         }
 
         for helper in reverse_helper {
-            let (rate, flow) = self.get_back_rate(helper);
+            let (rate, flow) = self.get_back_rate(helper, settings);
             debug!(
                 "Rate of back propagation {}, associated flow={:?}",
                 rate, flow
@@ -563,62 +1408,869 @@ impl RecipeGraph {
                 FlowCalculatorType::Helper(h) => match h.start_type {
                     RecipeWindowType::SimpleRecipe => {
                         let start = &mut self.simple_recipes[h.start_window_index];
-                        start.back_propagation_internal_calculation(rate, flow)
+                        start.back_propagation_internal_calculation(rate, flow, settings)
                     }
                     RecipeWindowType::CompoundRecipe => {
                         let start = &mut self.compound_recipes[h.start_window_index];
-                        start.back_propagation_internal_calculation(rate, flow)
+                        start.back_propagation_internal_calculation(rate, flow, settings)
                     }
                     RecipeWindowType::Source => {
                         let start = &mut self.sources[h.start_window_index];
                         if let Some(flow) = flow {
-                            if flow < start.output.total_out() {
+                            if flow.compare(&start.output.total_out(settings), settings)
+                                == Some(Ordering::Less)
+                            {
                                 debug!("source flow back propagated {}", flow);
                                 start.output.reset();
+                                self.arrows[h.arrow_index].flow = Some(flow.clone());
                                 start.output.add_out_flow(flow);
                             }
                         } else {
                             panic!("Hey! you need a flow to set it")
                         }
                     }
-                    RecipeWindowType::Sink => {}
+                    RecipeWindowType::Sink
+                    | RecipeWindowType::Splitter
+                    | RecipeWindowType::Merger => {}
                 },
                 FlowCalculatorType::EndRecipe(_, _) => {}
             }
         }
     }
+
+    /// Solve the graph as a linear system. The variables are the flow of every arrow and the
+    /// run-rate of every recipe, relative to its designed rate. Every connected input and output
+    /// of a recipe must balance with its arrows, and a limited source must deliver its limit.
+    fn linear_calculation(&mut self, settings: &TimeSettings) {
+        trace!("[START] linear calculation");
+        self.reset_designed_flows(settings);
+
+        let helpers = self.arrow_helpers();
+        let arrows_count = helpers.len();
+        let simple_count = self.simple_recipes.len();
+        let recipes_count = simple_count + self.compound_recipes.len();
+
+        let mut defaults = vec![0.0; arrows_count];
+        defaults.append(&mut vec![1.0; recipes_count]);
+        let exact = match self.exact {
+            true => {
+                let defaults: Vec<Rational> =
+                    defaults.iter().copied().map(Rational::from_f64).collect();
+                let solution = self
+                    .linear_system::<Rational>(&helpers, settings)
+                    .solve(&defaults);
+                self.exact_overflow = solution.status == SolverStatus::Overflow;
+                match self.exact_overflow {
+                    true => {
+                        error!("The exact machine counts overflowed, they are solved with floats");
+                        None
+                    }
+                    false => Some(solution),
+                }
+            }
+            false => None,
+        };
+        let values: Vec<f64> = match exact {
+            Some(solution) => {
+                self.solver_status = solution.status;
+                for (i, recipe) in self.simple_recipes.iter_mut().enumerate() {
+                    recipe.inner_recipe.exact_machines = Some(solution.values[arrows_count + i]);
+                }
+                solution.values.iter().map(|v| v.as_f64()).collect()
+            }
+            None => {
+                let solution = self
+                    .linear_system::<f64>(&helpers, settings)
+                    .solve(&defaults);
+                self.solver_status = solution.status;
+                solution.values
+            }
+        };
+
+        for (i, recipe) in self.simple_recipes.iter_mut().enumerate() {
+            let rate = values[arrows_count + i] as f32;
+            debug!("Run rate of {}: {}", recipe.inner_recipe.title, rate);
+            scale_recipe_flows(
+                &mut recipe.inner_recipe.inputs,
+                &mut recipe.inner_recipe.outputs,
+                rate,
+            );
+        }
+        for (i, recipe) in self.compound_recipes.iter_mut().enumerate() {
+            let rate = values[arrows_count + simple_count + i] as f32;
+            debug!("Run rate of {}: {}", recipe.inner_recipe.title, rate);
+            scale_recipe_flows(
+                &mut recipe.inner_recipe.inputs,
+                &mut recipe.inner_recipe.outputs,
+                rate,
+            );
+        }
+
+        for (helper, amount) in helpers.iter().zip(values.iter()) {
+            self.apply_arrow_flow(helper, *amount, settings);
+        }
+        info!("Linear calculation done: {}", self.solver_status);
+        trace!("[END] linear calculation");
+    }
+
+    /// Plan the graph backwards from the targets of the sinks. The nodes are walked in reverse
+    /// topological order: a recipe runs at the rate its most demanded output needs, and its inputs
+    /// ask that much to the arrows feeding them. Sinks without target take the surplus.
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph, the graph must not have loops
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: ()
+    fn target_calculation(&mut self, helpers: &[FlowCalculatorHelper], settings: &TimeSettings) {
+        trace!("[START] target calculation");
+        self.reset_designed_flows(settings);
+
+        let simple_count = self.simple_recipes.len();
+        let (mut demands, mut rates) = self.target_walk(helpers, &[], settings);
+        //the generators run for the power used, their fuel is planned by a second walk
+        if let Some(generators_rates) = self.generators_rates(&rates, settings) {
+            (demands, rates) = self.target_walk(helpers, &generators_rates, settings);
+        }
+
+        self.surplus_to_sinks(helpers, &rates, &mut demands, settings);
+
+        for (recipe, rate) in self.simple_recipes.iter_mut().zip(rates.iter()) {
+            scale_recipe_flows(
+                &mut recipe.inner_recipe.inputs,
+                &mut recipe.inner_recipe.outputs,
+                *rate as f32,
+            );
+        }
+        for (recipe, rate) in self
+            .compound_recipes
+            .iter_mut()
+            .zip(rates.iter().skip(simple_count))
+        {
+            scale_recipe_flows(
+                &mut recipe.inner_recipe.inputs,
+                &mut recipe.inner_recipe.outputs,
+                *rate as f32,
+            );
+        }
+        for (helper, amount) in helpers.iter().zip(demands.iter()) {
+            self.apply_arrow_flow(helper, *amount, settings);
+        }
+        trace!("[END] target calculation");
+    }
+
+    /// Walk the nodes in reverse topological order, from the targets of the sinks to the sources
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph, the graph must not have loops
+    /// * `minimum_rates`: lowest run-rate of the recipes, missing ones are zero
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: the flow of every arrow per hour and the run-rate of the simple recipes followed
+    /// by the compound recipes
+    fn target_walk(
+        &self,
+        helpers: &[FlowCalculatorHelper],
+        minimum_rates: &[f64],
+        settings: &TimeSettings,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let simple_count = self.simple_recipes.len();
+        let mut demands = vec![0.0f64; helpers.len()];
+        let mut rates = vec![0.0f64; simple_count + self.compound_recipes.len()];
+
+        let components = strongly_connected_components(&self.adjacency(helpers));
+        for node in components.iter().rev().flatten() {
+            let (window_type, index) = self.node_window(*node);
+            let (inputs, outputs, variable) = match window_type {
+                RecipeWindowType::SimpleRecipe => {
+                    let recipe = &self.simple_recipes[index].inner_recipe;
+                    (&recipe.inputs, &recipe.outputs, index)
+                }
+                RecipeWindowType::CompoundRecipe => {
+                    let recipe = &self.compound_recipes[index].inner_recipe;
+                    (&recipe.inputs, &recipe.outputs, simple_count + index)
+                }
+                RecipeWindowType::Sink => {
+                    let sink = &self.sinks[index];
+                    if sink.targeted {
+                        let target = sink.hourly_target(settings);
+                        share(
+                            &mut demands,
+                            &arrows_into(helpers, (window_type, index, 0)),
+                            target,
+                        );
+                    }
+                    continue;
+                }
+                RecipeWindowType::Source => continue,
+                RecipeWindowType::Splitter => {
+                    let splitter = &self.splitters[index];
+                    let port_demands: Vec<Option<f64>> = (0..splitter.outputs.len())
+                        .map(|output| {
+                            let arrows = arrows_from(helpers, (window_type, index, output));
+                            match arrows.is_empty() {
+                                true => None,
+                                false => Some(arrows.iter().map(|a| demands[*a]).sum()),
+                            }
+                        })
+                        .collect();
+                    let needed = splitter.needed_for(&port_demands);
+                    share(
+                        &mut demands,
+                        &arrows_into(helpers, (window_type, index, 0)),
+                        needed,
+                    );
+                    //the outputs get their part of the input, even beyond what they asked
+                    let parts = splitter.split(needed, &port_demands);
+                    for (output, part) in parts.into_iter().enumerate() {
+                        share(
+                            &mut demands,
+                            &arrows_from(helpers, (window_type, index, output)),
+                            part,
+                        );
+                    }
+                    continue;
+                }
+                RecipeWindowType::Merger => {
+                    let demand: f64 = arrows_from(helpers, (window_type, index, 0))
+                        .into_iter()
+                        .map(|a| demands[a])
+                        .sum();
+                    let connected: Vec<Vec<usize>> = (0..self.mergers[index].inputs.len())
+                        .map(|input| arrows_into(helpers, (window_type, index, input)))
+                        .filter(|arrows| !arrows.is_empty())
+                        .collect();
+                    for arrows in connected.iter() {
+                        share(&mut demands, arrows, demand / connected.len() as f64);
+                    }
+                    continue;
+                }
+            };
+
+            let mut rate = minimum_rates.get(variable).copied().unwrap_or(0.0);
+            for (flow_index, output) in outputs.iter().enumerate() {
+                if let ManageFlow::RecipeOutput(output) = output {
+                    let created = hourly_amount(&output.created, settings);
+                    let demand: f64 = arrows_from(helpers, (window_type, index, flow_index))
+                        .into_iter()
+                        .map(|a| demands[a])
+                        .sum();
+                    if created > 0.0 {
+                        rate = rate.max(demand / created);
+                    }
+                }
+            }
+            for (flow_index, input) in inputs.iter().enumerate() {
+                if let ManageFlow::RecipeInput(input) = input {
+                    let needed = rate * hourly_amount(&input.needed, settings);
+                    share(
+                        &mut demands,
+                        &arrows_into(helpers, (window_type, index, flow_index)),
+                        needed,
+                    );
+                }
+            }
+            debug!("Target run rate of node {node}: {rate}");
+            rates[variable] = rate;
+        }
+        (demands, rates)
+    }
+
+    /// Run-rate of the power generators so they make the power used by the other recipes, they
+    /// all run at the same utilisation
+    ///
+    /// # Arguments
+    ///
+    /// * `rates`: run-rate of the simple recipes followed by the compound recipes
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: the run-rates, None if the graph has no generator
+    fn generators_rates(&self, rates: &[f64], settings: &TimeSettings) -> Option<Vec<f64>> {
+        let balances: Vec<f64> = self
+            .simple_recipes
+            .iter()
+            .map(|recipe| f64::from(recipe.inner_recipe.power_balance_per_machine(settings)))
+            .collect();
+        let capacity: f64 = balances.iter().filter(|b| **b > 0.0).sum();
+        if capacity <= 0.0 {
+            return None;
+        }
+        let usage: f64 = balances
+            .iter()
+            .zip(rates.iter())
+            .filter(|(b, _)| **b < 0.0)
+            .map(|(b, rate)| -b * rate)
+            .sum();
+        let utilisation = usage / capacity;
+        debug!("Power used {usage}W, generators capacity {capacity}W");
+
+        let mut generators_rates = vec![0.0; rates.len()];
+        for (rate, balance) in generators_rates.iter_mut().zip(balances.iter()) {
+            if *balance > 0.0 {
+                *rate = utilisation;
+            }
+        }
+        Some(generators_rates)
+    }
+
+    /// Send what a recipe output makes beyond the demands of its arrows to the sinks without
+    /// target it feeds
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph
+    /// * `rates`: run-rate of the simple recipes followed by the compound recipes
+    /// * `demands`: flow of every arrow per hour, updated for the arrows to the sinks
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: ()
+    fn surplus_to_sinks(
+        &self,
+        helpers: &[FlowCalculatorHelper],
+        rates: &[f64],
+        demands: &mut [f64],
+        settings: &TimeSettings,
+    ) {
+        let simple_count = self.simple_recipes.len();
+        let is_surplus = |h: &FlowCalculatorHelper| {
+            h.end_type == RecipeWindowType::Sink && !self.sinks[h.end_window_index].targeted
+        };
+
+        for (i, helper) in helpers.iter().enumerate() {
+            if !is_surplus(helper) {
+                continue;
+            }
+            let (outputs, rate) = match helper.start_type {
+                RecipeWindowType::SimpleRecipe => (
+                    &self.simple_recipes[helper.start_window_index]
+                        .inner_recipe
+                        .outputs,
+                    rates[helper.start_window_index],
+                ),
+                RecipeWindowType::CompoundRecipe => (
+                    &self.compound_recipes[helper.start_window_index]
+                        .inner_recipe
+                        .outputs,
+                    rates[simple_count + helper.start_window_index],
+                ),
+                RecipeWindowType::Source
+                | RecipeWindowType::Sink
+                | RecipeWindowType::Splitter
+                | RecipeWindowType::Merger => continue,
+            };
+            let created = match &outputs[helper.start_flow_index] {
+                ManageFlow::RecipeOutput(output) => rate * hourly_amount(&output.created, settings),
+                ManageFlow::RecipeInput(_) => continue,
+            };
+
+            let siblings = arrows_from(
+                helpers,
+                (
+                    helper.start_type,
+                    helper.start_window_index,
+                    helper.start_flow_index,
+                ),
+            );
+            let used: f64 = siblings
+                .iter()
+                .filter(|a| !is_surplus(&helpers[**a]))
+                .map(|a| demands[*a])
+                .sum();
+            let surplus_arrows = siblings
+                .iter()
+                .filter(|a| is_surplus(&helpers[**a]))
+                .count();
+            demands[i] = (created - used).max(0.0) / surplus_arrows as f64;
+        }
+    }
+
+    ///Flag the limited sources drawn above their limit and keep the draw of every source
+    fn check_source_limits(&mut self, settings: &TimeSettings) {
+        for source in self.sources.iter_mut() {
+            let limit = source.hourly_limit(settings);
+            let drawn = hourly_amount(&source.output.total_out(settings), settings);
+            source.drawn = drawn;
+            source.over_limit = source.limited_output && drawn > limit * (1.0 + 1e-6);
+            if source.over_limit {
+                error!(
+                    "Source of {} drawn above its limit",
+                    source.output.resource().name
+                );
+            }
+        }
+    }
+
+    ///Set the flows of the recipes back to their designed value, for one machine
+    fn reset_designed_flows(&mut self, settings: &TimeSettings) {
+        for recipe in self.simple_recipes.iter_mut() {
+            for dir in [Io::Input, Io::Output] {
+                if let Err(e) = recipe.inner_recipe.update_flow(dir, settings) {
+                    error!("Designed flows couldn't be restored: {}", e.str());
+                }
+            }
+        }
+        for recipe in self.compound_recipes.iter_mut() {
+            recipe.update_interface(settings);
+        }
+    }
+
+    /// Build the linear system of the graph, see [RecipeGraph::linear_calculation]
+    ///
+    /// # Arguments
+    ///
+    /// * `helpers`: the arrows of the graph, their index is their variable
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: the system, its variables are the arrows then the simple and compound recipes
+    fn linear_system<S: Scalar>(
+        &self,
+        helpers: &[FlowCalculatorHelper],
+        settings: &TimeSettings,
+    ) -> LinearSystem<S> {
+        let arrows_count = helpers.len();
+        let simple_count = self.simple_recipes.len();
+        let recipes_count = simple_count + self.compound_recipes.len();
+        let mut system = LinearSystem::new(arrows_count + recipes_count);
+
+        for (i, recipe) in self.simple_recipes.iter().enumerate() {
+            Self::recipe_equations(
+                &mut system,
+                helpers,
+                &recipe.inner_recipe.inputs,
+                &recipe.inner_recipe.outputs,
+                (RecipeWindowType::SimpleRecipe, i),
+                arrows_count + i,
+                settings,
+            );
+        }
+        for (i, recipe) in self.compound_recipes.iter().enumerate() {
+            Self::recipe_equations(
+                &mut system,
+                helpers,
+                &recipe.inner_recipe.inputs,
+                &recipe.inner_recipe.outputs,
+                (RecipeWindowType::CompoundRecipe, i),
+                arrows_count + simple_count + i,
+                settings,
+            );
+        }
+        for (i, source) in self.sources.iter().enumerate() {
+            //in target mode the limits are only checked, the targets drive the graph
+            if !source.limited_output || self.mode == CalculationMode::Target {
+                continue;
+            }
+            let coefficients: Vec<(usize, S)> =
+                arrows_from(helpers, (RecipeWindowType::Source, i, 0))
+                    .into_iter()
+                    .map(|a| (a, S::one()))
+                    .collect();
+            if !coefficients.is_empty() {
+                let limit = source.hourly_limit(settings);
+                system.add_equation(coefficients, S::from_f64(limit));
+            }
+        }
+        for (i, sink) in self.sinks.iter().enumerate() {
+            if !sink.targeted {
+                continue;
+            }
+            let coefficients: Vec<(usize, S)> =
+                arrows_into(helpers, (RecipeWindowType::Sink, i, 0))
+                    .into_iter()
+                    .map(|a| (a, S::one()))
+                    .collect();
+            if !coefficients.is_empty() {
+                let target = sink.hourly_target(settings);
+                system.add_equation(coefficients, S::from_f64(target));
+            }
+        }
+
+        for (i, splitter) in self.splitters.iter().enumerate() {
+            Self::splitter_equations(&mut system, helpers, splitter, i);
+        }
+        for i in 0..self.mergers.len() {
+            let outputs = arrows_from(helpers, (RecipeWindowType::Merger, i, 0));
+            //like a recipe output, a merger without arrow out lets its inputs be excess
+            if outputs.is_empty() {
+                continue;
+            }
+            let mut coefficients: Vec<(usize, S)> = (0..self.mergers[i].inputs.len())
+                .flat_map(|input| arrows_into(helpers, (RecipeWindowType::Merger, i, input)))
+                .map(|a| (a, S::one()))
+                .collect();
+            coefficients.extend(outputs.into_iter().map(|a| (a, -S::one())));
+            system.add_equation(coefficients, S::zero());
+        }
+
+        //the power made by the generators balances the power used
+        let power: Vec<(usize, S)> = self
+            .simple_recipes
+            .iter()
+            .enumerate()
+            .map(|(i, recipe)| {
+                let balance = recipe.inner_recipe.power_balance_per_machine(settings);
+                (arrows_count + i, S::from_f64(f64::from(balance)))
+            })
+            .filter(|(_, balance)| !balance.is_zero())
+            .collect();
+        if power.iter().any(|(_, balance)| balance.is_positive()) {
+            system.add_equation(power, S::zero());
+        }
+
+        system
+    }
+
+    /// Add the balance equations of a recipe's connected flows to a linear system
+    ///
+    /// # Arguments
+    ///
+    /// * `system`: the system to fill
+    /// * `helpers`: the arrows of the graph, their index is their variable
+    /// * `inputs`: inputs of the recipe
+    /// * `outputs`: outputs of the recipe
+    /// * `node`: type and index of the recipe window
+    /// * `variable`: index of the variable of the recipe run-rate
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: ()
+    fn recipe_equations<S: Scalar>(
+        system: &mut LinearSystem<S>,
+        helpers: &[FlowCalculatorHelper],
+        inputs: &[ManageFlow<f32>],
+        outputs: &[ManageFlow<f32>],
+        node: (RecipeWindowType, usize),
+        variable: usize,
+        settings: &TimeSettings,
+    ) {
+        for (flow_index, input) in inputs.iter().enumerate() {
+            if let ManageFlow::RecipeInput(input) = input {
+                let mut coefficients: Vec<(usize, S)> =
+                    arrows_into(helpers, (node.0, node.1, flow_index))
+                        .into_iter()
+                        .map(|a| (a, S::one()))
+                        .collect();
+                if !coefficients.is_empty() {
+                    coefficients.push((
+                        variable,
+                        -S::from_f64(hourly_amount(&input.needed, settings)),
+                    ));
+                    system.add_equation(coefficients, S::zero());
+                }
+            }
+        }
+        for (flow_index, output) in outputs.iter().enumerate() {
+            if let ManageFlow::RecipeOutput(output) = output {
+                let mut coefficients: Vec<(usize, S)> =
+                    arrows_from(helpers, (node.0, node.1, flow_index))
+                        .into_iter()
+                        .map(|a| (a, S::one()))
+                        .collect();
+                if !coefficients.is_empty() {
+                    coefficients.push((
+                        variable,
+                        -S::from_f64(hourly_amount(&output.created, settings)),
+                    ));
+                    system.add_equation(coefficients, S::zero());
+                }
+            }
+        }
+    }
+
+    /// Add the equations of a splitter to a linear system: its outputs share its input, and in
+    /// even and ratio modes every output gets its part. The priorities aren't linear, a priority
+    /// splitter only keeps the balance.
+    ///
+    /// # Arguments
+    ///
+    /// * `system`: the system to fill
+    /// * `helpers`: the arrows of the graph, their index is their variable
+    /// * `splitter`: the splitter
+    /// * `index`: index of the splitter
+    ///
+    /// returns: ()
+    fn splitter_equations<S: Scalar>(
+        system: &mut LinearSystem<S>,
+        helpers: &[FlowCalculatorHelper],
+        splitter: &ResourceSplitter,
+        index: usize,
+    ) {
+        let outputs: Vec<Vec<usize>> = (0..splitter.outputs.len())
+            .map(|output| arrows_from(helpers, (RecipeWindowType::Splitter, index, output)))
+            .collect();
+        if outputs.iter().all(Vec::is_empty) {
+            return;
+        }
+        let inputs = arrows_into(helpers, (RecipeWindowType::Splitter, index, 0));
+
+        let mut coefficients: Vec<(usize, S)> = inputs.iter().map(|a| (*a, S::one())).collect();
+        coefficients.extend(outputs.iter().flatten().map(|a| (*a, -S::one())));
+        system.add_equation(coefficients, S::zero());
+
+        if splitter.mode == SplitMode::Priority {
+            return;
+        }
+        let connected: Vec<Option<f64>> = outputs
+            .iter()
+            .map(|arrows| (!arrows.is_empty()).then_some(0.0))
+            .collect();
+        let shares = splitter.split(1.0, &connected);
+        for (arrows, part) in outputs.iter().zip(shares) {
+            if arrows.is_empty() {
+                continue;
+            }
+            let mut coefficients: Vec<(usize, S)> = arrows.iter().map(|a| (*a, S::one())).collect();
+            coefficients.extend(inputs.iter().map(|a| (*a, -S::from_f64(part))));
+            system.add_equation(coefficients, S::zero());
+        }
+    }
+
+    /// Push the flow of an arrow to both its ends
+    ///
+    /// # Arguments
+    ///
+    /// * `helper`: the arrow
+    /// * `amount`: amount of the flow per hour
+    /// * `settings`: time settings the amounts are converted with
+    ///
+    /// returns: ()
+    fn apply_arrow_flow(
+        &mut self,
+        helper: &FlowCalculatorHelper,
+        amount: f64,
+        settings: &TimeSettings,
+    ) {
+        let start = match helper.start_type {
+            RecipeWindowType::SimpleRecipe => {
+                match &mut self.simple_recipes[helper.start_window_index]
+                    .inner_recipe
+                    .outputs[helper.start_flow_index]
+                {
+                    ManageFlow::RecipeOutput(o) => Some(o),
+                    ManageFlow::RecipeInput(_) => None,
+                }
+            }
+            RecipeWindowType::CompoundRecipe => {
+                match &mut self.compound_recipes[helper.start_window_index]
+                    .inner_recipe
+                    .outputs[helper.start_flow_index]
+                {
+                    ManageFlow::RecipeOutput(o) => Some(o),
+                    ManageFlow::RecipeInput(_) => None,
+                }
+            }
+            RecipeWindowType::Source => Some(&mut self.sources[helper.start_window_index].output),
+            RecipeWindowType::Sink => None,
+            RecipeWindowType::Splitter => self.splitters[helper.start_window_index]
+                .outputs
+                .get_mut(helper.start_flow_index),
+            RecipeWindowType::Merger => Some(&mut self.mergers[helper.start_window_index].output),
+        };
+        let start = match start {
+            None => {
+                error!("Source flows shouldn't be a RecipeInput");
+                return;
+            }
+            Some(start) => start,
+        };
+
+        let mut used_flow = start.created.clone();
+        used_flow.amount = (amount
+            / f64::from(used_flow.rate.per_hour(settings) * used_flow.resource.unit.factor()))
+            as f32;
+        let added_source = start.add_out_flow(used_flow.clone());
+
+        self.arrows[helper.arrow_index].flow = Some(used_flow.clone());
+        let end = self.end_flow_mut(helper, &used_flow);
+        let added_input = match end {
+            None => false,
+            Some(end) => end.add_in_flow(used_flow),
+        };
+
+        if !(added_source && added_input) {
+            error!("added_source:{added_source} added_inputs{added_input}");
+        }
+    }
+}
+
+///Amount of a flow in the common base of the linear system, base units of the resource per hour
+pub(crate) fn hourly_amount(flow: &ResourceFlow<f32, f32>, settings: &TimeSettings) -> f64 {
+    f64::from(flow.amount * flow.rate.per_hour(settings) * flow.resource.unit.factor())
 }
 
-fn add_flows(
-    source: &mut RecipeOutputResource<usize>,
-    input: &mut RecipeInputResource<usize>,
-    used_flow: ResourceFlow<usize, f32>,
-) {
-    let added_source = source.add_out_flow(used_flow.clone());
-    let added_input = input.add_in_flow(used_flow);
+///Flow of a resource from an amount in base units per hour, the opposite of `hourly_amount`
+pub(crate) fn hourly_flow(resource: &ResourceDefinition, amount: f64) -> ResourceFlow<f32, f32> {
+    let amount = amount / f64::from(resource.unit.factor());
+    ResourceFlow::new(resource, 1.0, amount as f32, RatePer::Hour)
+}
+
+///Indexes of the arrows ending at a flow given by its window type, window index and flow index
+fn arrows_into(
+    helpers: &[FlowCalculatorHelper],
+    end: (RecipeWindowType, usize, usize),
+) -> Vec<usize> {
+    helpers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| (h.end_type, h.end_window_index, h.end_flow_index) == end)
+        .map(|(a, _)| a)
+        .collect()
+}
 
-    if !(added_source && added_input) {
-        error!("added_source:{added_source} added_inputs{added_input}");
+///Indexes of the arrows starting at a flow given by its window type, window index and flow index
+fn arrows_from(
+    helpers: &[FlowCalculatorHelper],
+    start: (RecipeWindowType, usize, usize),
+) -> Vec<usize> {
+    helpers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| (h.start_type, h.start_window_index, h.start_flow_index) == start)
+        .map(|(a, _)| a)
+        .collect()
+}
+
+///Part of an output claimed by one of its arrows
+struct Claim {
+    ///the claims with the highest priority are served first
+    priority: u8,
+    ///the claim only gets what the others leave
+    overflow: bool,
+    ///amount needed per hour in base units
+    demand: f64,
+}
+
+/// Divide what an output supplies between its arrows. The priority levels are served in turn,
+/// the arrows of a level short of supply share it by their demand. What is left goes to the
+/// overflow arrows, or to the lowest priority level when there are none.
+///
+/// # Arguments
+///
+/// * `supply`: amount supplied per hour in base units
+/// * `claims`: the claims of the arrows
+///
+/// returns: the amount of every arrow
+fn allocate(supply: f64, claims: &[Claim]) -> Vec<f64> {
+    let mut parts = vec![0.0; claims.len()];
+    let mut priorities: Vec<u8> = claims
+        .iter()
+        .filter(|claim| !claim.overflow)
+        .map(|claim| claim.priority)
+        .collect();
+    priorities.sort_unstable_by(|a, b| b.cmp(a));
+    priorities.dedup();
+    let level = |priority: u8| -> Vec<usize> {
+        (0..claims.len())
+            .filter(|i| !claims[*i].overflow && claims[*i].priority == priority)
+            .collect()
+    };
+
+    let mut left = supply;
+    for priority in priorities.iter() {
+        let arrows = level(*priority);
+        let demand: f64 = arrows.iter().map(|i| claims[*i].demand).sum();
+        if demand <= 0.0 {
+            continue;
+        }
+        let served = left.min(demand);
+        for i in arrows {
+            parts[i] = served * claims[i].demand / demand;
+        }
+        left -= served;
+    }
+
+    let overflow: Vec<usize> = (0..claims.len()).filter(|i| claims[*i].overflow).collect();
+    let receivers = match (overflow.is_empty(), priorities.last()) {
+        (false, _) => overflow,
+        (true, Some(lowest)) => level(*lowest),
+        (true, None) => vec![],
+    };
+    for i in receivers.iter() {
+        parts[*i] += left / receivers.len() as f64;
+    }
+    parts
+}
+
+///Split an amount evenly between some arrows
+fn share(demands: &mut [f64], arrows: &[usize], amount: f64) {
+    for a in arrows.iter() {
+        demands[*a] = amount / arrows.len() as f64;
+    }
+}
+
+///Scale the designed flows of a recipe by its run-rate
+fn scale_recipe_flows(inputs: &mut [ManageFlow<f32>], outputs: &mut [ManageFlow<f32>], rate: f32) {
+    for input in inputs.iter_mut() {
+        if let ManageFlow::RecipeInput(input) = input {
+            input.needed.amount *= rate;
+        }
+    }
+    for output in outputs.iter_mut() {
+        if let ManageFlow::RecipeOutput(output) = output {
+            output.created.amount *= rate;
+        }
     }
 }
 
+/// Point the end of an arrow at its flow
+///
+/// # Arguments
+///
+/// * `id`: stable id of the flow, taken from the index if it's missing
+/// * `index`: index of the flow, updated from the id
+/// * `flows`: the flows of the recipe at the end, None if it isn't a recipe
+///
+/// returns: `bool` flag indicating the flow still exists
+fn follow_flow(
+    id: &mut Option<egui::Id>,
+    index: &mut usize,
+    flows: Option<&[ManageFlow<f32>]>,
+) -> bool {
+    let flows = match flows {
+        None => return true,
+        Some(flows) => flows,
+    };
+    match id {
+        None => match flows.get(*index) {
+            None => false,
+            Some(flow) => {
+                *id = Some(flow.id());
+                true
+            }
+        },
+        Some(id) => match flows.iter().position(|flow| flow.id() == *id) {
+            None => false,
+            Some(position) => {
+                *index = position;
+                true
+            }
+        },
+    }
+}
 #[cfg(test)]
 #[allow(dead_code)]
 pub mod tests {
-    use crate::app::recipe_graph::RecipeGraph;
-    use crate::app::recipe_window::arrow_flow::ArrowFlow;
+    use crate::app::recipe_graph::linear_system::SolverStatus;
+    use crate::app::recipe_graph::{allocate, CalculationMode, Claim, RecipeGraph};
+    use crate::app::recipe_window::arrow_flow::{ArrowEdit, ArrowFlow};
+    use crate::app::recipe_window::base_recipe_window::FlowEdit;
     use crate::app::recipe_window::compound_recipe_window::CompoundRecipeWindow;
+    use crate::app::recipe_window::resource_merger::ResourceMerger;
     use crate::app::recipe_window::resource_sink::ResourceSink;
-    use crate::app::recipe_window::resources_sources::ResourceSource;
+    use crate::app::recipe_window::resource_splitter::{ResourceSplitter, SplitMode};
+    use crate::app::recipe_window::resources_sources::{Extraction, ResourceSource};
     use crate::app::recipe_window::simple_recipe_window::tests::setup_simple_recipe_one_to_one_custom;
     use crate::app::recipe_window::test::{setup_resource_input, setup_resource_output};
     use crate::app::recipe_window::RecipeWindowType;
+    use crate::app::resources::recipe_output_resource::RecipeOutputResource;
     use crate::app::resources::resource_flow::test::{setup_flow_resource, setup_flow_resource_a};
     use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
     use crate::app::resources::test::setup_resource;
+    use crate::app::resources::time_settings::TimeSettings;
 
-    use crate::app::resources::{RatePer, ResourceDefinition};
-    use crate::utils::test_env;
+    use crate::app::resources::{ManageFlow, RatePer, ResourceDefinition, Unit};
+    use crate::utils::rational::Rational;
+    use crate::utils::{test_env, Io};
     use eframe::epaint::ahash::HashMapExt;
     use egui::epaint::ahash::HashMap;
     use egui::{LayerId, Order};
@@ -629,8 +2281,8 @@ pub mod tests {
     pub(crate) struct TestInfo {
         pub name: String,
         pub graph: RecipeGraph,
-        pub inputs: Vec<ResourceFlow<usize, f32>>,
-        pub outputs: Vec<ResourceFlow<usize, f32>>,
+        pub inputs: Vec<ResourceFlow<f32, f32>>,
+        pub outputs: Vec<ResourceFlow<f32, f32>>,
     }
 
     impl Display for TestInfo {
@@ -1201,10 +2853,190 @@ pub mod tests {
             }
         }
 
+        /// Graph with a shared intermediate: plates feed both the gears and the circuits, and the
+        /// gears feed the circuits too.
+        pub(crate) fn setup_diamond_graph() -> TestInfo {
+            let ore = setup_resource("Ore");
+            let plate = setup_resource("Plate");
+            let gear = setup_resource("Gear");
+            let circuit = setup_resource("Circuit");
+            let mut graph = RecipeGraph::new();
+            graph.mode = CalculationMode::Linear;
+
+            let smelter = setup_simple_recipe_one_to_one_custom(
+                Some(setup_resource_input(setup_flow_resource(
+                    ore.clone(),
+                    2,
+                    RatePer::Minute,
+                ))),
+                Some(setup_resource_output(setup_flow_resource(
+                    plate.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+            );
+            let gear_press = setup_simple_recipe_one_to_one_custom(
+                Some(setup_resource_input(setup_flow_resource(
+                    plate.clone(),
+                    2,
+                    RatePer::Minute,
+                ))),
+                Some(setup_resource_output(setup_flow_resource(
+                    gear.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+            );
+            let mut assembler = setup_simple_recipe_one_to_one_custom(
+                Some(setup_resource_input(setup_flow_resource(
+                    plate.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+                Some(setup_resource_output(setup_flow_resource(
+                    circuit.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+            );
+            assembler.recipe.inner_recipe.inputs.push(
+                setup_resource_input(setup_flow_resource(gear.clone(), 1, RatePer::Minute))
+                    .manage_flow,
+            );
+            let source = ResourceSource::limited_source(ore.name.clone(), 12.0, RatePer::Minute);
+            let sink = ResourceSink::new();
+
+            let simple = RecipeWindowType::SimpleRecipe;
+            let smelter_id = smelter.recipe.inner_recipe.id;
+            let gear_press_id = gear_press.recipe.inner_recipe.id;
+            let assembler_id = assembler.recipe.inner_recipe.id;
+            connect(
+                &mut graph,
+                &ore,
+                (source.id, RecipeWindowType::Source, 0),
+                (smelter_id, simple, 0),
+            );
+            connect(
+                &mut graph,
+                &plate,
+                (smelter_id, simple, 0),
+                (gear_press_id, simple, 0),
+            );
+            connect(
+                &mut graph,
+                &plate,
+                (smelter_id, simple, 0),
+                (assembler_id, simple, 0),
+            );
+            connect(
+                &mut graph,
+                &gear,
+                (gear_press_id, simple, 0),
+                (assembler_id, simple, 1),
+            );
+            connect(
+                &mut graph,
+                &circuit,
+                (assembler_id, simple, 0),
+                (sink.id, RecipeWindowType::Sink, 0),
+            );
+
+            graph.simple_recipes.push(assembler.recipe);
+            graph.simple_recipes.push(gear_press.recipe);
+            graph.simple_recipes.push(smelter.recipe);
+            graph.sources.push(source);
+            graph.sinks.push(sink);
+
+            TestInfo {
+                name: "diamond".to_string(),
+                graph,
+                inputs: vec![ResourceFlow::new(&ore, 2.0, 12.0, RatePer::Minute)],
+                outputs: vec![ResourceFlow::new(&circuit, 1.0, 2.0, RatePer::Minute)],
+            }
+        }
+
+        /// Graph with a recycling loop: the refinery makes slag the recycler turns back into ore
+        pub(crate) fn setup_loop_graph() -> TestInfo {
+            let ore = setup_resource("Ore");
+            let metal = setup_resource("Metal");
+            let slag = setup_resource("Slag");
+            let mut graph = RecipeGraph::new();
+
+            let mut refinery = setup_simple_recipe_one_to_one_custom(
+                Some(setup_resource_input(setup_flow_resource(
+                    ore.clone(),
+                    2,
+                    RatePer::Minute,
+                ))),
+                Some(setup_resource_output(setup_flow_resource(
+                    metal.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+            );
+            refinery.recipe.inner_recipe.outputs.push(
+                setup_resource_output(setup_flow_resource(slag.clone(), 1, RatePer::Minute))
+                    .manage_flow,
+            );
+            let recycler = setup_simple_recipe_one_to_one_custom(
+                Some(setup_resource_input(setup_flow_resource(
+                    slag.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+                Some(setup_resource_output(setup_flow_resource(
+                    ore.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+            );
+            let source = ResourceSource::limited_source(ore.name.clone(), 10.0, RatePer::Minute);
+            let sink = ResourceSink::new();
+
+            let simple = RecipeWindowType::SimpleRecipe;
+            let refinery_id = refinery.recipe.inner_recipe.id;
+            let recycler_id = recycler.recipe.inner_recipe.id;
+            connect(
+                &mut graph,
+                &ore,
+                (source.id, RecipeWindowType::Source, 0),
+                (refinery_id, simple, 0),
+            );
+            connect(
+                &mut graph,
+                &slag,
+                (refinery_id, simple, 1),
+                (recycler_id, simple, 0),
+            );
+            connect(
+                &mut graph,
+                &ore,
+                (recycler_id, simple, 0),
+                (refinery_id, simple, 0),
+            );
+            connect(
+                &mut graph,
+                &metal,
+                (refinery_id, simple, 0),
+                (sink.id, RecipeWindowType::Sink, 0),
+            );
+            graph.simple_recipes.push(refinery.recipe);
+            graph.simple_recipes.push(recycler.recipe);
+            graph.sources.push(source);
+            graph.sinks.push(sink);
+
+            TestInfo {
+                name: "loop".to_string(),
+                graph,
+                inputs: vec![ResourceFlow::new(&ore, 2.0, 10.0, RatePer::Minute)],
+                outputs: vec![ResourceFlow::new(&metal, 1.0, 10.0, RatePer::Minute)],
+            }
+        }
+
         fn get_calc_sources(&self) -> HashMap<ResourceDefinition, (f32, RatePer)> {
             let mut result = HashMap::new();
             for source in self.sources.iter() {
-                let flow = source.output.total_out();
+                let flow = source.output.total_out(&TimeSettings::default());
                 result.insert(flow.resource, (flow.amount, flow.rate));
             }
 
@@ -1218,13 +3050,40 @@ pub mod tests {
                     .sink
                     .as_ref()
                     .expect("Unconnected calculation sink")
-                    .total_in();
+                    .total_in(&TimeSettings::default());
                 result.insert(flow.resource, (flow.amount, flow.rate));
             }
 
             result
         }
     }
+    /// Connect two windows of a test graph with an arrow
+    ///
+    /// # Arguments
+    ///
+    /// * `graph`: the graph to add the arrow to
+    /// * `resource`: resource of the arrow
+    /// * `start`: id, type and output flow index of the starting window
+    /// * `end`: id, type and input flow index of the ending window
+    ///
+    /// returns: ()
+    pub(crate) fn connect(
+        graph: &mut RecipeGraph,
+        resource: &ResourceDefinition,
+        start: (egui::Id, RecipeWindowType, usize),
+        end: (egui::Id, RecipeWindowType, usize),
+    ) {
+        let dummy_layer: LayerId = LayerId {
+            order: Order::Background,
+            id: egui::Id::new("dummy"),
+        };
+        let mut arrow = ArrowFlow::new(resource.clone(), start.0, start.1, dummy_layer, start.2);
+        arrow
+            .put_end(Some(resource.clone()), end.0, end.1, end.2)
+            .expect("arrow error");
+        graph.arrows.push(arrow);
+    }
+
     // ------------------------------- Test -------------------------------
 
     #[test]
@@ -1242,7 +3101,7 @@ pub mod tests {
 
             let mut graph = test_info.graph;
             info!("Calculate for test");
-            graph.calculate();
+            graph.calculate(&TimeSettings::default());
             let calculated_inputs = graph.get_calc_sources();
 
             for input in test_info.inputs.iter() {
@@ -1255,13 +3114,15 @@ pub mod tests {
                 info!(
                     "Input calculated={}{} is equal to {}?",
                     calculated.0,
-                    calculated.1.to_shortened_string(),
+                    calculated.1.to_shortened_string(&TimeSettings::default()),
                     input
                 );
                 assert_eq!(calculated.1, input.rate, "Rate of an input doesn't match");
-                assert_eq!(
-                    calculated.0, input.amount,
-                    "Amount of an input doesn't match",
+                assert!(
+                    (calculated.0 - input.amount).abs() < 1e-4,
+                    "Amount of an input doesn't match: {} != {}",
+                    calculated.0,
+                    input.amount
                 );
             }
             let calculated_outputs = graph.get_calc_sinks();
@@ -1274,18 +3135,846 @@ pub mod tests {
                 info!(
                     "Output calculated={}{} is equal to {}?",
                     calculated.0,
-                    calculated.1.to_shortened_string(),
+                    calculated.1.to_shortened_string(&TimeSettings::default()),
                     output
                 );
-                assert_eq!(
-                    calculated.0, output.amount,
-                    "Amount of an output doesn't match",
+                assert!(
+                    (calculated.0 - output.amount).abs() < 1e-4,
+                    "Amount of an output doesn't match: {} != {}",
+                    calculated.0,
+                    output.amount
                 );
                 assert_eq!(calculated.1, output.rate, "Rate of an output doesn't match");
             }
         }
     }
 
+    /// Check the calculated flows of the sources and sinks of a graph, in any rate
+    ///
+    /// # Arguments
+    ///
+    /// * `graph`: the calculated graph
+    /// * `inputs`: expected flows of the sources
+    /// * `outputs`: expected flows of the sinks
+    ///
+    /// returns: ()
+    fn assert_calculated(
+        graph: &RecipeGraph,
+        inputs: &[ResourceFlow<f32, f32>],
+        outputs: &[ResourceFlow<f32, f32>],
+    ) {
+        let calculated_inputs = graph.get_calc_sources();
+        let calculated_outputs = graph.get_calc_sinks();
+        for (expected, calculated) in inputs
+            .iter()
+            .map(|input| (input, calculated_inputs.get(&input.resource)))
+            .chain(
+                outputs
+                    .iter()
+                    .map(|output| (output, calculated_outputs.get(&output.resource))),
+            )
+        {
+            let (amount, rate) = calculated.expect("no data in the resource");
+            let calculated = ResourceFlow::<f32, f32>::new(&expected.resource, 0.0, *amount, *rate);
+            let amount = calculated
+                .convert_amount(expected.rate, &TimeSettings::default())
+                .unwrap();
+            assert!(
+                (amount - expected.amount).abs() < 1e-3,
+                "Amount of {} doesn't match: {amount} != {}",
+                expected.resource,
+                expected.amount
+            );
+        }
+    }
+
+    ///Amount per minute a connected sink receives
+    fn sink_amount(sink: &ResourceSink) -> f32 {
+        let flow = sink
+            .sink
+            .as_ref()
+            .expect("Unconnected sink")
+            .total_in(&TimeSettings::default());
+        flow.convert_amount(RatePer::Minute, &TimeSettings::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_linear_calculation() {
+        test_env::setup();
+        let test_info = RecipeGraph::setup_diamond_graph();
+        info!("📍Start linear test on graph: {}📍", test_info);
+
+        let mut graph = test_info.graph;
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.solver_status, SolverStatus::Feasible);
+        assert_calculated(&graph, &test_info.inputs, &test_info.outputs);
+    }
+
+    #[test]
+    fn test_machine_count() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_diamond_graph().graph;
+        graph.calculate(&TimeSettings::default());
+        let counts: Vec<usize> = graph
+            .simple_recipes
+            .iter()
+            .map(|recipe| {
+                recipe
+                    .inner_recipe
+                    .machine_count_rounded(&TimeSettings::default())
+            })
+            .collect();
+        assert_eq!(counts, vec![2, 2, 6]);
+
+        graph.mode = CalculationMode::Target;
+        graph.sinks[0].targeted = true;
+        graph.sinks[0].target_amount = 1.5;
+        graph.sinks[0].target_rate = RatePer::Minute;
+        graph.calculate(&TimeSettings::default());
+        let smelter = &graph.simple_recipes[2].inner_recipe;
+        assert!((smelter.machine_count(&TimeSettings::default()) - 4.5).abs() < 1e-3);
+        assert_eq!(smelter.machine_count_rounded(&TimeSettings::default()), 5);
+        let throughput = smelter.build_throughput(&TimeSettings::default());
+        let plates = throughput[0]
+            .convert_amount(RatePer::Minute, &TimeSettings::default())
+            .unwrap();
+        assert!((plates - 5.0).abs() < 1e-3, "{plates} != 5");
+    }
+
+    #[test]
+    fn test_exact_machine_count() {
+        test_env::setup();
+        let test_info = RecipeGraph::setup_diamond_graph();
+        let mut graph = test_info.graph;
+        graph.exact = true;
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.solver_status, SolverStatus::Feasible);
+        assert_calculated(&graph, &test_info.inputs, &test_info.outputs);
+        let counts: Vec<Option<Rational>> = graph
+            .simple_recipes
+            .iter()
+            .map(|recipe| recipe.inner_recipe.exact_machines)
+            .collect();
+        let expected = [2, 2, 6].map(|count| Some(Rational::from_integer(count)));
+        assert_eq!(counts, expected);
+
+        //7/12 of the ore makes 7/6 assemblers, never 1.16666 or 1.1667
+        graph.sources[0].limit_amount = 7.0;
+        graph.calculate(&TimeSettings::default());
+        let assembler = &graph.simple_recipes[0].inner_recipe;
+        assert_eq!(assembler.exact_machines, Some(Rational::new(7, 6)));
+        assert_eq!(assembler.machine_count_rounded(&TimeSettings::default()), 2);
+        let smelter = &graph.simple_recipes[2].inner_recipe;
+        assert_eq!(smelter.exact_machines, Some(Rational::new(7, 2)));
+        assert_eq!(smelter.machine_count_rounded(&TimeSettings::default()), 4);
+    }
+
+    #[test]
+    fn test_rename_resource() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_diamond_graph().graph;
+        let plate = setup_resource("Plate");
+        let renamed = setup_resource("Iron Plate");
+        graph.rename_resource(&plate, &renamed);
+
+        let resources = graph.resources();
+        assert!(!resources.contains(&plate));
+        assert!(resources.contains(&renamed));
+        assert!(graph.arrows.iter().all(|arrow| arrow.resource != plate));
+
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.solver_status, SolverStatus::Feasible);
+        let test_info = RecipeGraph::setup_diamond_graph();
+        assert_calculated(&graph, &test_info.inputs, &test_info.outputs);
+    }
+
+    #[test]
+    fn test_mixed_units() {
+        test_env::setup();
+        for mode in [CalculationMode::Propagation, CalculationMode::Linear] {
+            let test_info = RecipeGraph::setup_simple_graph();
+            let mut graph = test_info.graph;
+            graph.mode = mode;
+            let mut stacks = graph.sources[0].output.resource();
+            stacks.unit = Unit::Stack(10);
+            let mut source = ResourceSource::from_definition(stacks);
+            source.id = graph.sources[0].id;
+            graph.sources[0] = source;
+
+            graph.calculate(&TimeSettings::default());
+            let expected = &test_info.inputs[0];
+            let drawn = graph.sources[0].output.total_out(&TimeSettings::default());
+            assert_eq!(drawn.resource.unit, Unit::Stack(10));
+            let amount = drawn
+                .amount_in(Unit::Piece, expected.rate, &TimeSettings::default())
+                .unwrap();
+            assert!(
+                (amount - expected.amount).abs() < 1e-3,
+                "{mode:?}: {amount} != {}",
+                expected.amount
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_arrows() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_simple_graph().graph;
+        graph.calculate(&TimeSettings::default());
+        assert!(graph.check_arrows().is_empty());
+
+        let mut stacks = graph.sources[0].output.resource();
+        stacks.unit = Unit::Stack(10);
+        graph.sources[0].output.set_resource(stacks.clone());
+        assert!(graph.check_arrows().is_empty());
+        assert!(graph.arrows.iter().any(|arrow| arrow.resource == stacks));
+
+        graph.sources[0].output.set_resource(setup_resource("Coal"));
+        assert_eq!(graph.check_arrows().len(), 1);
+        assert_eq!(graph.arrows.len(), 1);
+        graph.calculate(&TimeSettings::default());
+        assert!(
+            graph.sources[0]
+                .output
+                .total_out(&TimeSettings::default())
+                .amount
+                == 0.0
+        );
+    }
+
+    #[test]
+    fn test_arrow_flows() {
+        test_env::setup();
+        for mode in [CalculationMode::Propagation, CalculationMode::Linear] {
+            let mut graph = RecipeGraph::setup_simple_graph().graph;
+            graph.mode = mode;
+            graph.calculate(&TimeSettings::default());
+            for arrow in graph.arrows.iter() {
+                let flow = arrow.flow.as_ref().expect("arrow without flow");
+                assert!(flow.amount > 0.0, "{mode:?}: {flow}");
+                assert!(flow.resource.is_compatible(&arrow.resource));
+            }
+        }
+    }
+
+    #[test]
+    fn test_arrows_load() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_simple_graph().graph;
+        graph.calculate(&TimeSettings::default());
+        assert!(graph.arrows.iter().all(|arrow| !arrow.under_supplied));
+        assert!(graph
+            .arrows
+            .iter()
+            .all(|arrow| arrow.throughput > 0.0 && arrow.throughput <= 1.0));
+        assert!(graph.arrows.iter().any(|arrow| arrow.throughput == 1.0));
+
+        let mut graph = RecipeGraph::setup_rate_limited_graph(2.0).graph;
+        graph.calculate(&TimeSettings::default());
+        let from_source = graph
+            .arrows
+            .iter()
+            .find(|arrow| arrow.start_flow_type == RecipeWindowType::Source)
+            .unwrap();
+        assert!(from_source.under_supplied);
+    }
+
+    #[test]
+    fn test_move_arrow() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_simple_graph().graph;
+        graph.calculate(&TimeSettings::default());
+        let position = graph
+            .arrows
+            .iter()
+            .position(|arrow| arrow.start_flow_type == RecipeWindowType::Source)
+            .unwrap();
+        let mut arrow = graph.arrows.remove(position);
+        arrow.detach(ArrowEdit::MoveStart);
+        assert!(arrow.flow.is_none());
+
+        let recipe = &graph.simple_recipes[0].inner_recipe;
+        assert!(arrow
+            .put_start(
+                recipe.outputs[0].resource(),
+                recipe.id,
+                RecipeWindowType::SimpleRecipe,
+                0
+            )
+            .is_err());
+
+        let source = ResourceSource::new(arrow.resource.name.clone());
+        arrow
+            .put_start(
+                source.output.resource(),
+                source.id,
+                RecipeWindowType::Source,
+                0,
+            )
+            .expect("arrow error");
+        graph.sources = vec![source];
+        graph.arrows.push(arrow);
+        graph.calculate(&TimeSettings::default());
+        assert!(
+            graph.sources[0]
+                .output
+                .total_out(&TimeSettings::default())
+                .amount
+                > 0.0
+        );
+        assert!(graph.arrows.iter().all(|arrow| arrow.flow.is_some()));
+    }
+
+    #[test]
+    fn test_flow_edit() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_simple_graph().graph;
+        graph.sync_arrows();
+        let recipe = &mut graph.simple_recipes[0].inner_recipe;
+        let id = recipe.id;
+        let extra = setup_flow_resource(setup_resource("Slag"), 1, RatePer::Second).flow;
+        recipe
+            .outputs
+            .push(ManageFlow::RecipeOutput(RecipeOutputResource::new(
+                extra.resource.clone(),
+                extra,
+            )));
+
+        assert!(recipe.edit_flow(&Io::Output, 0, FlowEdit::Swap(1)));
+        assert_eq!(graph.sync_arrows(), 0);
+        let to_sink = graph
+            .arrows
+            .iter()
+            .find(|arrow| arrow.start_flow_window == id)
+            .unwrap();
+        assert_eq!(to_sink.start_flow_index, 1);
+        graph.calculate(&TimeSettings::default());
+        assert!(
+            graph.sinks[0]
+                .sink
+                .as_ref()
+                .unwrap()
+                .total_in(&TimeSettings::default())
+                .amount
+                > 0.0
+        );
+
+        let recipe = &mut graph.simple_recipes[0].inner_recipe;
+        assert!(!recipe.edit_flow(&Io::Output, 2, FlowEdit::Remove));
+        let (flow_id, needed) = match &recipe.inputs[0] {
+            ManageFlow::RecipeInput(input) => (input.id, input.needed.clone()),
+            ManageFlow::RecipeOutput(_) => panic!("the first flow should be an input"),
+        };
+        assert!(recipe.edit_flow(&Io::Input, 0, FlowEdit::Flip));
+        assert!(recipe.inputs.is_empty());
+        assert_eq!(recipe.outputs.len(), 3);
+        match &recipe.outputs[2] {
+            ManageFlow::RecipeOutput(output) => {
+                assert_eq!(output.id, flow_id);
+                assert_eq!(output.created, needed);
+            }
+            ManageFlow::RecipeInput(_) => panic!("the flipped flow should be an output"),
+        }
+        assert_eq!(graph.check_arrows().len(), 1);
+
+        let recipe = &mut graph.simple_recipes[0].inner_recipe;
+        assert!(recipe.edit_flow(&Io::Output, 0, FlowEdit::Remove));
+        assert_eq!(graph.sync_arrows(), 0);
+        assert_eq!(graph.arrows[0].start_flow_index, 0);
+        assert_eq!(
+            graph.simple_recipes[0].inner_recipe.outputs[0].resource(),
+            graph.arrows[0].resource
+        );
+        graph.calculate(&TimeSettings::default());
+    }
+
+    #[test]
+    fn test_arrows_migration() {
+        test_env::setup();
+        let graph = RecipeGraph::setup_diamond_graph().graph;
+        let mut save = serde_json::to_value(&graph).unwrap();
+        for arrow in save["arrows"].as_array_mut().unwrap() {
+            let arrow = arrow.as_object_mut().unwrap();
+            arrow.remove("start_flow_id");
+            arrow.remove("end_flow_id");
+        }
+        let mut graph: RecipeGraph = serde_json::from_value(save).unwrap();
+        assert!(graph
+            .arrows
+            .iter()
+            .all(|arrow| arrow.start_flow_id.is_none()));
+
+        assert_eq!(graph.sync_arrows(), 0);
+        for arrow in graph.arrows.iter() {
+            if let Some(recipe) = graph
+                .simple_recipes
+                .iter()
+                .find(|recipe| recipe.inner_recipe.id == arrow.start_flow_window)
+            {
+                let flow = &recipe.inner_recipe.outputs[arrow.start_flow_index];
+                assert_eq!(arrow.start_flow_id, Some(flow.id()));
+            }
+        }
+        graph.calculate(&TimeSettings::default());
+        let test_info = RecipeGraph::setup_diamond_graph();
+        assert_calculated(&graph, &test_info.inputs, &test_info.outputs);
+    }
+
+    #[test]
+    fn test_power_balance() {
+        test_env::setup();
+        let coal = setup_resource("Coal");
+        let ash = setup_resource("Ash");
+
+        for mode in [CalculationMode::Linear, CalculationMode::Target] {
+            let mut graph = RecipeGraph::setup_diamond_graph().graph;
+            graph.mode = mode;
+            graph.sinks[0].targeted = true;
+            graph.sinks[0].target_amount = 2.0;
+            graph.sinks[0].target_rate = RatePer::Minute;
+            //the smelter uses 1W and the generator makes 2W
+            graph.simple_recipes[2].inner_recipe.set_power(60, false);
+            let mut generator = setup_simple_recipe_one_to_one_custom(
+                Some(setup_resource_input(setup_flow_resource(
+                    coal.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+                Some(setup_resource_output(setup_flow_resource(
+                    ash.clone(),
+                    1,
+                    RatePer::Minute,
+                ))),
+            );
+            generator.recipe.inner_recipe.set_power(120, true);
+            let source = ResourceSource::new(coal.name.clone());
+            let sink = ResourceSink::new();
+
+            let generator_id = generator.recipe.inner_recipe.id;
+            connect(
+                &mut graph,
+                &coal,
+                (source.id, RecipeWindowType::Source, 0),
+                (generator_id, RecipeWindowType::SimpleRecipe, 0),
+            );
+            connect(
+                &mut graph,
+                &ash,
+                (generator_id, RecipeWindowType::SimpleRecipe, 0),
+                (sink.id, RecipeWindowType::Sink, 0),
+            );
+            graph.simple_recipes.push(generator.recipe);
+            graph.sources.push(source);
+            graph.sinks.push(sink);
+
+            graph.calculate(&TimeSettings::default());
+            assert!(
+                (graph.power_usage(&TimeSettings::default()) - 6.0).abs() < 1e-3,
+                "{mode}"
+            );
+            assert!(
+                (graph.power_generation(&TimeSettings::default()) - 6.0).abs() < 1e-3,
+                "{mode}"
+            );
+            assert!(!graph.power_deficit(&TimeSettings::default()));
+            assert_calculated(
+                &graph,
+                &[ResourceFlow::new(&coal, 1.0, 3.0, RatePer::Minute)],
+                &[ResourceFlow::new(&ash, 1.0, 3.0, RatePer::Minute)],
+            );
+        }
+    }
+
+    #[test]
+    fn test_byproduct_excess() {
+        test_env::setup();
+        let slag = setup_resource("Slag");
+        let mut graph = RecipeGraph::setup_diamond_graph().graph;
+        graph.simple_recipes[2].inner_recipe.outputs.push(
+            setup_resource_output(setup_flow_resource(slag.clone(), 1, RatePer::Minute))
+                .manage_flow,
+        );
+
+        graph.calculate(&TimeSettings::default());
+        let excess = graph.excess_flows(&TimeSettings::default());
+        assert_eq!(excess.len(), 1);
+        let amount = excess[0]
+            .1
+            .convert_amount(RatePer::Minute, &TimeSettings::default())
+            .unwrap();
+        assert!((amount - 6.0).abs() < 1e-3, "{amount} != 6");
+
+        let sink = ResourceSink::overflow_sink();
+        let smelter_id = graph.simple_recipes[2].inner_recipe.id;
+        connect(
+            &mut graph,
+            &slag,
+            (smelter_id, RecipeWindowType::SimpleRecipe, 1),
+            (sink.id, RecipeWindowType::Sink, 0),
+        );
+        graph.sinks.push(sink);
+
+        graph.calculate(&TimeSettings::default());
+        assert!(graph.excess_flows(&TimeSettings::default()).is_empty());
+        assert_calculated(
+            &graph,
+            &[],
+            &[ResourceFlow::new(&slag, 1.0, 6.0, RatePer::Minute)],
+        );
+    }
+
+    #[test]
+    fn test_linear_over_constrained() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_diamond_graph().graph;
+        let gear = setup_resource("Gear");
+        let gear_source = ResourceSource::limited_source(gear.name.clone(), 100.0, RatePer::Minute);
+        let assembler_id = graph.simple_recipes[0].inner_recipe.id;
+        connect(
+            &mut graph,
+            &gear,
+            (gear_source.id, RecipeWindowType::Source, 0),
+            (assembler_id, RecipeWindowType::SimpleRecipe, 1),
+        );
+        graph.sources.push(gear_source);
+
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.solver_status, SolverStatus::OverConstrained);
+    }
+
+    #[test]
+    fn test_splitter_and_merger() {
+        test_env::setup();
+        let ore = setup_resource("Ore");
+
+        for mode in [CalculationMode::Propagation, CalculationMode::Linear] {
+            let mut graph = RecipeGraph::new();
+            graph.mode = mode;
+            let source = ResourceSource::limited_source(ore.name.clone(), 12.0, RatePer::Minute);
+            let mut splitter = ResourceSplitter::new(ore.clone());
+            splitter.mode = SplitMode::Ratio;
+            splitter.ratios = vec![1.0, 2.0];
+            let first = ResourceSink::new();
+            let second = ResourceSink::new();
+            connect(
+                &mut graph,
+                &ore,
+                (source.id, RecipeWindowType::Source, 0),
+                (splitter.id, RecipeWindowType::Splitter, 0),
+            );
+            for (output, sink) in [&first, &second].iter().enumerate() {
+                connect(
+                    &mut graph,
+                    &ore,
+                    (splitter.id, RecipeWindowType::Splitter, output),
+                    (sink.id, RecipeWindowType::Sink, 0),
+                );
+            }
+            graph.sources.push(source);
+            graph.splitters.push(splitter);
+            graph.sinks.push(first);
+            graph.sinks.push(second);
+
+            graph.calculate(&TimeSettings::default());
+            let first = sink_amount(&graph.sinks[0]);
+            let second = sink_amount(&graph.sinks[1]);
+            assert!((first - 4.0).abs() < 1e-3, "{mode}: {first} != 4");
+            assert!((second - 8.0).abs() < 1e-3, "{mode}: {second} != 8");
+        }
+
+        for mode in [CalculationMode::Propagation, CalculationMode::Linear] {
+            let mut graph = RecipeGraph::new();
+            graph.mode = mode;
+            let first = ResourceSource::limited_source(ore.name.clone(), 3.0, RatePer::Minute);
+            let second = ResourceSource::limited_source(ore.name.clone(), 5.0, RatePer::Minute);
+            let merger = ResourceMerger::new(ore.clone());
+            let sink = ResourceSink::new();
+            for (input, source) in [&first, &second].iter().enumerate() {
+                connect(
+                    &mut graph,
+                    &ore,
+                    (source.id, RecipeWindowType::Source, 0),
+                    (merger.id, RecipeWindowType::Merger, input),
+                );
+            }
+            connect(
+                &mut graph,
+                &ore,
+                (merger.id, RecipeWindowType::Merger, 0),
+                (sink.id, RecipeWindowType::Sink, 0),
+            );
+            graph.sources.push(first);
+            graph.sources.push(second);
+            graph.mergers.push(merger);
+            graph.sinks.push(sink);
+
+            graph.calculate(&TimeSettings::default());
+            let merged = sink_amount(&graph.sinks[0]);
+            assert!((merged - 8.0).abs() < 1e-3, "{mode}: {merged} != 8");
+        }
+    }
+
+    #[test]
+    fn test_allocate() {
+        test_env::setup();
+        let claim = |priority, overflow, demand| Claim {
+            priority,
+            overflow,
+            demand,
+        };
+        let parts = allocate(
+            10.0,
+            &[
+                claim(0, false, 6.0),
+                claim(1, false, 6.0),
+                claim(0, false, 2.0),
+            ],
+        );
+        assert_eq!(parts, vec![3.0, 6.0, 1.0]);
+
+        let parts = allocate(10.0, &[claim(0, true, 0.0), claim(0, false, 4.0)]);
+        assert_eq!(parts, vec![6.0, 4.0]);
+
+        let parts = allocate(9.0, &[claim(0, false, 0.0), claim(0, false, 0.0)]);
+        assert_eq!(parts, vec![4.5, 4.5]);
+    }
+
+    #[test]
+    fn test_competing_consumers() {
+        test_env::setup();
+        let ore = setup_resource("Ore");
+
+        for (priority, overflow) in [(1, false), (0, true)] {
+            let mut graph = RecipeGraph::new();
+            let source = ResourceSource::limited_source(ore.name.clone(), 12.0, RatePer::Minute);
+            let first = ResourceSink::targeted_sink(8.0, RatePer::Minute);
+            let second = ResourceSink::targeted_sink(8.0, RatePer::Minute);
+            for sink in [&first, &second] {
+                connect(
+                    &mut graph,
+                    &ore,
+                    (source.id, RecipeWindowType::Source, 0),
+                    (sink.id, RecipeWindowType::Sink, 0),
+                );
+            }
+            graph.arrows[0].overflow = overflow;
+            graph.arrows[1].priority = priority;
+            graph.sources.push(source);
+            graph.sinks.push(first);
+            graph.sinks.push(second);
+
+            graph.calculate(&TimeSettings::default());
+            let first = sink_amount(&graph.sinks[0]);
+            let second = sink_amount(&graph.sinks[1]);
+            assert!((first - 4.0).abs() < 1e-3, "{first} != 4");
+            assert!((second - 8.0).abs() < 1e-3, "{second} != 8");
+            assert!(graph.arrows[0].starved);
+            assert!(!graph.arrows[1].starved);
+            assert!(!graph.sources[0].over_limit);
+        }
+    }
+
+    #[test]
+    fn test_transport_capacity() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_diamond_graph().graph;
+        let slow = graph
+            .transport
+            .add("Slow belt", 5.0, Unit::Piece, RatePer::Minute);
+        let fast = graph
+            .transport
+            .add("Fast belt", 20.0, Unit::Piece, RatePer::Minute);
+        let pipe = graph
+            .transport
+            .add("Pipe", 100.0, Unit::Liter, RatePer::Minute);
+        //the ore source gives 12/min
+        graph.arrows[0].tier = Some(slow);
+        graph.arrows[1].tier = Some(fast);
+        graph.arrows[2].tier = Some(pipe);
+
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.arrows[0].lanes, Some(("Slow belt".to_string(), 3)));
+        assert_eq!(graph.arrows[1].lanes, Some(("Fast belt".to_string(), 1)));
+        assert_eq!(graph.arrows[2].lanes, None, "A pipe doesn't carry pieces");
+        assert_eq!(graph.over_capacity_arrows().len(), 1);
+
+        graph.transport.remove(slow);
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.arrows[0].lanes, None);
+        assert!(graph.over_capacity_arrows().is_empty());
+    }
+
+    #[test]
+    fn test_grouped_sources_and_sinks() {
+        test_env::setup();
+        let ore = setup_resource("Ore");
+        let coal = setup_resource("Coal");
+        let mut graph = RecipeGraph::new();
+        let outpost = egui::Id::new("Outpost");
+        let storage = egui::Id::new("Storage");
+
+        for (resource, amount) in [(&ore, 12.0), (&coal, 3.0)] {
+            let mut source =
+                ResourceSource::limited_source(resource.name.clone(), amount, RatePer::Minute);
+            source.group = Some(outpost);
+            let sink = ResourceSink::in_group(storage);
+            connect(
+                &mut graph,
+                resource,
+                (source.id, RecipeWindowType::Source, 0),
+                (sink.id, RecipeWindowType::Sink, 0),
+            );
+            graph.sources.push(source);
+            graph.sinks.push(sink);
+        }
+
+        let saved = serde_json::to_string(&graph).expect("graph not saved");
+        let mut graph: RecipeGraph = serde_json::from_str(&saved).expect("graph not loaded");
+        assert!(graph.sources.iter().all(|s| s.group == Some(outpost)));
+        assert!(graph.sinks.iter().all(|s| s.group == Some(storage)));
+
+        graph.calculate(&TimeSettings::default());
+        assert_calculated(
+            &graph,
+            &[
+                ResourceFlow::new(&ore, 1.0, 12.0, RatePer::Minute),
+                ResourceFlow::new(&coal, 1.0, 3.0, RatePer::Minute),
+            ],
+            &[
+                ResourceFlow::new(&ore, 1.0, 12.0, RatePer::Minute),
+                ResourceFlow::new(&coal, 1.0, 3.0, RatePer::Minute),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_source_extraction() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_diamond_graph().graph;
+        graph.sources[0].extraction = Some(Extraction {
+            machine: "Miner".to_string(),
+            machine_amount: 5.0,
+            machine_rate: RatePer::Minute,
+            machines_per_deposit: 2,
+            purity: 2.0,
+            deposit: Some(7200.0),
+        });
+
+        graph.calculate(&TimeSettings::default());
+        //12 ore per minute drawn by miners extracting 10 per minute from the pure deposit
+        let source = &graph.sources[0];
+        let extraction = source.extraction.as_ref().unwrap();
+        let unit = source.output.resource().unit;
+        assert!(
+            (source.drawn - 720.0).abs() < 1e-3,
+            "{} != 720",
+            source.drawn
+        );
+        assert!(
+            (extraction.machines(unit, source.drawn, &TimeSettings::default()) - 1.2).abs() < 1e-6
+        );
+        assert!((extraction.hourly_capacity(unit, &TimeSettings::default()) - 1200.0).abs() < 1e-6);
+        let hours = extraction.depletion_hours(unit, source.drawn).unwrap();
+        assert!((hours - 10.0).abs() < 1e-6, "{hours} != 10");
+        assert_eq!(extraction.depletion_hours(unit, 0.0), None);
+    }
+
+    #[test]
+    fn test_uses_rate() {
+        test_env::setup();
+        let mut graph = RecipeGraph::setup_diamond_graph().graph;
+        assert!(!graph.uses_rate(RatePer::Custom(0)));
+        assert!(graph.uses_rate(RatePer::Minute));
+
+        graph.sources[0].limit_rate = RatePer::Custom(0);
+        assert!(graph.uses_rate(RatePer::Custom(0)));
+        assert!(!graph.uses_rate(RatePer::Custom(1)));
+
+        graph.sinks[0].target_rate = RatePer::Custom(1);
+        assert!(graph.uses_rate(RatePer::Custom(1)));
+
+        graph
+            .transport
+            .add("Belt", 15.0, Unit::Piece, RatePer::Custom(2));
+        assert!(graph.uses_rate(RatePer::Custom(2)));
+
+        let mut outer = RecipeGraph::new();
+        outer
+            .compound_recipes
+            .push(CompoundRecipeWindow::new(graph, &TimeSettings::default()));
+        assert!(outer.uses_rate(RatePer::Custom(0)));
+        assert!(!outer.uses_rate(RatePer::Custom(3)));
+    }
+
+    #[test]
+    fn test_loop_calculation() {
+        test_env::setup();
+        let test_info = RecipeGraph::setup_loop_graph();
+        let mut graph = test_info.graph;
+
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.loops.len(), 1, "The recycling loop wasn't found");
+        assert!(graph
+            .simple_recipes
+            .iter()
+            .all(|recipe| recipe.inner_recipe.in_loop));
+        assert_eq!(graph.solver_status, SolverStatus::Feasible);
+        assert_calculated(&graph, &test_info.inputs, &test_info.outputs);
+    }
+
+    #[test]
+    fn test_target_calculation() {
+        test_env::setup();
+        let test_info = RecipeGraph::setup_diamond_graph();
+        let ore = test_info.inputs[0].resource.clone();
+        let circuit = test_info.outputs[0].resource.clone();
+        let mut graph = test_info.graph;
+        graph.mode = CalculationMode::Target;
+        graph.sinks[0].targeted = true;
+        graph.sinks[0].target_rate = RatePer::Minute;
+
+        graph.sinks[0].target_amount = 1.0;
+        graph.calculate(&TimeSettings::default());
+        assert_calculated(
+            &graph,
+            &[ResourceFlow::new(&ore, 2.0, 6.0, RatePer::Minute)],
+            &[ResourceFlow::new(&circuit, 1.0, 1.0, RatePer::Minute)],
+        );
+        assert!(!graph.sources[0].over_limit);
+
+        graph.sinks[0].target_amount = 4.0;
+        graph.calculate(&TimeSettings::default());
+        assert_calculated(
+            &graph,
+            &[ResourceFlow::new(&ore, 2.0, 24.0, RatePer::Minute)],
+            &[ResourceFlow::new(&circuit, 1.0, 4.0, RatePer::Minute)],
+        );
+        assert!(graph.sources[0].over_limit);
+    }
+
+    #[test]
+    fn test_target_loop_calculation() {
+        test_env::setup();
+        let test_info = RecipeGraph::setup_loop_graph();
+        let ore = test_info.inputs[0].resource.clone();
+        let metal = test_info.outputs[0].resource.clone();
+        let mut graph = test_info.graph;
+        graph.mode = CalculationMode::Target;
+        graph.sinks[0].targeted = true;
+        graph.sinks[0].target_amount = 20.0;
+        graph.sinks[0].target_rate = RatePer::Minute;
+
+        graph.calculate(&TimeSettings::default());
+        assert_eq!(graph.solver_status, SolverStatus::Feasible);
+        assert_calculated(
+            &graph,
+            &[ResourceFlow::new(&ore, 2.0, 20.0, RatePer::Minute)],
+            &[ResourceFlow::new(&metal, 1.0, 20.0, RatePer::Minute)],
+        );
+        assert!(graph.sources[0].over_limit);
+    }
+
     pub(crate) fn setup_test_graphs() -> [TestInfo; 9] {
         [
             RecipeGraph::setup_empty_graph(),
@@ -1309,6 +3998,8 @@ enum FlowCalculatorType {
 
 #[derive(Copy, Clone, Debug)]
 struct FlowCalculatorHelper {
+    arrow_index: usize,
+
     start_window_index: usize,
     start_flow_index: usize,
 
@@ -0,0 +1,334 @@
+use crate::utils::rational::Rational;
+use crate::utils::Number;
+use log::{debug, trace};
+use num_traits::{Signed, Zero};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+///Coefficients smaller than this are considered to be zero during the elimination
+const EPSILON: f64 = 1e-9;
+
+///Result of the resolution of a linear system
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SolverStatus {
+    ///The system has not been solved yet
+    #[default]
+    NotSolved,
+
+    ///The system has exactly one solution
+    Feasible,
+
+    ///The system has some free variables, they are set to their default value, the number of free
+    /// variables is given
+    UnderConstrained(usize),
+
+    ///The equations of the system contradict each other or only have a negative solution
+    OverConstrained,
+
+    ///A value of the elimination can't be represented, the solution can't be used
+    Overflow,
+}
+
+impl Display for SolverStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolverStatus::NotSolved => write!(f, "Not solved"),
+            SolverStatus::Feasible => write!(f, "Feasible"),
+            SolverStatus::UnderConstrained(free) => {
+                write!(f, "Under-constrained ({free} free rates)")
+            }
+            SolverStatus::OverConstrained => write!(f, "Over-constrained"),
+            SolverStatus::Overflow => write!(f, "Overflow"),
+        }
+    }
+}
+
+///A number a linear system can be solved with
+pub(crate) trait Scalar: Number + Signed {
+    ///Indicate a value is small enough to be considered zero during the elimination
+    fn negligible(&self) -> bool;
+
+    ///Indicate a value could be represented, the overflows give invalid values
+    fn representable(&self) -> bool;
+}
+
+impl Scalar for f64 {
+    fn negligible(&self) -> bool {
+        self.abs() < EPSILON
+    }
+
+    fn representable(&self) -> bool {
+        self.is_finite()
+    }
+}
+
+///The rationals are exact, only zero is negligible
+impl Scalar for Rational {
+    fn negligible(&self) -> bool {
+        self.is_zero()
+    }
+
+    fn representable(&self) -> bool {
+        self.is_valid()
+    }
+}
+
+///A linear equation: the sum of the coefficients times the variables is equal to the constant
+#[derive(Debug, Clone)]
+struct Equation<S> {
+    coefficients: Vec<(usize, S)>,
+    constant: S,
+}
+
+///Solution of a linear system
+#[derive(Debug, Clone)]
+pub(crate) struct LinearSolution<S = f64> {
+    ///value of each variable
+    pub values: Vec<S>,
+
+    ///status of the resolution
+    pub status: SolverStatus,
+}
+
+///A system of linear equations solved by Gauss-Jordan elimination
+#[derive(Debug, Clone)]
+pub(crate) struct LinearSystem<S = f64> {
+    variables: usize,
+    equations: Vec<Equation<S>>,
+}
+
+impl<S: Scalar> LinearSystem<S> {
+    pub(crate) fn new(variables: usize) -> Self {
+        Self {
+            variables,
+            equations: vec![],
+        }
+    }
+
+    /// Add an equation to the system
+    ///
+    /// # Arguments
+    ///
+    /// * `coefficients`: pairs of variable index and coefficient, a variable can appear many times
+    /// * `constant`: the right side of the equation
+    ///
+    /// returns: ()
+    pub(crate) fn add_equation(&mut self, coefficients: Vec<(usize, S)>, constant: S) {
+        self.equations.push(Equation {
+            coefficients,
+            constant,
+        });
+    }
+
+    /// Solve the system, the free variables of an under constrained system take their default
+    ///
+    /// # Arguments
+    ///
+    /// * `defaults`: default value of every variable
+    ///
+    /// returns: LinearSolution
+    pub(crate) fn solve(&self, defaults: &[S]) -> LinearSolution<S> {
+        trace!(
+            "[START] solve {} equations with {} variables",
+            self.equations.len(),
+            self.variables
+        );
+        let n = self.variables;
+        let mut rows = self.build_rows();
+
+        let mut pivots = vec![];
+        for col in 0..n {
+            let row = pivots.len();
+            if row >= rows.len() {
+                break;
+            }
+
+            let best = (row..rows.len())
+                .max_by(|a, b| {
+                    let (a, b) = (rows[*a][col].abs(), rows[*b][col].abs());
+                    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                })
+                .unwrap_or(row);
+            if rows[best][col].negligible() {
+                continue;
+            }
+            rows.swap(row, best);
+
+            let pivot = rows[row][col];
+            for value in rows[row].iter_mut() {
+                *value = *value / pivot;
+            }
+
+            let pivot_row = rows[row].clone();
+            for (other, other_row) in rows.iter_mut().enumerate() {
+                let factor = other_row[col];
+                if other == row || factor.is_zero() {
+                    continue;
+                }
+                for (value, pivot_value) in other_row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                    *value = *value - factor * *pivot_value;
+                }
+            }
+            pivots.push(col);
+        }
+
+        if rows.iter().flatten().any(|value| !value.representable()) {
+            debug!("Linear system not solved, a value overflowed");
+            trace!("[END] solve");
+            return LinearSolution {
+                values: defaults.to_vec(),
+                status: SolverStatus::Overflow,
+            };
+        }
+
+        let rank = pivots.len();
+        let inconsistent = rows[rank..].iter().any(|row| !row[n].negligible());
+
+        let mut values: Vec<S> = (0..n)
+            .map(|i| defaults.get(i).copied().unwrap_or_else(S::zero))
+            .collect();
+        for (row, col) in pivots.iter().enumerate() {
+            let free_sum = (0..n)
+                .filter(|c| !pivots.contains(c))
+                .fold(S::zero(), |sum, c| sum + rows[row][c] * values[c]);
+            values[*col] = rows[row][n] - free_sum;
+        }
+
+        let negative = values.iter().any(|v| v.is_negative() && !v.negligible());
+        let status = if values.iter().any(|v| !v.representable()) {
+            SolverStatus::Overflow
+        } else if inconsistent || negative {
+            SolverStatus::OverConstrained
+        } else if rank < n {
+            SolverStatus::UnderConstrained(n - rank)
+        } else {
+            SolverStatus::Feasible
+        };
+
+        debug!("Linear system solved, rank={rank}, status={status}");
+        trace!("[END] solve");
+        LinearSolution { values, status }
+    }
+
+    ///build the augmented matrix, every row scaled so its biggest coefficient is one
+    fn build_rows(&self) -> Vec<Vec<S>> {
+        let n = self.variables;
+        self.equations
+            .iter()
+            .map(|equation| {
+                let mut row = vec![S::zero(); n + 1];
+                for (variable, coefficient) in equation.coefficients.iter() {
+                    row[*variable] += *coefficient;
+                }
+                row[n] = equation.constant;
+
+                let scale = row[..n]
+                    .iter()
+                    .fold(S::zero(), |max, v| match v.abs() > max {
+                        true => v.abs(),
+                        false => max,
+                    });
+                if !scale.negligible() {
+                    for value in row.iter_mut() {
+                        *value = *value / scale;
+                    }
+                }
+                row
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::recipe_graph::linear_system::{LinearSystem, SolverStatus};
+    use crate::utils::rational::Rational;
+    use crate::utils::test_env;
+    use num_traits::Zero;
+
+    fn assert_values(expected: &[f64], result: &[f64]) {
+        for (expected, result) in expected.iter().zip(result.iter()) {
+            assert!(
+                (expected - result).abs() < 1e-6,
+                "expected {expected} got {result}"
+            );
+        }
+    }
+
+    //-------------------Tests-------------------
+
+    #[test]
+    fn test_feasible() {
+        test_env::setup();
+        let mut system = LinearSystem::new(2);
+        system.add_equation(vec![(0, 1.0), (1, 1.0)], 3.0);
+        system.add_equation(vec![(0, 2.0), (1, -1.0)], 0.0);
+
+        let solution = system.solve(&[0.0, 0.0]);
+        assert_eq!(solution.status, SolverStatus::Feasible);
+        assert_values(&[1.0, 2.0], &solution.values);
+    }
+
+    #[test]
+    fn test_under_constrained() {
+        test_env::setup();
+        let mut system = LinearSystem::new(3);
+        system.add_equation(vec![(0, 1.0), (2, -2.0)], 0.0);
+        system.add_equation(vec![(1, 1.0), (2, -1.0)], 0.0);
+
+        let solution = system.solve(&[0.0, 0.0, 1.5]);
+        assert_eq!(solution.status, SolverStatus::UnderConstrained(1));
+        assert_values(&[3.0, 1.5, 1.5], &solution.values);
+    }
+
+    #[test]
+    fn test_over_constrained() {
+        test_env::setup();
+        let mut system = LinearSystem::new(1);
+        system.add_equation(vec![(0, 1.0)], 1.0);
+        system.add_equation(vec![(0, 2.0)], 4.0);
+
+        let solution = system.solve(&[0.0]);
+        assert_eq!(solution.status, SolverStatus::OverConstrained);
+
+        let mut system = LinearSystem::new(2);
+        system.add_equation(vec![(0, 1.0), (1, 1.0)], 1.0);
+        system.add_equation(vec![(0, 1.0)], 2.0);
+
+        let solution = system.solve(&[0.0, 0.0]);
+        assert_eq!(solution.status, SolverStatus::OverConstrained);
+    }
+
+    #[test]
+    fn test_exact() {
+        test_env::setup();
+        let r = Rational::from_integer;
+        let mut system = LinearSystem::new(2);
+        system.add_equation(vec![(0, r(3)), (1, r(1))], r(7));
+        system.add_equation(vec![(0, r(1)), (1, r(-2))], r(0));
+
+        let solution = system.solve(&[r(0), r(0)]);
+        assert_eq!(solution.status, SolverStatus::Feasible);
+        assert_eq!(
+            solution.values,
+            vec![Rational::new(2, 1), Rational::new(1, 1)]
+        );
+
+        let mut system = LinearSystem::new(1);
+        system.add_equation(vec![(0, r(6))], r(14));
+        let solution = system.solve(&[r(0)]);
+        assert_eq!(solution.values, vec![Rational::new(7, 3)]);
+    }
+
+    #[test]
+    fn test_exact_overflow() {
+        test_env::setup();
+        let huge = Rational::new(i128::MAX / 3, 7);
+        let mut system = LinearSystem::new(2);
+        system.add_equation(vec![(0, huge), (1, Rational::new(1, i128::MAX / 5))], huge);
+        system.add_equation(vec![(0, Rational::new(3, 11)), (1, huge)], huge);
+
+        let solution = system.solve(&[Rational::zero(), Rational::zero()]);
+        assert_eq!(solution.status, SolverStatus::Overflow);
+    }
+}
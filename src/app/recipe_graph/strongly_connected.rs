@@ -0,0 +1,121 @@
+///State of Tarjan's algorithm
+struct Tarjan<'a> {
+    adjacency: &'a [Vec<usize>],
+    index: usize,
+    indexes: Vec<Option<usize>>,
+    low_links: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    components: Vec<Vec<usize>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn new(adjacency: &'a [Vec<usize>]) -> Self {
+        let n = adjacency.len();
+        Self {
+            adjacency,
+            index: 0,
+            indexes: vec![None; n],
+            low_links: vec![0; n],
+            on_stack: vec![false; n],
+            stack: vec![],
+            components: vec![],
+        }
+    }
+
+    fn visit(&mut self, node: usize) {
+        self.indexes[node] = Some(self.index);
+        self.low_links[node] = self.index;
+        self.index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for next in self.adjacency[node].iter().copied() {
+            match self.indexes[next] {
+                None => {
+                    self.visit(next);
+                    self.low_links[node] = self.low_links[node].min(self.low_links[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.low_links[node] = self.low_links[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low_links[node]) == self.indexes[node] {
+            let mut component = vec![];
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            component.reverse();
+            self.components.push(component);
+        }
+    }
+}
+
+/// Find the strongly connected components of a directed graph using Tarjan's algorithm
+///
+/// # Arguments
+///
+/// * `adjacency`: for every node, the list of nodes it has an edge toward
+///
+/// returns: the components in topological order, edges only go from a component to a later one
+pub(crate) fn strongly_connected_components(adjacency: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut tarjan = Tarjan::new(adjacency);
+    for node in 0..adjacency.len() {
+        if tarjan.indexes[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+    let mut components = tarjan.components;
+    components.reverse();
+    components
+}
+
+///Indicate if a component forms a loop, either several nodes or a node feeding itself
+pub(crate) fn is_loop(component: &[usize], adjacency: &[Vec<usize>]) -> bool {
+    match component {
+        [node] => adjacency[*node].contains(node),
+        _ => !component.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::recipe_graph::strongly_connected::{is_loop, strongly_connected_components};
+    use crate::utils::test_env;
+
+    //-------------------Tests-------------------
+
+    #[test]
+    fn test_chain_order() {
+        test_env::setup();
+        let adjacency = vec![vec![], vec![0], vec![1]];
+        let components = strongly_connected_components(&adjacency);
+        assert_eq!(components, vec![vec![2], vec![1], vec![0]]);
+        assert!(components.iter().all(|c| !is_loop(c, &adjacency)));
+    }
+
+    #[test]
+    fn test_loops() {
+        test_env::setup();
+        // 0 -> 1 -> 2 -> 1, 2 -> 3, 3 -> 3
+        let adjacency = vec![vec![1], vec![2], vec![1, 3], vec![3]];
+        let components = strongly_connected_components(&adjacency);
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0], vec![0]);
+        let mut middle = components[1].clone();
+        middle.sort();
+        assert_eq!(middle, vec![1, 2]);
+        assert_eq!(components[2], vec![3]);
+
+        assert!(!is_loop(&components[0], &adjacency));
+        assert!(is_loop(&components[1], &adjacency));
+        assert!(is_loop(&components[2], &adjacency));
+    }
+}
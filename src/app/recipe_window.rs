@@ -1,12 +1,16 @@
 use crate::app::commons::CommonsManager;
-use crate::app::resources::RatePer;
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{Dimension, RatePer, Unit};
+use egui::Widget;
 use std::f32;
 
 pub(crate) mod arrow_flow;
 pub(crate) mod base_recipe_window;
 pub(crate) mod compound_recipe_window;
 pub(crate) mod resource_adding_window;
+pub(crate) mod resource_merger;
 pub(crate) mod resource_sink;
+pub(crate) mod resource_splitter;
 pub(crate) mod resources_sources;
 pub(crate) mod simple_recipe_window;
 
@@ -22,33 +26,116 @@ pub trait RecipeWindowGUI {
     ///
     fn show(&mut self, commons: &mut CommonsManager, ctx: &egui::Context, enabled: bool) -> bool;
 
-    fn generate_tooltip(&self) -> Result<String, std::fmt::Error>;
+    fn generate_tooltip(&self, settings: &TimeSettings) -> Result<String, std::fmt::Error>;
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Copy, Clone, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RecipeWindowType {
     SimpleRecipe,
     CompoundRecipe,
     Source,
     Sink,
+    Splitter,
+    Merger,
 }
 
-fn rate_combo(ui: &mut egui::Ui, rate: &mut RatePer) {
+/// Combo box to pick the rate of an amount
+///
+/// # Arguments
+///
+/// * `ui`: where to show the combo
+/// * `rate`: the rate picked
+/// * `settings`: give the custom time units
+///
+/// returns: `bool` flag indicating the rate changed
+fn rate_combo(ui: &mut egui::Ui, rate: &mut RatePer, settings: &TimeSettings) -> bool {
+    let mut changed = false;
     egui::ComboBox::from_label("Time unit")
-        .selected_text(format!("{rate:?}"))
+        .selected_text(rate.name(settings))
         .show_ui(ui, |ui| {
-            ui.selectable_value(rate, RatePer::Tick, "Tick");
-            ui.selectable_value(rate, RatePer::Second, "Second");
-            ui.selectable_value(rate, RatePer::Minute, "Minute");
-            ui.selectable_value(rate, RatePer::Hour, "Hour");
+            for r in RatePer::all(settings) {
+                changed |= ui.selectable_value(rate, r, r.name(settings)).changed();
+            }
         });
+    changed
 }
 
-fn text_edit(ui: &mut egui::Ui, text: &mut String) {
-    let text_len = text.len();
-    egui::TextEdit::singleline(text)
-        .desired_width((text_len * 7) as f32)
-        .show(ui);
+/// Combo box to pick a unit, the size of a stack is edited next to it
+///
+/// # Arguments
+///
+/// * `ui`: where to show the combo
+/// * `id_source`: makes the combo unique
+/// * `unit`: the unit picked
+/// * `dimension`: only the units measuring it are proposed, every unit if None
+///
+/// returns: `bool` flag indicating the unit changed
+pub(crate) fn unit_combo(
+    ui: &mut egui::Ui,
+    id_source: impl std::hash::Hash,
+    unit: &mut Unit,
+    dimension: Option<Dimension>,
+) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(unit.name())
+        .show_ui(ui, |ui| {
+            for u in Unit::all() {
+                if dimension.map_or(false, |d| d != u.dimension()) {
+                    continue;
+                }
+                let selected = std::mem::discriminant(unit) == std::mem::discriminant(&u);
+                if ui.selectable_label(selected, u.name()).clicked() && !selected {
+                    *unit = u;
+                    changed = true;
+                }
+            }
+        });
+    if let Unit::Stack(size) = unit {
+        changed |= egui::DragValue::new(size)
+            .clamp_range(1..=u32::MAX)
+            .prefix("×")
+            .ui(ui)
+            .changed();
+    }
+    changed
+}
+
+/// Edit of the resource name of a flow, the name is given only once the edit is left so the
+/// flow isn't changed at every key typed
+///
+/// # Arguments
+///
+/// * `ui`: where to show the edit
+/// * `commons`: its catalog proposes the names
+/// * `id`: unique id of the edit
+/// * `name`: the current name of the resource
+///
+/// returns: the new name when the edit is left, None if it's unchanged
+pub(crate) fn resource_name_edit(
+    ui: &mut egui::Ui,
+    commons: &CommonsManager,
+    id: egui::Id,
+    name: &str,
+) -> Option<String> {
+    let mut edited = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .unwrap_or_else(|| name.to_string());
+    let response = ui
+        .scope(|ui| {
+            ui.spacing_mut().text_edit_width = (edited.len() * 7).max(60) as f32;
+            commons
+                .resources
+                .autocomplete(ui, &mut edited, "resource name")
+        })
+        .inner;
+    if response.has_focus() {
+        ui.data_mut(|d| d.insert_temp(id, edited));
+        return None;
+    }
+    ui.data_mut(|d| d.remove::<String>(id));
+    let edited = edited.trim();
+    (!edited.is_empty() && edited != name).then(|| edited.to_string())
 }
 
 #[cfg(test)]
@@ -63,10 +150,9 @@ pub mod test {
     use std::fmt::Write;
 
     pub(crate) struct ManageFlowTestInfo {
-        pub manage_flow: ManageFlow<usize>,
+        pub manage_flow: ManageFlow<f32>,
         pub flow: resource_flow::test::TestInfo,
     }
-    #[deprecated]
     pub(crate) fn setup_resource_a_input(amount: Option<usize>) -> ManageFlowTestInfo {
         let flow = setup_flow_resource_a(amount);
         let manage_flow = ManageFlow::RecipeInput(RecipeInputResource::new(
@@ -76,18 +162,7 @@ pub mod test {
 
         ManageFlowTestInfo { manage_flow, flow }
     }
-    #[deprecated]
-    pub(crate) fn setup_resource_a_output(amount: Option<usize>) -> ManageFlowTestInfo {
-        let flow = setup_flow_resource_a(amount);
-        let manage_flow = ManageFlow::RecipeOutput(RecipeOutputResource::new(
-            flow.flow.resource.clone(),
-            flow.flow.clone(),
-        ));
-
-        ManageFlowTestInfo { manage_flow, flow }
-    }
 
-    #[deprecated]
     pub(crate) fn setup_resource_b_input(
         amount: Option<usize>,
         rate: Option<RatePer>,
@@ -100,7 +175,6 @@ pub mod test {
 
         ManageFlowTestInfo { manage_flow, flow }
     }
-    #[deprecated]
     pub(crate) fn setup_resource_b_output(amount: Option<usize>) -> ManageFlowTestInfo {
         let flow = setup_flow_resource_b(amount, None);
         let manage_flow = ManageFlow::RecipeOutput(RecipeOutputResource::new(
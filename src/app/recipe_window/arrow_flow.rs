@@ -1,7 +1,20 @@
 use crate::app::commons::CommonsManager;
 use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
+use crate::app::resources::resource_flow::ResourceFlow;
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::transport::{TierId, TransportLibrary};
 use crate::app::resources::{FlowError, FlowErrorType, ResourceDefinition};
-use crate::utils::gen_id;
+use crate::utils::{formatting, gen_id};
+use std::fmt::Write;
+
+///Distance from the line under which the pointer is on an arrow
+const HIT_DISTANCE: f32 = 6.0;
+
+///Width of an arrow without flow, and of the arrows being placed
+const MIN_WIDTH: f32 = 3.0;
+
+///Width of the arrow carrying the largest flow of the graph
+const MAX_WIDTH: f32 = 10.0;
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
 pub struct ArrowFlow {
@@ -17,28 +30,97 @@ pub struct ArrowFlow {
     pub(crate) start_flow_index: usize,
     pub(crate) end_flow_index: usize,
 
+    ///stable id of the flow the arrow starts from, the index is found again from it
+    #[serde(default)]
+    pub(crate) start_flow_id: Option<egui::Id>,
+
+    ///stable id of the flow the arrow ends in
+    #[serde(default)]
+    pub(crate) end_flow_id: Option<egui::Id>,
+
     pub(crate) layer_id: egui::LayerId,
+
+    ///order in which the arrows from the same output are served when it can't feed them all,
+    /// the highest first
+    #[serde(default)]
+    pub(crate) priority: u8,
+
+    ///the arrow only gets what the other arrows from the same output leave, like an overflow belt
+    #[serde(default)]
+    pub(crate) overflow: bool,
+
+    ///transport tier carrying the flow, its capacity limits the arrow
+    #[serde(default)]
+    pub(crate) tier: Option<TierId>,
+
+    ///flow carried by the arrow at the last calculation
+    #[serde(skip)]
+    pub(crate) flow: Option<ResourceFlow<f32, f32>>,
+
+    ///share of the largest flow of the graph carried by the arrow, from 0 to 1
+    #[serde(skip)]
+    pub(crate) throughput: f32,
+
+    ///the input at the end of the arrow gets less than it needs
+    #[serde(skip)]
+    pub(crate) under_supplied: bool,
+
+    ///the arrow gets less than its end needs while sharing its output with other arrows
+    #[serde(skip)]
+    pub(crate) starved: bool,
+
+    ///name of the transport tier and number of its lanes needed at the last calculation
+    #[serde(skip)]
+    pub(crate) lanes: Option<(String, u32)>,
+
+    ///ends of the line last painted, an arrow is only hit when anchored
+    #[serde(skip)]
+    segment: Option<[egui::Pos2; 2]>,
+}
+
+///Change asked on an anchored arrow from its menu
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ArrowEdit {
+    ///Delete the arrow
+    Remove,
+    ///Detach the start to connect it to another output
+    MoveStart,
+    ///Detach the end to connect it to another input
+    MoveEnd,
+    ///Set the priority of the arrow
+    Priority(u8),
+    ///Set whether the arrow only gets the overflow of its output
+    Overflow(bool),
+    ///Set the transport tier of the arrow
+    Tier(Option<TierId>),
 }
 
 impl RecipeWindowGUI for ArrowFlow {
     fn show(&mut self, commons: &mut CommonsManager, ctx: &egui::Context, enabled: bool) -> bool {
         let painter = ctx.layer_painter(self.layer_id);
 
-        let start_coordinate_info = commons.window_coordinates.get(&self.start_flow_window);
-        let start_point = match start_coordinate_info {
-            None => return false,
-            Some(r) => {
-                let start_rect = r.window;
-                let flow_rect = r.out_flow.get(self.start_flow_index);
-                match flow_rect {
-                    None => egui::Pos2 {
-                        x: start_rect.max.x,
-                        y: (start_rect.max.y - start_rect.min.y) / 2.0 + start_rect.min.y,
-                    },
-                    Some(rect) => egui::Pos2 {
-                        x: start_rect.max.x,
-                        y: (rect.max.y - rect.min.y) / 2.0 + rect.min.y,
-                    },
+        let start_point = match self.state {
+            ArrowUsageState::ActiveStart => ctx
+                .pointer_hover_pos()
+                .unwrap_or(egui::Pos2::new(10.0, 10.0)),
+            ArrowUsageState::Active | ArrowUsageState::Anchored => {
+                let start_coordinate_info = commons.window_coordinates.get(&self.start_flow_window);
+                match start_coordinate_info {
+                    None => return false,
+                    Some(r) => {
+                        let start_rect = r.window;
+                        let flow_rect = r.out_flow.get(self.start_flow_index);
+                        match flow_rect {
+                            None => egui::Pos2 {
+                                x: start_rect.max.x,
+                                y: (start_rect.max.y - start_rect.min.y) / 2.0 + start_rect.min.y,
+                            },
+                            Some(rect) => egui::Pos2 {
+                                x: start_rect.max.x,
+                                y: (rect.max.y - rect.min.y) / 2.0 + rect.min.y,
+                            },
+                        }
+                    }
                 }
             }
         };
@@ -46,7 +128,7 @@ impl RecipeWindowGUI for ArrowFlow {
             ArrowUsageState::Active => ctx
                 .pointer_hover_pos()
                 .unwrap_or(egui::Pos2::new(10.0, 10.0)),
-            ArrowUsageState::Anchored => {
+            ArrowUsageState::Anchored | ArrowUsageState::ActiveStart => {
                 let end_rect = commons
                     .window_coordinates
                     .get(self.end_flow_window.as_ref().unwrap());
@@ -70,25 +152,77 @@ impl RecipeWindowGUI for ArrowFlow {
             }
         };
 
-        let color = match enabled {
-            true => egui::Color32::GRAY,
-            false => egui::Color32::BLACK,
+        let color = match (enabled, self.starved, self.under_supplied) {
+            (true, true, _) => egui::Color32::from_rgb(255, 140, 0),
+            (true, false, true) => egui::Color32::RED,
+            (true, false, false) if self.over_capacity() => egui::Color32::YELLOW,
+            (true, false, false) => egui::Color32::GRAY,
+            (false, _, _) => egui::Color32::BLACK,
         };
 
+        self.segment = None;
         match self.state {
             ArrowUsageState::Active => {
                 commons.arrow_active = true;
             }
-            ArrowUsageState::Anchored => {}
+            ArrowUsageState::ActiveStart => {}
+            ArrowUsageState::Anchored => self.segment = Some([start_point, end_point]),
         }
 
-        painter.line_segment([start_point, end_point], egui::Stroke::new(5.0, color));
+        painter.line_segment(
+            [start_point, end_point],
+            egui::Stroke::new(self.width(), color),
+        );
+
+        if let ArrowUsageState::Anchored = self.state {
+            let label = match self.flow {
+                None => self.resource.name.clone(),
+                Some(_) => self
+                    .generate_tooltip(&commons.time_settings)
+                    .unwrap_or_else(|_| self.resource.name.clone()),
+            };
+            painter.text(
+                start_point + (end_point - start_point) / 2.0,
+                egui::Align2::CENTER_BOTTOM,
+                label,
+                egui::FontId::proportional(12.0),
+                ctx.style().visuals.text_color(),
+            );
+        }
 
         true
     }
 
-    fn generate_tooltip(&self) -> Result<String, std::fmt::Error> {
-        Ok("".to_string())
+    fn generate_tooltip(&self, settings: &TimeSettings) -> Result<String, std::fmt::Error> {
+        let mut tooltip = String::new();
+        match &self.flow {
+            None => write!(tooltip, "{}, no flow calculated", self.resource.name)?,
+            Some(flow) => {
+                let flow = flow.displayed(settings);
+                write!(
+                    tooltip,
+                    "{}: {} {}{}",
+                    self.resource.name,
+                    formatting::float_format(flow.amount, 3),
+                    flow.resource.unit.symbol(),
+                    flow.rate.to_shortened_string(settings)
+                )?;
+            }
+        }
+        if self.overflow {
+            write!(tooltip, ", overflow")?;
+        } else if self.priority > 0 {
+            write!(tooltip, ", priority {}", self.priority)?;
+        }
+        if self.starved {
+            write!(tooltip, ", starved")?;
+        }
+        match &self.lanes {
+            Some((tier, 1)) => write!(tooltip, ", {tier}")?,
+            Some((tier, lanes)) => write!(tooltip, ", over capacity: {lanes} × {tier} needed")?,
+            None => {}
+        }
+        Ok(tooltip)
     }
 }
 
@@ -110,10 +244,177 @@ impl ArrowFlow {
             end_flow_type: None,
             start_flow_index: flow_index,
             end_flow_index: 0,
+            start_flow_id: None,
+            end_flow_id: None,
             layer_id,
+            priority: 0,
+            overflow: false,
+            tier: None,
+            flow: None,
+            throughput: 0.0,
+            under_supplied: false,
+            starved: false,
+            lanes: None,
+            segment: None,
         }
     }
 
+    /// Hit-test the line painted by the last `show` and give it a context menu. The arrow is only
+    /// hovered where no window is on top of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui`: the ui of the panel the windows are shown on
+    /// * `enabled`: the arrow can be interacted with
+    /// * `transport`: the transport tiers proposed
+    /// * `settings`: the time settings the flow is shown with
+    ///
+    /// returns: the change asked from the menu, if any
+    pub(crate) fn interact(
+        &self,
+        ui: &mut egui::Ui,
+        enabled: bool,
+        transport: &TransportLibrary,
+        settings: &TimeSettings,
+    ) -> Option<ArrowEdit> {
+        let [start, end] = self.segment?;
+        let hovered = enabled
+            && ui.ctx().pointer_hover_pos().map_or(false, |pos| {
+                distance_to_segment(pos, start, end) < HIT_DISTANCE
+                    && ui
+                        .ctx()
+                        .layer_id_at(pos)
+                        .map_or(true, |layer| layer == ui.layer_id())
+            });
+
+        if hovered {
+            ui.ctx().layer_painter(self.layer_id).line_segment(
+                [start, end],
+                egui::Stroke::new(self.width() + 2.0, egui::Color32::LIGHT_GRAY),
+            );
+        }
+
+        let tooltip = self
+            .generate_tooltip(settings)
+            .unwrap_or_else(|_| "Error generating tooltip".to_string());
+        let rect = egui::Rect::from_two_pos(start, end).expand(HIT_DISTANCE);
+        let mut edit = None;
+        ui.interact_with_hovered(rect, hovered, self.id, egui::Sense::click())
+            .on_hover_text(&tooltip)
+            .context_menu(|ui| {
+                ui.label(&tooltip);
+                ui.separator();
+                let mut overflow = self.overflow;
+                if ui
+                    .checkbox(&mut overflow, "Overflow only")
+                    .on_hover_text("Only get what the other arrows from the output leave")
+                    .changed()
+                {
+                    edit = Some(ArrowEdit::Overflow(overflow));
+                }
+                ui.add_enabled_ui(!self.overflow, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Priority:");
+                        let mut priority = self.priority;
+                        if ui
+                            .add(egui::DragValue::new(&mut priority))
+                            .on_hover_text("The arrows with the highest priority are served first")
+                            .changed()
+                        {
+                            edit = Some(ArrowEdit::Priority(priority));
+                        }
+                    });
+                });
+                let tier_name = self
+                    .tier
+                    .and_then(|id| transport.get(id))
+                    .map_or("None".to_string(), |tier| tier.name.clone());
+                egui::ComboBox::from_label("Transport")
+                    .selected_text(tier_name)
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(self.tier.is_none(), "None").clicked() {
+                            edit = Some(ArrowEdit::Tier(None));
+                        }
+                        for tier in transport.tiers() {
+                            if !tier.carries(self.resource.unit) {
+                                continue;
+                            }
+                            let selected = self.tier == Some(tier.id);
+                            if ui.selectable_label(selected, &tier.name).clicked() {
+                                edit = Some(ArrowEdit::Tier(Some(tier.id)));
+                            }
+                        }
+                    });
+                ui.separator();
+                if ui.button("Move the start").clicked() {
+                    edit = Some(ArrowEdit::MoveStart);
+                    ui.close_menu();
+                }
+                if ui.button("Move the end").clicked() {
+                    edit = Some(ArrowEdit::MoveEnd);
+                    ui.close_menu();
+                }
+                if ui.button("Delete").clicked() {
+                    edit = Some(ArrowEdit::Remove);
+                    ui.close_menu();
+                }
+            });
+        edit
+    }
+
+    ///The flow needs more than one lane of the transport tier of the arrow
+    pub(crate) fn over_capacity(&self) -> bool {
+        matches!(self.lanes, Some((_, lanes)) if lanes > 1)
+    }
+
+    ///Width of the line, scaled with the flow carried by an anchored arrow
+    fn width(&self) -> f32 {
+        match self.state {
+            ArrowUsageState::Anchored => MIN_WIDTH + (MAX_WIDTH - MIN_WIDTH) * self.throughput,
+            ArrowUsageState::Active | ArrowUsageState::ActiveStart => MIN_WIDTH,
+        }
+    }
+
+    /// Detach an anchored arrow from one of its ends, the end then follows the pointer until it
+    /// is put on another flow
+    pub(crate) fn detach(&mut self, edit: ArrowEdit) {
+        self.state = match edit {
+            ArrowEdit::Remove
+            | ArrowEdit::Priority(_)
+            | ArrowEdit::Overflow(_)
+            | ArrowEdit::Tier(_) => return,
+            ArrowEdit::MoveStart => ArrowUsageState::ActiveStart,
+            ArrowEdit::MoveEnd => ArrowUsageState::Active,
+        };
+        self.flow = None;
+        self.throughput = 0.0;
+        self.under_supplied = false;
+        self.starved = false;
+        self.lanes = None;
+        self.segment = None;
+    }
+
+    pub(crate) fn put_start(
+        &mut self,
+        resource: ResourceDefinition,
+        start_flow: egui::Id,
+        start_flow_type: RecipeWindowType,
+        flow_index: usize,
+    ) -> Result<(), FlowError> {
+        if !resource.is_compatible(&self.resource) {
+            return Err(FlowError::new(FlowErrorType::WrongResourceType));
+        }
+
+        self.resource = resource;
+        self.start_flow_window = start_flow;
+        self.start_flow_type = start_flow_type;
+        self.start_flow_index = flow_index;
+        self.start_flow_id = None;
+        self.state = ArrowUsageState::Anchored;
+
+        Ok(())
+    }
+
     pub(crate) fn put_end(
         &mut self,
         resource: Option<ResourceDefinition>,
@@ -122,7 +423,7 @@ impl ArrowFlow {
         flow_index: usize,
     ) -> Result<(), FlowError> {
         if let Some(resource) = resource {
-            if resource != self.resource {
+            if !resource.is_compatible(&self.resource) {
                 return Err(FlowError::new(FlowErrorType::WrongResourceType));
             }
         }
@@ -130,6 +431,7 @@ impl ArrowFlow {
         self.end_flow_window = Some(end_flow);
         self.end_flow_type = Some(end_flow_type);
         self.end_flow_index = flow_index;
+        self.end_flow_id = None;
         self.state = ArrowUsageState::Anchored;
 
         Ok(())
@@ -139,5 +441,18 @@ impl ArrowFlow {
 #[derive(serde::Deserialize, serde::Serialize, Copy, Clone, Debug)]
 pub(crate) enum ArrowUsageState {
     Active,
+    ///the start is being moved, the end stays anchored
+    ActiveStart,
     Anchored,
 }
+
+///Distance from a point to the segment between two others
+fn distance_to_segment(point: egui::Pos2, start: egui::Pos2, end: egui::Pos2) -> f32 {
+    let line = end - start;
+    let length_sq = line.length_sq();
+    if length_sq == 0.0 {
+        return point.distance(start);
+    }
+    let t = ((point - start).dot(line) / length_sq).clamp(0.0, 1.0);
+    point.distance(start + line * t)
+}
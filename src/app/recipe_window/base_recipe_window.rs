@@ -4,11 +4,16 @@ use crate::app::error::ShowError;
 use crate::app::recipe_window;
 use crate::app::recipe_window::resource_adding_window::ResourceAddingWindow;
 use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
+use crate::app::resources::recipe_input_resource::RecipeInputResource;
 use crate::app::resources::recipe_output_resource::RecipeOutputResource;
 use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
+use crate::app::resources::time_settings::TimeSettings;
 use crate::app::resources::ManageFlow::{RecipeInput, RecipeOutput};
-use crate::app::resources::{FlowError, ManageFlow, RatePer, ResourceDefinition, Unit};
-use crate::utils::{gen_id, Io};
+use crate::app::resources::{
+    power_resource, FlowError, ManageFlow, RatePer, ResourceDefinition, Unit,
+};
+use crate::utils::rational::Rational;
+use crate::utils::{formatting, gen_id, Io};
 use egui::{Context, InnerResponse, Widget};
 use itertools::{EitherOrBoth, Itertools};
 use log::debug;
@@ -17,6 +22,9 @@ use std::fmt::{Debug, Write};
 use std::io::Cursor;
 use std::time::Duration;
 
+///Tolerance on the machine count before rounding up, so rounding errors don't add a machine
+const MACHINE_COUNT_EPSILON: f32 = 1e-4;
+
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 #[serde(default)]
 /// Descriptor for a Base Recipe window, the recipe is directly calculated
@@ -34,19 +42,22 @@ pub struct BaseRecipeWindow {
     temp_tooltip_id: egui::Id,
 
     ///list of inputs
-    pub(crate) inputs: Vec<ManageFlow<usize>>,
+    pub(crate) inputs: Vec<ManageFlow<f32>>,
 
     ///list of outputs
-    pub(crate) outputs: Vec<ManageFlow<usize>>,
+    pub(crate) outputs: Vec<ManageFlow<f32>>,
+
+    ///power used per cycle, in joules, or constantly, in watts. An output for the generators
+    power: Option<ManageFlow<f32>>,
 
-    ///power used per cycle
-    power: Option<ManageFlow<usize>>,
+    ///flag if the power is a constant draw instead of an amount per cycle
+    constant_power: bool,
 
     ///Resource adding windows
-    resource_adding_windows: Vec<ResourceAddingWindow<usize>>,
+    resource_adding_windows: Vec<ResourceAddingWindow<f32>>,
 
     ///Time of cycle
-    time_cycle: usize,
+    time_cycle: f32,
 
     ///Time unit of cycle
     time_unit: RatePer,
@@ -63,6 +74,18 @@ pub struct BaseRecipeWindow {
     ///Flag indicating if every outputs have sufficient draining
     stable_out: bool,
 
+    ///Flag indicating if the power of the graph is sufficient
+    #[serde(skip)]
+    pub(crate) stable_power: bool,
+
+    ///Flag indicating the recipe is part of a loop of the graph
+    #[serde(skip)]
+    pub(crate) in_loop: bool,
+
+    ///Exact number of machines when the graph is solved with rationals
+    #[serde(skip)]
+    pub(crate) exact_machines: Option<Rational>,
+
     ///Configurations of the features shown
     config: ConfigFeatures,
 
@@ -86,6 +109,19 @@ pub struct ConfigFeatures {
     pub show_time: bool,
 }
 
+///Change of the place of a flow in a recipe
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum FlowEdit {
+    ///the flow is deleted with its arrows
+    Remove,
+
+    ///the flow trades places with the flow at this index
+    Swap(usize),
+
+    ///the flow goes at the end of the other list, an input becomes an output
+    Flip,
+}
+
 impl Default for BaseRecipeWindow {
     fn default() -> Self {
         Self::new(
@@ -120,7 +156,7 @@ impl BaseRecipeWindow {
                     name: title,
                     unit: Unit::Piece,
                 },
-                1,
+                1.0,
                 1.0,
                 RatePer::Second,
             ),
@@ -130,7 +166,7 @@ impl BaseRecipeWindow {
     pub fn new_with_custom_output(
         config: ConfigFeatures,
         recipe_type: RecipeWindowType,
-        flow: ResourceFlow<usize, f32>,
+        flow: ResourceFlow<f32, f32>,
     ) -> Self {
         let title = flow.resource.name.clone();
         let id = gen_id(title.clone());
@@ -148,13 +184,17 @@ impl BaseRecipeWindow {
             inputs: vec![],
             outputs: vec![output],
             power: None,
+            constant_power: false,
             resource_adding_windows: vec![],
-            time_cycle: 1,
+            time_cycle: 1.0,
             time_unit: rate,
             description: "".to_string(),
             description_open: false,
             stable_in: false,
             stable_out: false,
+            stable_power: true,
+            in_loop: false,
+            exact_machines: None,
             config,
             recipe_type,
             window_coordinate: CoordinatesInfo::default(),
@@ -183,15 +223,21 @@ impl BaseRecipeWindow {
                 }
             });
 
+            let mut edited = None;
             for i in 0..self.inputs.len() {
-                self.show_flow(
+                if let Some(edit) = self.show_flow(
                     commons,
                     i,
                     Io::Input,
                     ui,
                     enabled,
                     self.config.pure_time_input,
-                );
+                ) {
+                    edited = Some((i, edit));
+                }
+            }
+            if let Some((i, edit)) = edited {
+                self.edit_flow_request(commons, Io::Input, i, edit);
             }
         });
     }
@@ -216,15 +262,21 @@ impl BaseRecipeWindow {
                     self.open_resource_adding_window(Io::Output);
                 }
             });
+            let mut edited = None;
             for i in 0..self.outputs.len() {
-                self.show_flow(
+                if let Some(edit) = self.show_flow(
                     commons,
                     i,
                     Io::Output,
                     ui,
                     enabled,
                     self.config.pure_time_output,
-                );
+                ) {
+                    edited = Some((i, edit));
+                }
+            }
+            if let Some((i, edit)) = edited {
+                self.edit_flow_request(commons, Io::Output, i, edit);
             }
         });
     }
@@ -237,10 +289,33 @@ impl BaseRecipeWindow {
         ui: &mut egui::Ui,
         _enabled: bool,
         pure_time: bool,
-    ) {
+    ) -> Option<FlowEdit> {
         let mut changed = false;
+        let mut renamed = None;
+        let mut edit = None;
+        let flow_count = match dir {
+            Io::Input => self.inputs.len(),
+            Io::Output => self.outputs.len(),
+        };
+        let interactive = match dir {
+            Io::Input => self.config.interactive_input,
+            Io::Output => self.config.interactive_output,
+        };
+        let name_id = self
+            .id
+            .with(("Resource name", resource_flow_index, dir == Io::Input));
+        let settings = commons.time_settings.clone();
+        let excess = match dir {
+            Io::Input => None,
+            Io::Output => self.output_excess(resource_flow_index, &settings),
+        };
+        let probability = match (&dir, &self.outputs.get(resource_flow_index)) {
+            (Io::Output, Some(RecipeOutput(output))) => Some(output.probability),
+            _ => None,
+        };
+        let mut chance = probability.map(|p| p * 100.0);
         //get variables
-        let resource_flow: &mut dyn ManageResourceFlow<usize> = match dir {
+        let resource_flow: &mut dyn ManageResourceFlow<f32> = match dir {
             Io::Input => match &mut self.inputs[resource_flow_index] {
                 RecipeInput(r) => r,
                 RecipeOutput(_) => {
@@ -256,19 +331,18 @@ impl BaseRecipeWindow {
         };
         let resource = resource_flow.resource();
 
-        let mut name = resource.clone().name;
-
-        self.stable_in &= resource_flow.is_enough();
+        self.stable_in &= resource_flow.is_enough(&settings);
 
-        let color = match resource_flow.is_enough() {
+        let color = match resource_flow.is_enough(&settings) {
             true => egui::Rgba::GREEN,
             false => egui::Rgba::RED,
         };
 
         let flow = match dir {
-            Io::Input => resource_flow.total_out(),
-            Io::Output => resource_flow.total_in(),
-        };
+            Io::Input => resource_flow.total_out(&settings),
+            Io::Output => resource_flow.total_in(&settings),
+        }
+        .displayed(&settings);
         let rate = flow.rate;
         let mut amount = flow.amount_per_cycle;
         let mut amount_per_time = flow.amount;
@@ -292,25 +366,100 @@ impl BaseRecipeWindow {
                 Io::Output => {}
             }
 
-            recipe_window::text_edit(ui, &mut name);
+            match interactive {
+                true => {
+                    renamed =
+                        recipe_window::resource_name_edit(ui, commons, name_id, &resource.name)
+                }
+                false => {
+                    ui.label(&resource.name);
+                }
+            }
+            if interactive {
+                ui.menu_button("☰", |ui| {
+                    let index = resource_flow_index;
+                    if ui
+                        .add_enabled(index > 0, egui::Button::new("Move up"))
+                        .clicked()
+                    {
+                        edit = Some(FlowEdit::Swap(index - 1));
+                    }
+                    if ui
+                        .add_enabled(index + 1 < flow_count, egui::Button::new("Move down"))
+                        .clicked()
+                    {
+                        edit = Some(FlowEdit::Swap(index + 1));
+                    }
+                    let flip = match dir {
+                        Io::Input => "Make it an output",
+                        Io::Output => "Make it an input",
+                    };
+                    if ui.button(flip).clicked() {
+                        edit = Some(FlowEdit::Flip);
+                    }
+                    if ui.button("Remove").clicked() {
+                        edit = Some(FlowEdit::Remove);
+                    }
+                    if edit.is_some() {
+                        ui.close_menu();
+                    }
+                });
+            }
             ui.label(":");
 
             ui.vertical(|ui| {
                 if !pure_time {
                     ui.horizontal(|ui| {
-                        changed |= egui::DragValue::new(&mut amount).ui(ui).changed();
+                        changed |= egui::DragValue::new(&mut amount)
+                            .speed(0.1)
+                            .clamp_range(0.0..=f32::MAX)
+                            .ui(ui)
+                            .changed();
                         ui.label("per cycle");
+                        if let Some(chance) = chance.as_mut() {
+                            changed |= egui::DragValue::new(chance)
+                                .suffix("%")
+                                .clamp_range(0.0..=100.0)
+                                .ui(ui)
+                                .on_hover_text("Chance to create the amount each cycle")
+                                .changed();
+                        }
                     });
                 }
                 ui.horizontal(|ui| {
-                    changed |= egui::DragValue::new(&mut amount_per_time).ui(ui).changed();
-                    ui.label(egui::RichText::new(rate.to_shortened_string()).color(color))
+                    changed |= egui::DragValue::new(&mut amount_per_time)
+                        .clamp_range(0.0..=f32::MAX)
+                        .ui(ui)
+                        .changed();
+                    ui.label(egui::RichText::new(rate.to_shortened_string(&settings)).color(color))
                 });
             });
 
             match dir {
                 Io::Input => {}
                 Io::Output => {
+                    if let Some(excess) = excess.map(|excess| excess.displayed(&settings)) {
+                        ui.colored_label(egui::Color32::GOLD, "⚠")
+                            .on_hover_text(format!(
+                                "Excess of {}{} with nowhere to go, the machine would back up",
+                                formatting::float_format(excess.amount, 3),
+                                excess.rate.to_shortened_string(&settings)
+                            ));
+                        if ui
+                            .button("🗑")
+                            .on_hover_text("Route the excess to a new overflow sink")
+                            .clicked()
+                        {
+                            commons.overflow_sink_request = Some((
+                                resource.clone(),
+                                self.id,
+                                ui.layer_id(),
+                                resource_flow_index,
+                                self.recipe_type,
+                            ));
+                        }
+                    }
+
                     let btn_resp = ui.button("⭕");
 
                     self.window_coordinate.out_flow.push(btn_resp.rect);
@@ -328,6 +477,14 @@ impl BaseRecipeWindow {
             }
         });
 
+        if let Some(name) = renamed {
+            let new = commons.resources.resolve(&name, resource.unit);
+            debug!("Resource of a flow changed from {} to {}", resource, new);
+            resource_flow.set_resource(new);
+            commons.check_arrows = true;
+            changed = true;
+        }
+
         if changed {
             if amount != flow.amount_per_cycle {
                 match dir {
@@ -344,20 +501,120 @@ impl BaseRecipeWindow {
                 }
             }
 
+            if let (Some(chance), Some(RecipeOutput(output))) =
+                (chance, self.outputs.get_mut(resource_flow_index))
+            {
+                output.probability = chance / 100.0;
+            }
+
             commons.recalculate = true;
-            let r = self.update_flow(dir);
+            let r = self.update_flow(dir, &settings);
             if let Err(e) = r {
                 commons.add_error(ShowError::new(e.to_string()));
             }
         }
 
         commons.recalculate |= changed;
+        edit
+    }
+
+    ///Edit a flow asked from the window, the arrows find their flows again afterwards
+    fn edit_flow_request(
+        &mut self,
+        commons: &mut CommonsManager,
+        dir: Io,
+        index: usize,
+        edit: FlowEdit,
+    ) {
+        if !self.edit_flow(&dir, index, edit) {
+            return;
+        }
+        for dir in [Io::Input, Io::Output] {
+            if let Err(e) = self.update_flow(dir, &commons.time_settings) {
+                commons.add_error(ShowError::new(e.to_string()));
+            }
+        }
+        commons.check_arrows = true;
+        commons.recalculate = true;
+    }
+
+    /// Remove, move or flip an input or an output
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: the list of the flow
+    /// * `index`: the index of the flow in its list
+    /// * `edit`: the change of the flow
+    ///
+    /// returns: `bool` flag indicating the flow has been changed
+    pub(crate) fn edit_flow(&mut self, dir: &Io, index: usize, edit: FlowEdit) -> bool {
+        let flows = match dir {
+            Io::Input => &mut self.inputs,
+            Io::Output => &mut self.outputs,
+        };
+        if index >= flows.len() {
+            return false;
+        }
+        match edit {
+            FlowEdit::Remove => {
+                let flow = flows.remove(index);
+                debug!("Flow {} removed from {}", flow.resource(), self.title);
+            }
+            FlowEdit::Swap(other) => {
+                if other >= flows.len() {
+                    return false;
+                }
+                flows.swap(index, other);
+            }
+            FlowEdit::Flip => match flows.remove(index) {
+                RecipeInput(input) => self.outputs.push(RecipeOutput(input.into())),
+                RecipeOutput(output) => self.inputs.push(RecipeInput(output.into())),
+            },
+        }
+        true
     }
 
-    pub(crate) fn show_power(&mut self, ui: &mut egui::Ui, _enabled: bool) {
-        let power: &dyn ManageResourceFlow<usize> = match &self.power {
+    pub(crate) fn show_power(
+        &mut self,
+        commons: &mut CommonsManager,
+        ui: &mut egui::Ui,
+        enabled: bool,
+    ) {
+        let settings = &commons.time_settings;
+        let usage = match self.power {
+            Some(RecipeOutput(_)) => format!(
+                "Power generated: {}",
+                formatting::power_format(self.power_generation(settings), 3)
+            ),
+            _ => format!(
+                "Power usage: {}",
+                formatting::power_format(self.power_usage(settings), 3)
+            ),
+        };
+        let power: &mut dyn ManageResourceFlow<f32> = match &mut self.power {
             None => {
-                if ui.button("Add Power").clicked() {} //TODO: Add power
+                let resource = power_resource();
+                let flow = ResourceFlow::new(&resource, 1.0, 1.0, RatePer::Second);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(enabled, egui::Button::new("Add Power"))
+                        .clicked()
+                    {
+                        self.power = Some(RecipeInput(RecipeInputResource::new(
+                            resource.clone(),
+                            flow.clone(),
+                        )));
+                        commons.recalculate = true;
+                    }
+                    if ui
+                        .add_enabled(enabled, egui::Button::new("Add Generator"))
+                        .on_hover_text("The recipe makes power instead of using it")
+                        .clicked()
+                    {
+                        self.power = Some(RecipeOutput(RecipeOutputResource::new(resource, flow)));
+                        commons.recalculate = true;
+                    }
+                });
                 return;
             }
             Some(a) => match a {
@@ -366,21 +623,133 @@ impl BaseRecipeWindow {
             },
         };
         //get variables
-        let resource = power.resource();
-        let mut name = resource.name;
-        let resource_flow = power.total_out();
-        let mut amount = resource_flow.amount;
+        let mut amount = power.total_out(&commons.time_settings).amount_per_cycle;
+        let mut constant = self.constant_power;
+        let mut removed = false;
         ui.horizontal(|ui| {
-            recipe_window::text_edit(ui, &mut name);
-            ui.label(":");
+            ui.label("Power:");
             egui::DragValue::new(&mut amount).ui(ui);
-            ui.label("per cycle");
+            ui.label(match constant {
+                true => "W",
+                false => "J per cycle",
+            });
+            ui.checkbox(&mut constant, "Constant")
+                .on_hover_text("Constant draw in watts instead of an energy per cycle");
+            removed = ui
+                .add_enabled(enabled, egui::Button::new("🗑"))
+                .on_hover_text("Remove the power")
+                .clicked();
         });
+        ui.label(usage);
+
+        if amount != power.total_out(&commons.time_settings).amount_per_cycle
+            || constant != self.constant_power
+        {
+            power.set_designed_amount_per_cycle(amount);
+            self.constant_power = constant;
+            commons.recalculate = true;
+        }
+        if removed {
+            self.power = None;
+            commons.recalculate = true;
+        }
+    }
+
+    ///Resources of the inputs and outputs
+    pub(crate) fn resources(&self) -> Vec<ResourceDefinition> {
+        self.inputs
+            .iter()
+            .chain(self.outputs.iter())
+            .map(|flow| flow.resource())
+            .collect()
+    }
+
+    ///Rates of the cycle, the inputs, the outputs and the power
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates = vec![self.time_unit];
+        rates.extend(
+            self.inputs
+                .iter()
+                .chain(self.outputs.iter())
+                .chain(self.power.iter())
+                .flat_map(ManageFlow::rates),
+        );
+        rates
+    }
+
+    ///Replace a resource by another in the inputs and outputs, when a resource is renamed
+    pub(crate) fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        for flow in self.inputs.iter_mut().chain(self.outputs.iter_mut()) {
+            flow.rename_resource(old, new);
+        }
+    }
+
+    ///Excess of an output, None if everything it creates is taken
+    pub(crate) fn output_excess(
+        &self,
+        index: usize,
+        settings: &TimeSettings,
+    ) -> Option<ResourceFlow<f32, f32>> {
+        match self.outputs.get(index)? {
+            RecipeOutput(output) if output.has_excess(settings) => Some(output.excess(settings)),
+            _ => None,
+        }
+    }
+
+    ///Designed flow of the power, the energy per cycle or the constant draw
+    fn power_flow(&self) -> Option<&ResourceFlow<f32, f32>> {
+        match self.power.as_ref()? {
+            RecipeInput(power) => Some(&power.needed),
+            RecipeOutput(power) => Some(&power.created),
+        }
+    }
+
+    ///Power in watts of a single machine running at full speed
+    pub(crate) fn power_per_machine(&self, settings: &TimeSettings) -> f32 {
+        let amount = match self.power_flow() {
+            None => return 0.0,
+            Some(flow) => flow.amount_per_cycle,
+        };
+        if self.constant_power {
+            amount
+        } else if self.time_cycle <= 0.0 {
+            0.0
+        } else {
+            amount / self.time_cycle * self.time_unit.per_hour(settings)
+                / RatePer::Second.per_hour(settings)
+        }
+    }
+
+    ///Power in watts used by the recipe, for its machine count and utilisation
+    pub(crate) fn power_usage(&self, settings: &TimeSettings) -> f32 {
+        match self.power {
+            Some(RecipeInput(_)) => self.power_per_machine(settings) * self.machine_count(settings),
+            _ => 0.0,
+        }
+    }
+
+    ///Power in watts made by the recipe when it's a generator, for its machine count
+    pub(crate) fn power_generation(&self, settings: &TimeSettings) -> f32 {
+        match self.power {
+            Some(RecipeOutput(_)) => {
+                self.power_per_machine(settings) * self.machine_count(settings)
+            }
+            _ => 0.0,
+        }
+    }
+
+    ///Power in watts a machine adds to the balance of the graph, negative when it uses power
+    pub(crate) fn power_balance_per_machine(&self, settings: &TimeSettings) -> f32 {
+        match self.power {
+            None => 0.0,
+            Some(RecipeInput(_)) => -self.power_per_machine(settings),
+            Some(RecipeOutput(_)) => self.power_per_machine(settings),
+        }
     }
 
     pub(crate) fn show_time_settings(
         &mut self,
-        mut common: &mut CommonsManager,
+        common: &mut CommonsManager,
         ui: &mut egui::Ui,
         _enabled: bool,
     ) -> Result<(), FlowError> {
@@ -388,8 +757,11 @@ impl BaseRecipeWindow {
         let mut rate = self.time_unit;
         ui.horizontal(|ui| {
             ui.label("Cycle duration:");
-            egui::DragValue::new(&mut amount).ui(ui);
-            recipe_window::rate_combo(ui, &mut rate);
+            egui::DragValue::new(&mut amount)
+                .speed(0.1)
+                .clamp_range(0.001..=f32::MAX)
+                .ui(ui);
+            recipe_window::rate_combo(ui, &mut rate, &common.time_settings);
         });
         let mut changed = false;
         if amount != self.time_cycle {
@@ -404,12 +776,96 @@ impl BaseRecipeWindow {
 
         if changed {
             common.recalculate = true;
-            self.update_flow(Io::Input)?;
-            self.update_flow(Io::Output)?;
+            self.update_flow(Io::Input, &common.time_settings)?;
+            self.update_flow(Io::Output, &common.time_settings)?;
         }
         Ok(())
     }
 
+    pub(crate) fn show_machines(&self, ui: &mut egui::Ui, settings: &TimeSettings) {
+        let count = formatting::float_format(self.machine_count(settings), 3);
+        let rounded = self.machine_count_rounded(settings);
+        let count = match self.exact_machines {
+            Some(exact) if !exact.is_integer() => format!("{exact} ({count})"),
+            _ => count,
+        };
+        ui.label(format!("Machines: {count} → {rounded}"))
+            .on_hover_text("Machines needed by the calculated flows, rounded up to build");
+
+        egui::CollapsingHeader::new("Build throughput")
+            .id_source(self.id.with("Build throughput"))
+            .show(ui, |ui| {
+                for flow in self.build_throughput(settings) {
+                    let flow = flow.displayed(settings);
+                    ui.label(format!(
+                        "{}: {}{}",
+                        flow.resource.name,
+                        formatting::float_format(flow.amount, 3),
+                        flow.rate.to_shortened_string(settings)
+                    ));
+                }
+            });
+    }
+
+    ///Designed amount of a flow per hour for a single machine
+    fn machine_hourly_amount(&self, flow: &ResourceFlow<f32, f32>, settings: &TimeSettings) -> f32 {
+        if self.time_cycle <= 0.0 {
+            return 0.0;
+        }
+        flow.amount_per_cycle / self.time_cycle * self.time_unit.per_hour(settings)
+    }
+
+    ///Number of machines needed for the calculated flows, it can be fractional
+    pub(crate) fn machine_count(&self, settings: &TimeSettings) -> f32 {
+        if let Some(exact) = self.exact_machines {
+            return exact.as_f64() as f32;
+        }
+        let inputs = self.inputs.iter().filter_map(|f| match f {
+            RecipeInput(input) => Some(&input.needed),
+            RecipeOutput(_) => None,
+        });
+        let inputs = inputs.map(|flow| (flow, 1.0));
+        let outputs = self.outputs.iter().filter_map(|f| match f {
+            RecipeInput(_) => None,
+            RecipeOutput(output) => Some((&output.created, output.probability)),
+        });
+        inputs
+            .chain(outputs)
+            .filter_map(|(flow, probability)| {
+                let designed = self.machine_hourly_amount(flow, settings) * probability;
+                (designed > 0.0).then(|| flow.amount * flow.rate.per_hour(settings) / designed)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    ///Number of machines to build, the machine count rounded up
+    pub(crate) fn machine_count_rounded(&self, settings: &TimeSettings) -> usize {
+        if let Some(count) = self.exact_machines.and_then(|exact| exact.ceil()) {
+            return count.max(0) as usize;
+        }
+        (self.machine_count(settings) - MACHINE_COUNT_EPSILON)
+            .ceil()
+            .max(0.0) as usize
+    }
+
+    ///Flows of the outputs when every machine of the rounded build runs at full speed
+    pub(crate) fn build_throughput(&self, settings: &TimeSettings) -> Vec<ResourceFlow<f32, f32>> {
+        let machines = self.machine_count_rounded(settings) as f32;
+        self.outputs
+            .iter()
+            .filter_map(|f| match f {
+                RecipeInput(_) => None,
+                RecipeOutput(output) => {
+                    let mut flow = output.created.clone();
+                    flow.amount =
+                        machines * self.machine_hourly_amount(&flow, settings) * output.probability
+                            / flow.rate.per_hour(settings);
+                    Some(flow)
+                }
+            })
+            .collect()
+    }
+
     pub(crate) fn show_notes(&mut self, ui: &mut egui::Ui, _enabled: bool) {
         let short_title = self.description.lines().next().unwrap_or("").trim();
         egui::CollapsingHeader::new(format!("Notes: {short_title}"))
@@ -419,7 +875,11 @@ impl BaseRecipeWindow {
             });
     }
 
-    pub(crate) fn update_flow(&mut self, dir: Io) -> Result<(), FlowError> {
+    pub(crate) fn update_flow(
+        &mut self,
+        dir: Io,
+        settings: &TimeSettings,
+    ) -> Result<(), FlowError> {
         let flow = match dir {
             Io::Input => &mut self.inputs,
             Io::Output => &mut self.outputs,
@@ -428,17 +888,18 @@ impl BaseRecipeWindow {
         for f in flow.iter_mut() {
             match f {
                 RecipeInput(f) => {
-                    stable &= f.is_enough();
+                    stable &= f.is_enough(settings);
                     if !self.config.pure_time_input {
                         f.needed
-                            .convert_time_base(self.time_cycle, self.time_unit)?;
+                            .convert_time_base(self.time_cycle, self.time_unit, settings)?;
                     }
                 }
                 RecipeOutput(f) => {
-                    stable &= f.is_enough();
+                    stable &= f.is_enough(settings);
                     if !self.config.pure_time_output {
                         f.created
-                            .convert_time_base(self.time_cycle, self.time_unit)?;
+                            .convert_time_base(self.time_cycle, self.time_unit, settings)?;
+                        f.created.amount *= f.probability;
                     }
                 }
             }
@@ -458,7 +919,7 @@ impl BaseRecipeWindow {
             self.title,
             self.resource_adding_windows.len() + 1
         );
-        let window = ResourceAddingWindow::<usize>::new(title, dir);
+        let window = ResourceAddingWindow::<f32>::new(title, dir);
         self.resource_adding_windows.push(window);
     }
 
@@ -476,10 +937,14 @@ impl BaseRecipeWindow {
         self.window_coordinate.out_flow.clear();
     }
 
-    pub(crate) fn gen_title_string(&mut self) -> String {
+    pub(crate) fn gen_title_string(&mut self, settings: &TimeSettings) -> String {
         format!(
-            "{}{}{}",
+            "{}{}{}{}{}",
             self.title,
+            match self.in_loop {
+                true => "🔁",
+                false => "",
+            },
             match self.stable_in {
                 true => {
                     "✔"
@@ -495,6 +960,10 @@ impl BaseRecipeWindow {
                 false => {
                     "⛔"
                 }
+            },
+            match self.stable_power || self.power_usage(settings) == 0.0 {
+                true => "",
+                false => "⚡",
             }
         )
     }
@@ -520,13 +989,14 @@ impl BaseRecipeWindow {
                     });
                     ui.separator();
                     if self.config.show_power {
-                        self.show_power(ui, enabled);
+                        self.show_power(commons, ui, enabled);
                     }
                     if self.config.show_time {
                         let result = self.show_time_settings(commons, ui, enabled);
                         if result.is_err() {
                             commons.add_error(ShowError::new(format!("{}", result.err().unwrap())));
                         }
+                        self.show_machines(ui, &commons.time_settings);
                     }
                     self.show_notes(ui, enabled);
                 });
@@ -555,7 +1025,7 @@ impl BaseRecipeWindow {
             resp.on_hover_ui(|ui| {
                 ui.label(
                     egui::RichText::new(
-                        self.generate_tooltip()
+                        self.generate_tooltip(&commons.time_settings)
                             .unwrap_or_else(|_| "Error generating tooltip".to_string()),
                     )
                     .font(egui::FontId::monospace(10.0)),
@@ -572,9 +1042,16 @@ impl BaseRecipeWindow {
     ) {
         self.resource_adding_windows.retain_mut(|window| {
             let open = window.show(commons, ctx, enabled);
+            if window.okay && window.amount_per_cycle <= 0.0 {
+                commons.add_error(ShowError::new(format!(
+                    "{} needs an amount above zero per cycle",
+                    window.resource_name
+                )));
+                window.okay = false;
+            }
             if window.okay {
                 //add the response
-                let resource = window.get_resource();
+                let resource = window.get_resource(&mut commons.resources);
                 match window.dir {
                     Io::Input => {
                         self.inputs.push(resource);
@@ -604,7 +1081,10 @@ impl BaseRecipeWindow {
         }
     }
 
-    pub(crate) fn generate_tooltip(&self) -> Result<String, std::fmt::Error> {
+    pub(crate) fn generate_tooltip(
+        &self,
+        settings: &TimeSettings,
+    ) -> Result<String, std::fmt::Error> {
         //TODO: FIX
         let mut tooltip = String::new();
 
@@ -622,7 +1102,7 @@ impl BaseRecipeWindow {
         for it in self.inputs.iter().zip_longest(self.outputs.iter()) {
             match it {
                 EitherOrBoth::Both(input, output) => {
-                    let temp = input.to_split_string();
+                    let temp = input.to_split_string(settings);
                     colum_a_lengths = (
                         colum_a_lengths.0.max(temp[0].len()),
                         colum_a_lengths.1.max(temp[1].len()),
@@ -633,7 +1113,7 @@ impl BaseRecipeWindow {
                         Some(temp[1].clone()),
                         Some(temp[2].clone()),
                     ));
-                    let temp = output.to_split_string();
+                    let temp = output.to_split_string(settings);
                     colum_b_lengths = (
                         colum_b_lengths.0.max(temp[0].len()),
                         colum_b_lengths.1.max(temp[1].len()),
@@ -646,7 +1126,7 @@ impl BaseRecipeWindow {
                     ));
                 }
                 EitherOrBoth::Left(input) => {
-                    let temp = input.to_split_string();
+                    let temp = input.to_split_string(settings);
                     colum_a_lengths = (
                         colum_a_lengths.0.max(temp[0].len()),
                         colum_a_lengths.1.max(temp[1].len()),
@@ -659,7 +1139,7 @@ impl BaseRecipeWindow {
                     ));
                 }
                 EitherOrBoth::Right(output) => {
-                    let temp = output.to_split_string();
+                    let temp = output.to_split_string(settings);
                     colum_b_lengths = (
                         colum_b_lengths.0.max(temp[0].len()),
                         colum_b_lengths.1.max(temp[1].len()),
@@ -777,6 +1257,7 @@ impl BaseRecipeWindow {
         r &= self.inputs == other.inputs;
         r &= self.outputs == other.outputs;
         r &= self.power == other.power;
+        r &= self.constant_power == other.constant_power;
         r &= self.resource_adding_windows == other.resource_adding_windows;
         r &= self.time_cycle == other.time_cycle;
         r &= self.time_unit == other.time_unit;
@@ -793,8 +1274,6 @@ impl Eq for BaseRecipeWindow {}
 pub trait RecipeWindowUser<'a>: serde::Serialize {
     type Gen: serde::Serialize + serde::Deserialize<'a> + RecipeWindowUser<'a>;
 
-    fn recipe(self) -> BaseRecipeWindow;
-
     ///Save to self to json data
     ///None if there was an error, which is added to the error queue
     fn save(&mut self) -> Option<String> {
@@ -848,29 +1327,39 @@ pub trait RecipeWindowUser<'a>: serde::Serialize {
 
     fn gen_ids(&mut self);
 
-    fn internal_calculation(&mut self);
+    fn internal_calculation(&mut self, settings: &TimeSettings);
 
     fn back_propagation_internal_calculation(
         &mut self,
         rate: f32,
-        amount: Option<ResourceFlow<usize, f32>>,
+        amount: Option<ResourceFlow<f32, f32>>,
+        settings: &TimeSettings,
     );
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use crate::app::recipe_window::base_recipe_window::BaseRecipeWindow;
+    use crate::app::recipe_window::simple_recipe_window::SimpleRecipeWindow;
+    use crate::app::resources::recipe_input_resource::RecipeInputResource;
+    use crate::app::resources::recipe_output_resource::RecipeOutputResource;
+    use crate::app::resources::resource_flow::ManageResourceFlow;
     use crate::app::resources::resource_flow::ResourceFlow;
-    use crate::app::resources::{RatePer, ResourceDefinition};
+    use crate::app::resources::test::setup_resource;
+    use crate::app::resources::time_settings::TimeSettings;
+    use crate::app::resources::ManageFlow::{RecipeInput, RecipeOutput};
+    use crate::app::resources::{power_resource, RatePer, ResourceDefinition};
+    use crate::utils::{test_env, Io};
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub(crate) struct RecipeResourceInfos {
         pub def: ResourceDefinition,
         pub amount: f32,
-        pub amount_per_cycle: usize,
+        pub amount_per_cycle: f32,
         pub rate: RatePer,
     }
-    impl From<&ResourceFlow<usize, f32>> for RecipeResourceInfos {
-        fn from(value: &ResourceFlow<usize, f32>) -> Self {
+    impl From<&ResourceFlow<f32, f32>> for RecipeResourceInfos {
+        fn from(value: &ResourceFlow<f32, f32>) -> Self {
             RecipeResourceInfos {
                 def: value.resource.clone(),
                 amount: value.amount,
@@ -879,4 +1368,90 @@ pub(crate) mod tests {
             }
         }
     }
+
+    impl BaseRecipeWindow {
+        /// Set the power of a recipe
+        ///
+        /// # Arguments
+        ///
+        /// * `joules`: energy per cycle of a machine
+        /// * `generator`: the recipe makes the power instead of using it
+        ///
+        /// returns: ()
+        pub(crate) fn set_power(&mut self, joules: usize, generator: bool) {
+            let resource = power_resource();
+            let flow = ResourceFlow::new(&resource, joules as f32, joules as f32, RatePer::Second);
+            self.constant_power = false;
+            self.power = Some(match generator {
+                true => RecipeOutput(RecipeOutputResource::new(resource, flow)),
+                false => RecipeInput(RecipeInputResource::new(resource, flow)),
+            });
+        }
+    }
+
+    //-------------------Tests-------------------
+
+    #[test]
+    fn test_power_usage() {
+        test_env::setup();
+        let resource = setup_resource("Plate");
+        let flow = ResourceFlow::new(&resource, 1.0, 1.0, RatePer::Minute);
+        let mut recipe = SimpleRecipeWindow::new_with_custom_output(flow).inner_recipe;
+        assert_eq!(recipe.power_usage(&TimeSettings::default()), 0.0);
+
+        recipe.set_power(60, false);
+        if let RecipeOutput(output) = &mut recipe.outputs[0] {
+            output.created.amount *= 2.5;
+        }
+        assert!((recipe.power_per_machine(&TimeSettings::default()) - 1.0).abs() < 1e-6);
+        assert!((recipe.power_usage(&TimeSettings::default()) - 2.5).abs() < 1e-6);
+
+        recipe.constant_power = true;
+        assert!((recipe.power_usage(&TimeSettings::default()) - 150.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_output_probability() {
+        test_env::setup();
+        let resource = setup_resource("Dust");
+        let flow = ResourceFlow::new(&resource, 2.0, 2.0, RatePer::Minute);
+        let mut recipe = SimpleRecipeWindow::new_with_custom_output(flow).inner_recipe;
+        if let RecipeOutput(output) = &mut recipe.outputs[0] {
+            output.probability = 0.3;
+        }
+        recipe
+            .update_flow(Io::Output, &TimeSettings::default())
+            .unwrap();
+
+        let output = match &recipe.outputs[0] {
+            RecipeOutput(output) => output,
+            RecipeInput(_) => panic!("The output became an input"),
+        };
+        assert!((output.created.amount - 0.6).abs() < 1e-6);
+        assert_eq!(
+            output.to_split_string(&TimeSettings::default())[1],
+            "2 (30.0%)"
+        );
+        assert!((recipe.machine_count(&TimeSettings::default()) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fractional_cycle() {
+        test_env::setup();
+        let resource = setup_resource("Water");
+        let flow = ResourceFlow::new(&resource, 0.5, 0.5, RatePer::Minute);
+        let mut recipe = SimpleRecipeWindow::new_with_custom_output(flow).inner_recipe;
+        recipe.time_cycle = 1.5;
+        recipe
+            .update_flow(Io::Output, &TimeSettings::default())
+            .unwrap();
+
+        let output = match &recipe.outputs[0] {
+            RecipeOutput(output) => output,
+            RecipeInput(_) => panic!("The output became an input"),
+        };
+        // 0.5 every 1.5 seconds is 20 per minute
+        assert!((output.created.amount - 20.0).abs() < 1e-4);
+        assert!((recipe.machine_count(&TimeSettings::default()) - 1.0).abs() < 1e-6);
+    }
 }
@@ -1,5 +1,6 @@
 use crate::app::commons::CommonsManager;
 use crate::app::recipe_graph::RecipeGraph;
+use crate::app::resources::time_settings::TimeSettings;
 
 use crate::app::error::ShowError;
 use crate::app::recipe_window::base_recipe_window::{
@@ -9,7 +10,7 @@ use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
 use crate::app::resources::recipe_input_resource::RecipeInputResource;
 use crate::app::resources::recipe_output_resource::RecipeOutputResource;
 use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
-use crate::app::resources::ManageFlow;
+use crate::app::resources::{ManageFlow, RatePer, ResourceDefinition};
 use egui::Context;
 use log::{debug, info, trace};
 use std::fmt::Error;
@@ -24,7 +25,20 @@ pub(crate) struct CompoundRecipeWindow {
 }
 
 impl CompoundRecipeWindow {
-    pub fn new(recipe_graph: RecipeGraph) -> Self {
+    ///Replace a resource by another in the interface and the graph of the recipe
+    pub(crate) fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        self.inner_recipe.rename_resource(old, new);
+        self.recipe_graph.rename_resource(old, new);
+    }
+
+    ///Rates of the interface and of the graph of the recipe
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates = self.inner_recipe.rates();
+        rates.extend(self.recipe_graph.rates());
+        rates
+    }
+
+    pub fn new(recipe_graph: RecipeGraph, settings: &TimeSettings) -> Self {
         let title = if let Some(first) = recipe_graph.sinks.first() {
             if let Some(recipe_resource) = &first.sink {
                 recipe_resource.needed.resource.name.clone()
@@ -50,58 +64,57 @@ impl CompoundRecipeWindow {
                 RecipeWindowType::CompoundRecipe,
             ),
         };
-        graph.update_interface();
+        graph.update_interface(settings);
         graph
     }
-    fn update_interface(&mut self) {
+    pub(crate) fn update_interface(&mut self, settings: &TimeSettings) {
         info!("Update the interfaces");
-        self.recipe_graph.calculate();
-        self.update_inputs();
-        self.update_outputs();
+        self.recipe_graph.calculate(settings);
+        self.update_inputs(settings);
+        self.update_outputs(settings);
     }
 
-    fn update_outputs(&mut self) {
+    fn update_outputs(&mut self, settings: &TimeSettings) {
         self.inner_recipe.outputs.clear();
         for sink in self.recipe_graph.sinks.iter() {
             if let Some(flow) = &sink.sink {
-                debug!("And outputs!: {}", flow.total_in());
-                self.inner_recipe.outputs.push(ManageFlow::RecipeOutput(
-                    RecipeOutputResource::new(flow.resource().clone(), flow.total_in()),
-                ));
+                let total_in = flow.total_in(settings);
+                debug!("And outputs!: {}", total_in);
+                let mut output = RecipeOutputResource::new(flow.resource().clone(), total_in);
+                //the flow is rebuilt at every calculation, it keeps the id of its sink
+                output.id = sink.id;
+                self.inner_recipe
+                    .outputs
+                    .push(ManageFlow::RecipeOutput(output));
             }
         }
     }
 
-    fn update_inputs(&mut self) {
+    fn update_inputs(&mut self, settings: &TimeSettings) {
         self.inner_recipe.inputs.clear();
         for source in self.recipe_graph.sources.iter() {
+            let mut input = RecipeInputResource::new(
+                source.output.resource().clone(),
+                source.output.total_out(settings),
+            );
+            input.id = source.id;
             self.inner_recipe
                 .inputs
-                .push(ManageFlow::RecipeInput(RecipeInputResource::new(
-                    source.output.resource().clone(),
-                    source.output.total_out(),
-                )));
+                .push(ManageFlow::RecipeInput(input));
         }
     }
 
     /// Transmit the limit
-    pub(crate) fn limit_inputs(&mut self) {
+    pub(crate) fn limit_inputs(&mut self, settings: &TimeSettings) {
         trace!("limit_inputs start");
         for (i, input) in self.inner_recipe.inputs.iter().enumerate() {
             match input {
                 ManageFlow::RecipeInput(input) => {
                     debug!("Resource: {}", input.resource().name);
-                    debug!(
-                        "inputs! {}{}",
-                        input.total_in().amount,
-                        input.total_in().rate.to_shortened_string()
-                    );
-                    debug!(
-                        "outputs! {}{}",
-                        input.total_out().amount,
-                        input.total_out().rate.to_shortened_string()
-                    );
-                    let input = input.total_in();
+                    let total_out = input.total_out(settings);
+                    debug!("outputs! {}", total_out);
+                    let input = input.total_in(settings);
+                    debug!("inputs! {}", input);
                     self.recipe_graph.sources[i].limit_source(input.amount, input.rate);
                     debug!("limit: {}", self.recipe_graph.sources[i].limited_output);
                 }
@@ -136,7 +149,7 @@ impl RecipeWindowGUI for CompoundRecipeWindow {
         open
     }
 
-    fn generate_tooltip(&self) -> Result<String, Error> {
+    fn generate_tooltip(&self, _settings: &TimeSettings) -> Result<String, Error> {
         todo!()
     }
 }
@@ -144,10 +157,6 @@ impl RecipeWindowGUI for CompoundRecipeWindow {
 impl RecipeWindowUser<'static> for CompoundRecipeWindow {
     type Gen = CompoundRecipeWindow;
 
-    fn recipe(self) -> BaseRecipeWindow {
-        self.inner_recipe
-    }
-
     fn push_errors(&mut self, e: ShowError) {
         self.inner_recipe.errors.push(e);
     }
@@ -156,17 +165,18 @@ impl RecipeWindowUser<'static> for CompoundRecipeWindow {
         self.inner_recipe.gen_ids();
     }
 
-    fn internal_calculation(&mut self) {
+    fn internal_calculation(&mut self, settings: &TimeSettings) {
         info!("Internal calculation");
-        self.limit_inputs();
-        self.recipe_graph.calculate();
-        self.update_outputs();
+        self.limit_inputs(settings);
+        self.recipe_graph.calculate(settings);
+        self.update_outputs(settings);
     }
 
     fn back_propagation_internal_calculation(
         &mut self,
         rate: f32,
-        _amount: Option<ResourceFlow<usize, f32>>,
+        _amount: Option<ResourceFlow<f32, f32>>,
+        _settings: &TimeSettings,
     ) {
         trace!("[START] back propagation internal calculation for compound recipe window");
 
@@ -192,6 +202,7 @@ pub mod tests {
     use crate::app::recipe_graph::RecipeGraph;
     use crate::app::recipe_window::base_recipe_window::tests::RecipeResourceInfos;
     use crate::app::recipe_window::compound_recipe_window::CompoundRecipeWindow;
+    use crate::app::resources::time_settings::TimeSettings;
     use log::info;
 
     use crate::app::resources::ManageFlow;
@@ -207,7 +218,7 @@ pub mod tests {
 
     impl CompoundRecipeWindow {
         pub(crate) fn setup_from_graph_info(graph_info: recipe_graph::tests::TestInfo) -> TestInfo {
-            let recipe = Self::new(graph_info.graph.clone());
+            let recipe = Self::new(graph_info.graph.clone(), &TimeSettings::default());
             let mut input_resources = vec![];
             let mut output_resources = vec![];
             for input in graph_info.inputs.iter() {
@@ -246,7 +257,7 @@ pub mod tests {
     }
 
     pub(crate) fn check_flow_and_test_info(
-        result: &ManageFlow<usize>,
+        result: &ManageFlow<f32>,
         expected: &RecipeResourceInfos,
     ) {
         let flow = match result {
@@ -1,10 +1,13 @@
+use crate::app::commons::resource_catalog::ResourceCatalog;
 use crate::app::commons::CommonsManager;
+use crate::app::recipe_window;
 use crate::app::recipe_window::RecipeWindowGUI;
 use crate::app::resources::recipe_input_resource::RecipeInputResource;
 use crate::app::resources::recipe_output_resource::RecipeOutputResource;
 use crate::app::resources::resource_flow::ResourceFlow;
+use crate::app::resources::time_settings::TimeSettings;
 use crate::app::resources::ManageFlow::{RecipeInput, RecipeOutput};
-use crate::app::resources::{ManageFlow, RatePer, ResourceDefinition, Unit};
+use crate::app::resources::{ManageFlow, RatePer, Unit};
 use crate::utils::{gen_id, Io, Number};
 use egui::Widget;
 
@@ -28,6 +31,10 @@ pub struct ResourceAddingWindow<T> {
     ///Rate
     pub(crate) rate: RatePer,
 
+    ///Unit of the flow
+    #[serde(default)]
+    pub(crate) unit: Unit,
+
     ///Io direction
     pub(crate) dir: Io,
 
@@ -44,16 +51,15 @@ impl<T: Number> ResourceAddingWindow<T> {
             amount_per_cycle: T::one(),
             amount_per_time: T::one(),
             rate: RatePer::Second,
+            unit: Unit::Piece,
             dir,
             okay: false,
         }
     }
 
-    pub(crate) fn get_resource(&self) -> ManageFlow<T> {
-        let resource = ResourceDefinition {
-            name: self.resource_name.clone(),
-            unit: Unit::Piece,
-        };
+    ///The flow to add, its resource is taken from the catalog
+    pub(crate) fn get_resource(&self, catalog: &mut ResourceCatalog) -> ManageFlow<T> {
+        let resource = catalog.resolve(&self.resource_name, self.unit);
         let flow = ResourceFlow::new(&resource, self.amount_per_cycle, 1.0f32, RatePer::Second);
         match self.dir {
             Io::Input => RecipeInput(RecipeInputResource::new(resource, flow)),
@@ -63,7 +69,7 @@ impl<T: Number> ResourceAddingWindow<T> {
 }
 
 impl<T: Number> RecipeWindowGUI for ResourceAddingWindow<T> {
-    fn show(&mut self, _commons: &mut CommonsManager, ctx: &egui::Context, enabled: bool) -> bool {
+    fn show(&mut self, commons: &mut CommonsManager, ctx: &egui::Context, enabled: bool) -> bool {
         let mut open = true;
 
         let response = egui::Window::new(self.title.to_owned())
@@ -74,10 +80,9 @@ impl<T: Number> RecipeWindowGUI for ResourceAddingWindow<T> {
                 ui.vertical(|ui| {
                     ui.horizontal(|ui| {
                         ui.label("Name:");
-                        if egui::TextEdit::singleline(&mut self.resource_name)
-                            .hint_text("resource name")
-                            .show(ui)
-                            .response
+                        if commons
+                            .resources
+                            .autocomplete(ui, &mut self.resource_name, "resource name")
                             .has_focus()
                             && ctx.input(|i| i.key_pressed(egui::Key::Enter))
                         {
@@ -86,7 +91,25 @@ impl<T: Number> RecipeWindowGUI for ResourceAddingWindow<T> {
                         ui.label("Amount:");
 
                         //ui.horizontal( |ui| {
-                        egui::DragValue::new(&mut self.amount_per_cycle).ui(ui);
+                        egui::DragValue::new(&mut self.amount_per_cycle)
+                            .speed(0.1)
+                            .clamp_range(T::zero()..=T::MAX)
+                            .ui(ui);
+                        let dimension = commons
+                            .resources
+                            .find(&self.resource_name)
+                            .map(|entry| entry.unit);
+                        if let Some(known) = dimension {
+                            if known.dimension() != self.unit.dimension() {
+                                self.unit = known;
+                            }
+                        }
+                        recipe_window::unit_combo(
+                            ui,
+                            self.id.with("Unit"),
+                            &mut self.unit,
+                            dimension.map(Unit::dimension),
+                        );
                         ui.label("per cycle");
                         // });
                         //TODO Implement time based entering
@@ -112,7 +135,7 @@ impl<T: Number> RecipeWindowGUI for ResourceAddingWindow<T> {
         open
     }
 
-    fn generate_tooltip(&self) -> Result<String, std::fmt::Error> {
+    fn generate_tooltip(&self, _settings: &TimeSettings) -> Result<String, std::fmt::Error> {
         Ok("Window to add resources".to_string())
     }
 }
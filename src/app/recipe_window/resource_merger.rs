@@ -0,0 +1,221 @@
+use crate::app::commons::CommonsManager;
+use crate::app::coordinates_info::CoordinatesInfo;
+use crate::app::recipe_graph::{hourly_amount, hourly_flow};
+use crate::app::recipe_window;
+use crate::app::recipe_window::resource_splitter::flow_label;
+use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
+use crate::app::resources::recipe_input_resource::RecipeInputResource;
+use crate::app::resources::recipe_output_resource::RecipeOutputResource;
+use crate::app::resources::resource_flow::ManageResourceFlow;
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{RatePer, ResourceDefinition};
+use crate::utils::gen_id;
+use log::debug;
+use std::fmt::Write;
+
+///Node joining several flows of a resource in one output
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub(crate) struct ResourceMerger {
+    ///unique id of the window
+    pub(crate) id: egui::Id,
+
+    ///the flows joined
+    pub(crate) inputs: Vec<RecipeInputResource<f32>>,
+
+    ///the joined flow
+    pub(crate) output: RecipeOutputResource<f32>,
+
+    #[serde(skip)]
+    window_coordinate: CoordinatesInfo,
+}
+
+impl ResourceMerger {
+    pub(crate) fn new(resource: ResourceDefinition) -> Self {
+        let mut new = Self {
+            id: gen_id(format!("Merger{}", resource.name)),
+            inputs: vec![],
+            output: RecipeOutputResource::new(resource.clone(), hourly_flow(&resource, 0.0)),
+            window_coordinate: Default::default(),
+        };
+        new.add_input();
+        new.add_input();
+        new
+    }
+
+    pub(crate) fn resource(&self) -> ResourceDefinition {
+        self.output.resource()
+    }
+
+    pub(crate) fn add_input(&mut self) {
+        let resource = self.resource();
+        self.inputs.push(RecipeInputResource::new(
+            resource.clone(),
+            hourly_flow(&resource, 0.0),
+        ));
+    }
+
+    ///Remove the last input, a merger keeps at least one
+    pub(crate) fn remove_input(&mut self) {
+        if self.inputs.len() > 1 {
+            self.inputs.pop();
+        }
+    }
+
+    pub(crate) fn set_resource(&mut self, resource: ResourceDefinition) {
+        self.output.set_resource(resource.clone());
+        for input in self.inputs.iter_mut() {
+            input.set_resource(resource.clone());
+        }
+    }
+
+    pub(crate) fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        self.output.rename_resource(old, new);
+        for input in self.inputs.iter_mut() {
+            input.rename_resource(old, new);
+        }
+    }
+
+    ///Rates of the inputs and output
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates = self.output.rates();
+        rates.extend(self.inputs.iter().flat_map(ManageResourceFlow::rates));
+        rates
+    }
+
+    ///Empty the flows before a calculation
+    pub(crate) fn reset(&mut self) {
+        self.output.reset();
+        self.output.created.amount = 0.0;
+        for input in self.inputs.iter_mut() {
+            input.reset();
+            input.needed.amount = 0.0;
+        }
+    }
+
+    ///Join what the inputs get in the output
+    pub(crate) fn internal_calculation(&mut self, settings: &TimeSettings) {
+        let amount = self
+            .inputs
+            .iter()
+            .map(|input| hourly_amount(&input.total_in(settings), settings))
+            .sum();
+        self.output.created = hourly_flow(&self.resource(), amount);
+    }
+}
+
+impl RecipeWindowGUI for ResourceMerger {
+    fn show(&mut self, commons: &mut CommonsManager, ctx: &egui::Context, enabled: bool) -> bool {
+        self.window_coordinate.in_flow.clear();
+        self.window_coordinate.out_flow.clear();
+
+        let mut open = true;
+        let mut ports_changed = false;
+
+        let resource = self.resource();
+        let settings = commons.time_settings.clone();
+        let mut renamed = None;
+
+        let response = egui::Window::new("Merger")
+            .id(self.id)
+            .enabled(enabled)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                for (index, input) in self.inputs.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let btn_resp = ui.button("⭕");
+                        self.window_coordinate.in_flow.push(btn_resp.rect);
+                        if btn_resp.clicked() && commons.arrow_active {
+                            commons.clicked_place_arrow_info = Some((
+                                Some(resource.clone()),
+                                self.id,
+                                index,
+                                RecipeWindowType::Merger,
+                            ));
+                        }
+                        ui.label(format!("{}.", index + 1));
+                        ui.label(flow_label(&input.total_in(&settings), &settings));
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("➕").on_hover_text("Add an input").clicked() {
+                        self.add_input();
+                        ports_changed = true;
+                    }
+                    if ui
+                        .add_enabled(self.inputs.len() > 1, egui::Button::new("➖"))
+                        .on_hover_text("Remove the last input")
+                        .clicked()
+                    {
+                        self.remove_input();
+                        ports_changed = true;
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    renamed = recipe_window::resource_name_edit(
+                        ui,
+                        commons,
+                        self.id.with("Resource name"),
+                        &resource.name,
+                    );
+                    ui.label(flow_label(&self.output.total_out(&settings), &settings));
+
+                    let btn_resp = ui.button("⭕");
+                    self.window_coordinate.out_flow.push(btn_resp.rect);
+                    if btn_resp.clicked() {
+                        commons.clicked_start_arrow_info = Some((
+                            resource.clone(),
+                            self.id,
+                            ui.layer_id(),
+                            0,
+                            RecipeWindowType::Merger,
+                        ));
+                    }
+                });
+            });
+        let inner_response = response.unwrap();
+        self.window_coordinate.window = inner_response.response.rect;
+
+        if let Some(name) = renamed {
+            let new = commons.resources.resolve(&name, resource.unit);
+            debug!("Resource of a merger changed from {} to {}", resource, new);
+            self.set_resource(new);
+            ports_changed = true;
+        }
+        if ports_changed {
+            commons.check_arrows = true;
+            commons.recalculate = true;
+        }
+
+        if inner_response.inner.is_none() {
+            inner_response.response.on_hover_ui(|ui| {
+                ui.label(
+                    self.generate_tooltip(&commons.time_settings)
+                        .unwrap_or_else(|_| "Error generating tooltip".to_string()),
+                );
+            });
+        }
+
+        if open {
+            commons
+                .window_coordinates
+                .insert(self.id, self.window_coordinate.clone());
+        } else {
+            commons.window_coordinates.remove(&self.id);
+        }
+
+        open
+    }
+
+    fn generate_tooltip(&self, _settings: &TimeSettings) -> Result<String, std::fmt::Error> {
+        let mut tooltip = String::new();
+        write!(
+            tooltip,
+            "Merger of {} from {} inputs",
+            self.resource().name,
+            self.inputs.len()
+        )?;
+        Ok(tooltip)
+    }
+}
@@ -1,10 +1,13 @@
 use crate::app::commons::CommonsManager;
 use crate::app::coordinates_info::CoordinatesInfo;
+use crate::app::recipe_graph::{hourly_amount, hourly_flow};
 use crate::app::recipe_window;
+use crate::app::recipe_window::resource_splitter::flow_label;
 use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
 use crate::app::resources::recipe_input_resource::RecipeInputResource;
 use crate::app::resources::resource_flow::ManageResourceFlow;
-use crate::app::resources::RatePer;
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{RatePer, ResourceDefinition, Unit};
 use crate::utils::gen_id;
 use egui::Widget;
 use std::fmt::Write;
@@ -15,7 +18,27 @@ pub(crate) struct ResourceSink {
     pub(crate) id: egui::Id,
 
     ///output
-    pub(crate) sink: Option<RecipeInputResource<usize>>,
+    pub(crate) sink: Option<RecipeInputResource<f32>>,
+
+    ///the sink has a desired amount the graph is planned for
+    #[serde(default)]
+    pub(crate) targeted: bool,
+
+    ///target amount
+    #[serde(default)]
+    pub(crate) target_amount: f32,
+
+    ///target rate
+    #[serde(default = "default_target_rate")]
+    pub(crate) target_rate: RatePer,
+
+    ///the sink was created to take the excess of an output
+    #[serde(default)]
+    pub(crate) overflow: bool,
+
+    ///storage window the sink is shown in with other sinks, None for a window of its own
+    #[serde(default)]
+    pub(crate) group: Option<egui::Id>,
 
     #[serde(skip)]
     window_coordinate: CoordinatesInfo,
@@ -26,9 +49,250 @@ impl ResourceSink {
         Self {
             id: gen_id("ResourceSink".to_string()),
             sink: None,
+            targeted: false,
+            target_amount: 1.0,
+            target_rate: default_target_rate(),
+            overflow: false,
+            group: None,
             window_coordinate: Default::default(),
         }
     }
+
+    ///Sink shown in a storage window, it takes the resource of the first arrow like the others
+    pub(crate) fn in_group(group: egui::Id) -> Self {
+        let mut new = ResourceSink::new();
+        new.group = Some(group);
+        new
+    }
+
+    pub(crate) fn overflow_sink() -> Self {
+        let mut new = ResourceSink::new();
+        new.overflow = true;
+        new
+    }
+
+    #[cfg(test)]
+    pub(crate) fn targeted_sink(amount: f32, rate: RatePer) -> Self {
+        let mut new = ResourceSink::new();
+        new.target_amount = amount;
+        new.target_rate = rate;
+        new.targeted = true;
+        new
+    }
+
+    ///Unit of the flow sunk, the target is in this unit
+    fn unit(&self) -> Unit {
+        self.sink
+            .as_ref()
+            .map(|sink| sink.resource().unit)
+            .unwrap_or(Unit::Piece)
+    }
+
+    ///Target of the sink in base units of its resource per hour
+    pub(crate) fn hourly_target(&self, settings: &TimeSettings) -> f64 {
+        f64::from(self.target_amount * self.target_rate.per_hour(settings) * self.unit().factor())
+    }
+
+    ///Rates of the input and the target
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates: Vec<RatePer> = self.sink.iter().flat_map(|sink| sink.rates()).collect();
+        rates.push(self.target_rate);
+        rates
+    }
+}
+
+impl ResourceSink {
+    /// Show the input of the sink, the flow it gets and its target
+    ///
+    /// # Arguments
+    ///
+    /// * `ui`: where to show the rows
+    /// * `commons`: gets the arrow placed on the input
+    /// * `removable`: a button removes the sink from its storage window
+    ///
+    /// returns: the resource name typed if it changed, and whether the removal was asked
+    fn rows(
+        &mut self,
+        ui: &mut egui::Ui,
+        commons: &mut CommonsManager,
+        removable: bool,
+    ) -> (Option<String>, bool) {
+        let mut renamed = None;
+        let mut removed = false;
+        ui.horizontal(|ui| {
+            let btn_resp = ui.button("⭕");
+
+            self.window_coordinate.in_flow.push(btn_resp.rect);
+
+            if btn_resp.clicked() && commons.arrow_active {
+                commons.clicked_place_arrow_info = Some((
+                    self.sink.as_ref().map(|sink| sink.resource()),
+                    self.id,
+                    0,
+                    RecipeWindowType::Sink,
+                ));
+            }
+
+            match &self.sink {
+                Some(sink) => {
+                    renamed = recipe_window::resource_name_edit(
+                        ui,
+                        commons,
+                        self.id.with("Resource name"),
+                        &sink.resource().name,
+                    );
+                    let settings = &commons.time_settings;
+                    let flow = sink.total_in(settings).displayed(settings);
+                    let mut amount_per_time = flow.amount;
+                    let rate = flow.rate;
+                    egui::DragValue::new(&mut amount_per_time).ui(ui);
+                    ui.label(egui::RichText::new(format!(
+                        "{}{}",
+                        flow.resource.unit.symbol(),
+                        rate.to_shortened_string(settings)
+                    )));
+                }
+                None if removable => {
+                    ui.weak("Any resource");
+                }
+                None => {}
+            }
+            if removable {
+                removed = ui
+                    .button("🗑")
+                    .on_hover_text("Remove the resource from the storage")
+                    .clicked();
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut changed = ui.checkbox(&mut self.targeted, "Target").changed();
+
+            if self.targeted {
+                changed |= egui::DragValue::new(&mut self.target_amount)
+                    .clamp_range(0.0..=f32::MAX)
+                    .ui(ui)
+                    .changed();
+                ui.label(self.unit().symbol());
+                changed |=
+                    recipe_window::rate_combo(ui, &mut self.target_rate, &commons.time_settings);
+            }
+            if changed {
+                commons.recalculate = true;
+            }
+        });
+        (renamed, removed)
+    }
+
+    ///Give the sink the resource of a name typed by the user
+    fn rename(&mut self, commons: &mut CommonsManager, name: &str) {
+        if let Some(sink) = self.sink.as_mut() {
+            let new = commons.resources.resolve(name, sink.resource().unit);
+            sink.set_resource(new);
+            commons.check_arrows = true;
+            commons.recalculate = true;
+        }
+    }
+}
+
+/// Show the sinks of a group in one storage window, with the total they get for every kind of
+/// unit. Closing the window removes all of them.
+///
+/// # Arguments
+///
+/// * `sinks`: every sink of the graph
+/// * `group`: id of the storage window
+/// * `commons`: shared state of the windows
+/// * `ctx`: the context to show the window in
+/// * `enabled`: the window can be interacted with
+///
+/// returns: ()
+pub(crate) fn show_group(
+    sinks: &mut Vec<ResourceSink>,
+    group: egui::Id,
+    commons: &mut CommonsManager,
+    ctx: &egui::Context,
+    enabled: bool,
+) {
+    let mut open = true;
+    let mut renamed = vec![];
+    let mut removed = vec![];
+    let mut added = false;
+
+    let response = egui::Window::new("Storage")
+        .id(group)
+        .enabled(enabled)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            let mut totals: Vec<(Unit, f64)> = vec![];
+            for sink in sinks.iter_mut().filter(|sink| sink.group == Some(group)) {
+                sink.window_coordinate.in_flow.clear();
+                sink.window_coordinate.out_flow.clear();
+                ui.push_id(sink.id, |ui| {
+                    let (name, remove) = sink.rows(ui, commons, true);
+                    if let Some(name) = name {
+                        renamed.push((sink.id, name));
+                    }
+                    if remove {
+                        removed.push(sink.id);
+                    }
+                });
+                if let Some(input) = &sink.sink {
+                    let unit = input.resource().unit.dimension().base_unit();
+                    let settings = &commons.time_settings;
+                    let amount = hourly_amount(&input.total_in(settings), settings);
+                    match totals.iter_mut().find(|(u, _)| *u == unit) {
+                        Some((_, total)) => *total += amount,
+                        None => totals.push((unit, amount)),
+                    }
+                }
+                ui.separator();
+            }
+            for (unit, amount) in totals {
+                let resource = ResourceDefinition {
+                    name: "Total".to_string(),
+                    unit,
+                };
+                ui.label(format!(
+                    "Total: {}",
+                    flow_label(&hourly_flow(&resource, amount), &commons.time_settings)
+                ));
+            }
+            if ui.button("➕").on_hover_text("Add a resource").clicked() {
+                added = true;
+            }
+        });
+
+    let rect = response.map(|response| response.response.rect);
+    for (id, name) in renamed {
+        if let Some(sink) = sinks.iter_mut().find(|sink| sink.id == id) {
+            sink.rename(commons, &name);
+        }
+    }
+    sinks.retain(|sink| {
+        let kept = sink.group != Some(group) || (open && !removed.contains(&sink.id));
+        if !kept {
+            commons.window_coordinates.remove(&sink.id);
+        }
+        kept
+    });
+    for sink in sinks.iter_mut().filter(|sink| sink.group == Some(group)) {
+        if let Some(rect) = rect {
+            sink.window_coordinate.window = rect;
+        }
+        commons
+            .window_coordinates
+            .insert(sink.id, sink.window_coordinate.clone());
+    }
+    if added {
+        sinks.push(ResourceSink::in_group(group));
+    }
+    if !removed.is_empty() || !open {
+        commons.recalculate = true;
+    }
+}
+
+fn default_target_rate() -> RatePer {
+    RatePer::Minute
 }
 
 impl RecipeWindowGUI for ResourceSink {
@@ -37,48 +301,30 @@ impl RecipeWindowGUI for ResourceSink {
         self.window_coordinate.out_flow.clear();
 
         let mut open = true;
+        let mut renamed = None;
 
-        let response = egui::Window::new("Resource sink")
+        let title = match self.overflow {
+            true => "Overflow sink",
+            false => "Resource sink",
+        };
+        let response = egui::Window::new(title)
             .id(self.id)
             .enabled(enabled)
             .open(&mut open)
             .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    let btn_resp = ui.button("⭕");
-
-                    self.window_coordinate.in_flow.push(btn_resp.rect);
-
-                    if btn_resp.clicked() && commons.arrow_active {
-                        commons.clicked_place_arrow_info = Some((
-                            self.sink.as_ref().map(|sink| sink.resource()),
-                            self.id,
-                            0,
-                            RecipeWindowType::Sink,
-                        ));
-                    }
-
-                    if let Some(sink) = &self.sink {
-                        let mut resource_name = sink.resource().name;
-                        recipe_window::text_edit(ui, &mut resource_name);
-                        let mut amount_per_time = sink.total_in().amount;
-                        let rate = sink.total_in().rate;
-                        egui::DragValue::new(&mut amount_per_time).ui(ui);
-                        ui.label(egui::RichText::new(match rate {
-                            RatePer::Tick => "/tick",
-                            RatePer::Second => "/s",
-                            RatePer::Minute => "/min ",
-                            RatePer::Hour => "/h",
-                        }));
-                    }
-                });
+                (renamed, _) = self.rows(ui, commons, false);
             });
         let inner_response = response.unwrap();
         self.window_coordinate.window = inner_response.response.rect;
 
+        if let Some(name) = renamed {
+            self.rename(commons, &name);
+        }
+
         if inner_response.inner.is_none() {
             inner_response.response.on_hover_ui(|ui| {
                 ui.label(
-                    self.generate_tooltip()
+                    self.generate_tooltip(&commons.time_settings)
                         .unwrap_or_else(|_| "Error generating tooltip".to_string()),
                 );
             });
@@ -95,20 +341,32 @@ impl RecipeWindowGUI for ResourceSink {
         open
     }
 
-    fn generate_tooltip(&self) -> Result<String, std::fmt::Error> {
+    fn generate_tooltip(&self, settings: &TimeSettings) -> Result<String, std::fmt::Error> {
         let mut tooltip = String::new();
 
         match &self.sink {
             None => {}
             Some(input) => {
-                let flow = &input.needed;
+                let flow = input.needed.displayed(settings);
                 write!(
                     tooltip,
-                    "Sink of {}. {} {}",
-                    flow.resource.name, flow.amount, flow.rate
+                    "Sink of {}. {} {} Per {}",
+                    flow.resource.name,
+                    flow.amount,
+                    flow.resource.unit.symbol(),
+                    flow.rate.name(settings)
                 )?;
             }
         }
+        if self.targeted {
+            write!(
+                tooltip,
+                "\nTarget: {} {} Per {}",
+                self.target_amount,
+                self.unit().symbol(),
+                self.target_rate.name(settings)
+            )?;
+        }
 
         Ok(tooltip)
     }
@@ -0,0 +1,417 @@
+use crate::app::commons::CommonsManager;
+use crate::app::coordinates_info::CoordinatesInfo;
+use crate::app::recipe_graph::{hourly_amount, hourly_flow};
+use crate::app::recipe_window;
+use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
+use crate::app::resources::recipe_input_resource::RecipeInputResource;
+use crate::app::resources::recipe_output_resource::RecipeOutputResource;
+use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{RatePer, ResourceDefinition};
+use crate::utils::{formatting, gen_id};
+use egui::Widget;
+use log::debug;
+use std::fmt::{Display, Formatter, Write};
+
+///Way a splitter divides its input between its connected outputs
+#[derive(serde::Deserialize, serde::Serialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum SplitMode {
+    ///every connected output gets the same amount
+    #[default]
+    Even,
+
+    ///the connected outputs get amounts proportional to their ratio
+    Ratio,
+
+    ///the outputs are served in order up to what they need, the last connected one takes the rest
+    Priority,
+}
+
+impl SplitMode {
+    pub(crate) fn all() -> [SplitMode; 3] {
+        [SplitMode::Even, SplitMode::Ratio, SplitMode::Priority]
+    }
+}
+
+impl Display for SplitMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitMode::Even => write!(f, "Even"),
+            SplitMode::Ratio => write!(f, "Ratio"),
+            SplitMode::Priority => write!(f, "Priority"),
+        }
+    }
+}
+
+///Node dividing one flow of a resource between several outputs
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub(crate) struct ResourceSplitter {
+    ///unique id of the window
+    pub(crate) id: egui::Id,
+
+    ///the flow split
+    pub(crate) input: RecipeInputResource<f32>,
+
+    ///the parts of the flow, in priority order
+    pub(crate) outputs: Vec<RecipeOutputResource<f32>>,
+
+    ///how the input is divided
+    #[serde(default)]
+    pub(crate) mode: SplitMode,
+
+    ///weight of every output in ratio mode
+    #[serde(default)]
+    pub(crate) ratios: Vec<f32>,
+
+    #[serde(skip)]
+    window_coordinate: CoordinatesInfo,
+}
+
+impl ResourceSplitter {
+    pub(crate) fn new(resource: ResourceDefinition) -> Self {
+        let mut new = Self {
+            id: gen_id(format!("Splitter{}", resource.name)),
+            input: RecipeInputResource::new(resource.clone(), hourly_flow(&resource, 0.0)),
+            outputs: vec![],
+            mode: SplitMode::default(),
+            ratios: vec![],
+            window_coordinate: Default::default(),
+        };
+        new.add_output();
+        new.add_output();
+        new
+    }
+
+    pub(crate) fn resource(&self) -> ResourceDefinition {
+        self.input.resource()
+    }
+
+    pub(crate) fn add_output(&mut self) {
+        let resource = self.resource();
+        self.outputs.push(RecipeOutputResource::new(
+            resource.clone(),
+            hourly_flow(&resource, 0.0),
+        ));
+        self.ratios.resize(self.outputs.len(), 1.0);
+    }
+
+    ///Remove the last output, a splitter keeps at least one
+    pub(crate) fn remove_output(&mut self) {
+        if self.outputs.len() > 1 {
+            self.outputs.pop();
+            self.ratios.truncate(self.outputs.len());
+        }
+    }
+
+    pub(crate) fn set_resource(&mut self, resource: ResourceDefinition) {
+        self.input.set_resource(resource.clone());
+        for output in self.outputs.iter_mut() {
+            output.set_resource(resource.clone());
+        }
+    }
+
+    pub(crate) fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        self.input.rename_resource(old, new);
+        for output in self.outputs.iter_mut() {
+            output.rename_resource(old, new);
+        }
+    }
+
+    ///Rates of the input and outputs
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates = self.input.rates();
+        rates.extend(self.outputs.iter().flat_map(ManageResourceFlow::rates));
+        rates
+    }
+
+    ///Empty the flows before a calculation
+    pub(crate) fn reset(&mut self) {
+        self.input.reset();
+        self.input.needed.amount = 0.0;
+        for output in self.outputs.iter_mut() {
+            output.reset();
+            output.created.amount = 0.0;
+        }
+    }
+
+    ///Weight of an output in ratio mode
+    fn ratio(&self, output: usize) -> f64 {
+        f64::from(self.ratios.get(output).copied().unwrap_or(1.0).max(0.0))
+    }
+
+    ///Part of the input going to every output in even and ratio modes, zero if not connected
+    fn shares(&self, demands: &[Option<f64>]) -> Vec<f64> {
+        let weights: Vec<f64> = demands
+            .iter()
+            .enumerate()
+            .map(|(output, demand)| match (demand, self.mode) {
+                (None, _) => 0.0,
+                (Some(_), SplitMode::Ratio) => self.ratio(output),
+                (Some(_), _) => 1.0,
+            })
+            .collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return vec![0.0; weights.len()];
+        }
+        weights.iter().map(|weight| weight / total).collect()
+    }
+
+    /// Divide an amount between the outputs
+    ///
+    /// # Arguments
+    ///
+    /// * `amount`: the amount split, per hour in base units
+    /// * `demands`: amount needed at every output, None if nothing is connected to it
+    ///
+    /// returns: the amount of every output
+    pub(crate) fn split(&self, amount: f64, demands: &[Option<f64>]) -> Vec<f64> {
+        match self.mode {
+            SplitMode::Even | SplitMode::Ratio => self
+                .shares(demands)
+                .iter()
+                .map(|share| share * amount)
+                .collect(),
+            SplitMode::Priority => {
+                let mut left = amount;
+                let mut parts: Vec<f64> = demands
+                    .iter()
+                    .map(|demand| {
+                        let part = demand.map_or(0.0, |demand| demand.min(left));
+                        left -= part;
+                        part
+                    })
+                    .collect();
+                if let Some(last) = demands.iter().rposition(Option::is_some) {
+                    parts[last] += left;
+                }
+                parts
+            }
+        }
+    }
+
+    /// Amount the input must get for every output to get its demand
+    ///
+    /// # Arguments
+    ///
+    /// * `demands`: amount needed at every output, None if nothing is connected to it
+    ///
+    /// returns: the amount, per hour in base units
+    pub(crate) fn needed_for(&self, demands: &[Option<f64>]) -> f64 {
+        match self.mode {
+            SplitMode::Even | SplitMode::Ratio => self
+                .shares(demands)
+                .iter()
+                .zip(demands.iter())
+                .filter_map(|(share, demand)| match (share > &0.0, demand) {
+                    (true, Some(demand)) => Some(demand / share),
+                    _ => None,
+                })
+                .fold(0.0, f64::max),
+            SplitMode::Priority => demands.iter().flatten().sum(),
+        }
+    }
+
+    /// Split what the input gets between the outputs
+    ///
+    /// # Arguments
+    ///
+    /// * `demands`: amount needed at every output, None if nothing is connected to it
+    /// * `settings`: the time settings the input is converted with
+    pub(crate) fn internal_calculation(
+        &mut self,
+        demands: &[Option<f64>],
+        settings: &TimeSettings,
+    ) {
+        let amount = hourly_amount(&self.input.total_in(settings), settings);
+        let parts = self.split(amount, demands);
+        let resource = self.resource();
+        for (output, part) in self.outputs.iter_mut().zip(parts) {
+            output.created = hourly_flow(&resource, part);
+        }
+    }
+}
+
+impl RecipeWindowGUI for ResourceSplitter {
+    fn show(&mut self, commons: &mut CommonsManager, ctx: &egui::Context, enabled: bool) -> bool {
+        self.window_coordinate.in_flow.clear();
+        self.window_coordinate.out_flow.clear();
+
+        let mut open = true;
+        let mut changed = false;
+        let mut ports_changed = false;
+
+        let resource = self.resource();
+        let settings = commons.time_settings.clone();
+        let mut renamed = None;
+
+        let response = egui::Window::new("Splitter")
+            .id(self.id)
+            .enabled(enabled)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let btn_resp = ui.button("⭕");
+                    self.window_coordinate.in_flow.push(btn_resp.rect);
+                    if btn_resp.clicked() && commons.arrow_active {
+                        commons.clicked_place_arrow_info = Some((
+                            Some(resource.clone()),
+                            self.id,
+                            0,
+                            RecipeWindowType::Splitter,
+                        ));
+                    }
+
+                    renamed = recipe_window::resource_name_edit(
+                        ui,
+                        commons,
+                        self.id.with("Resource name"),
+                        &resource.name,
+                    );
+                    ui.label(flow_label(&self.input.total_in(&settings), &settings));
+                });
+
+                egui::ComboBox::from_id_source(self.id.with("Split mode"))
+                    .selected_text(self.mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in SplitMode::all() {
+                            changed |= ui
+                                .selectable_value(&mut self.mode, mode, mode.to_string())
+                                .changed();
+                        }
+                    });
+
+                for (index, output) in self.outputs.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", index + 1));
+                        if self.mode == SplitMode::Ratio {
+                            changed |= egui::DragValue::new(&mut self.ratios[index])
+                                .clamp_range(0.0..=f32::MAX)
+                                .speed(0.1)
+                                .ui(ui)
+                                .on_hover_text("Ratio of the output")
+                                .changed();
+                        }
+                        ui.label(flow_label(&output.total_out(&settings), &settings));
+
+                        let btn_resp = ui.button("⭕");
+                        self.window_coordinate.out_flow.push(btn_resp.rect);
+                        if btn_resp.clicked() {
+                            commons.clicked_start_arrow_info = Some((
+                                resource.clone(),
+                                self.id,
+                                ui.layer_id(),
+                                index,
+                                RecipeWindowType::Splitter,
+                            ));
+                        }
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("➕").on_hover_text("Add an output").clicked() {
+                        self.add_output();
+                        ports_changed = true;
+                    }
+                    if ui
+                        .add_enabled(self.outputs.len() > 1, egui::Button::new("➖"))
+                        .on_hover_text("Remove the last output")
+                        .clicked()
+                    {
+                        self.remove_output();
+                        ports_changed = true;
+                    }
+                });
+            });
+        let inner_response = response.unwrap();
+        self.window_coordinate.window = inner_response.response.rect;
+
+        if let Some(name) = renamed {
+            let new = commons.resources.resolve(&name, resource.unit);
+            debug!(
+                "Resource of a splitter changed from {} to {}",
+                resource, new
+            );
+            self.set_resource(new);
+            ports_changed = true;
+        }
+        if ports_changed {
+            commons.check_arrows = true;
+        }
+        if changed || ports_changed {
+            commons.recalculate = true;
+        }
+
+        if inner_response.inner.is_none() {
+            inner_response.response.on_hover_ui(|ui| {
+                ui.label(
+                    self.generate_tooltip(&commons.time_settings)
+                        .unwrap_or_else(|_| "Error generating tooltip".to_string()),
+                );
+            });
+        }
+
+        if open {
+            commons
+                .window_coordinates
+                .insert(self.id, self.window_coordinate.clone());
+        } else {
+            commons.window_coordinates.remove(&self.id);
+        }
+
+        open
+    }
+
+    fn generate_tooltip(&self, _settings: &TimeSettings) -> Result<String, std::fmt::Error> {
+        let mut tooltip = String::new();
+        write!(
+            tooltip,
+            "{} split of {} in {} outputs",
+            self.mode,
+            self.resource().name,
+            self.outputs.len()
+        )?;
+        Ok(tooltip)
+    }
+}
+
+///Amount and rate of a flow as shown in the node windows
+pub(crate) fn flow_label(flow: &ResourceFlow<f32, f32>, settings: &TimeSettings) -> String {
+    let flow = flow.displayed(settings);
+    format!(
+        "{} {}{}",
+        formatting::float_format(flow.amount, 3),
+        flow.resource.unit.symbol(),
+        flow.rate.to_shortened_string(settings)
+    )
+}
+
+//-------------------Tests-------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::app::recipe_window::resource_splitter::{ResourceSplitter, SplitMode};
+    use crate::app::resources::test::setup_resource;
+    use crate::utils::test_env;
+
+    #[test]
+    fn test_split() {
+        test_env::setup();
+        let mut splitter = ResourceSplitter::new(setup_resource("Ore"));
+        splitter.add_output();
+        let demands = [Some(10.0), None, Some(30.0)];
+
+        assert_eq!(splitter.split(60.0, &demands), vec![30.0, 0.0, 30.0]);
+        assert_eq!(splitter.needed_for(&demands), 60.0);
+
+        splitter.mode = SplitMode::Ratio;
+        splitter.ratios = vec![1.0, 5.0, 3.0];
+        assert_eq!(splitter.split(60.0, &demands), vec![15.0, 0.0, 45.0]);
+        assert_eq!(splitter.needed_for(&demands), 40.0);
+
+        splitter.mode = SplitMode::Priority;
+        assert_eq!(splitter.split(60.0, &demands), vec![10.0, 0.0, 50.0]);
+        assert_eq!(splitter.split(20.0, &demands), vec![10.0, 0.0, 10.0]);
+        assert_eq!(splitter.needed_for(&demands), 40.0);
+    }
+}
@@ -4,19 +4,100 @@ use crate::app::recipe_window;
 use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
 use crate::app::resources::recipe_output_resource::RecipeOutputResource;
 use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
+use crate::app::resources::time_settings::TimeSettings;
 use crate::app::resources::{RatePer, ResourceDefinition, Unit};
-use crate::utils::gen_id;
+use crate::utils::{formatting, gen_id};
 use egui::Widget;
 use log::debug;
 use std::fmt::Write;
 
+///Purities proposed for a deposit, with their multiplier of the extraction speed
+const PURITIES: [(&str, f32); 3] = [("Impure", 0.5), ("Normal", 1.0), ("Pure", 2.0)];
+
+///Machines extracting a source and the deposit they work on
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Extraction {
+    ///machine extracting the resource, like a miner or a pump
+    pub(crate) machine: String,
+
+    ///amount one machine extracts from a normal deposit, in the unit of the source per `machine_rate`
+    pub(crate) machine_amount: f32,
+
+    pub(crate) machine_rate: RatePer,
+
+    ///number of machines the deposit has room for
+    pub(crate) machines_per_deposit: u32,
+
+    ///multiplier of the extraction speed, like the purity of a node or the richness of a patch
+    pub(crate) purity: f32,
+
+    ///amount in the deposit in the unit of the source, None when it never runs out
+    pub(crate) deposit: Option<f32>,
+}
+
+impl Default for Extraction {
+    fn default() -> Self {
+        Self {
+            machine: "Miner".to_string(),
+            machine_amount: 60.0,
+            machine_rate: RatePer::Minute,
+            machines_per_deposit: 1,
+            purity: 1.0,
+            deposit: None,
+        }
+    }
+}
+
+impl Extraction {
+    ///Amount one machine extracts from this deposit per hour in base units
+    fn hourly_per_machine(&self, unit: Unit, settings: &TimeSettings) -> f64 {
+        let rate = self.machine_rate.per_hour(settings);
+        f64::from(self.machine_amount * self.purity * rate * unit.factor())
+    }
+
+    ///Most the deposit gives per hour in base units, with every machine it has room for
+    pub(crate) fn hourly_capacity(&self, unit: Unit, settings: &TimeSettings) -> f64 {
+        self.hourly_per_machine(unit, settings) * f64::from(self.machines_per_deposit)
+    }
+
+    /// Number of machines extracting a draw
+    ///
+    /// # Arguments
+    ///
+    /// * `unit`: unit of the source
+    /// * `drawn`: the draw per hour in base units
+    /// * `settings`: the time settings the rate of the machines is converted with
+    ///
+    /// returns: the number of machines, fractional like the machines of the recipes
+    pub(crate) fn machines(&self, unit: Unit, drawn: f64, settings: &TimeSettings) -> f64 {
+        let per_machine = self.hourly_per_machine(unit, settings);
+        match per_machine > 0.0 {
+            true => drawn / per_machine,
+            false => f64::INFINITY,
+        }
+    }
+
+    /// Time until the deposit runs out
+    ///
+    /// # Arguments
+    ///
+    /// * `unit`: unit of the source
+    /// * `drawn`: the draw per hour in base units
+    ///
+    /// returns: the hours left, None if the deposit never runs out at this draw
+    pub(crate) fn depletion_hours(&self, unit: Unit, drawn: f64) -> Option<f64> {
+        let deposit = f64::from(self.deposit? * unit.factor());
+        (drawn > 0.0).then(|| deposit / drawn)
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
 pub struct ResourceSource {
     ///unique id of the resource
     pub(crate) id: egui::Id,
 
     ///output
-    pub(crate) output: RecipeOutputResource<usize>,
+    pub(crate) output: RecipeOutputResource<f32>,
 
     ///limited output
     pub(crate) limited_output: bool,
@@ -30,6 +111,22 @@ pub struct ResourceSource {
     //force the limitation to be active
     pub(crate) force_limited: bool,
 
+    ///the planned draw is above the limit
+    #[serde(skip)]
+    pub(crate) over_limit: bool,
+
+    ///machines and deposit behind the source, None when they aren't modelled
+    #[serde(default)]
+    pub(crate) extraction: Option<Extraction>,
+
+    ///planned draw per hour in base units at the last calculation
+    #[serde(skip)]
+    pub(crate) drawn: f64,
+
+    ///outpost window the source is shown in with other sources, None for a window of its own
+    #[serde(default)]
+    pub(crate) group: Option<egui::Id>,
+
     #[serde(skip)]
     window_coordinate: CoordinatesInfo,
 }
@@ -40,48 +137,26 @@ impl RecipeWindowGUI for ResourceSource {
         self.window_coordinate.out_flow.clear();
 
         let mut open = true;
-
-        let resource = self.output.resource();
-
-        let mut resource_name = resource.name.clone();
+        let mut renamed = None;
 
         let response = egui::Window::new("Resource source")
             .id(self.id)
             .enabled(enabled)
             .open(&mut open)
             .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    recipe_window::text_edit(ui, &mut resource_name);
-
-                    ui.checkbox(&mut self.limited_output, "Limited");
-
-                    if self.limited_output {
-                        egui::DragValue::new(&mut self.limit_amount).ui(ui);
-                        recipe_window::rate_combo(ui, &mut self.limit_rate);
-                    }
-
-                    let btn_resp = ui.button("⭕");
-
-                    self.window_coordinate.out_flow.push(btn_resp.rect);
-
-                    if btn_resp.clicked() {
-                        commons.clicked_start_arrow_info = Some((
-                            resource.clone(),
-                            self.id,
-                            ui.layer_id(),
-                            0,
-                            RecipeWindowType::Source,
-                        ));
-                    }
-                });
+                (renamed, _) = self.row(ui, commons, false);
             });
         let inner_response = response.unwrap();
         self.window_coordinate.window = inner_response.response.rect;
 
+        if let Some(name) = renamed {
+            self.rename(commons, &name);
+        }
+
         if inner_response.inner.is_none() {
             inner_response.response.on_hover_ui(|ui| {
                 ui.label(
-                    self.generate_tooltip()
+                    self.generate_tooltip(&commons.time_settings)
                         .unwrap_or_else(|_| "Error generating tooltip".to_string()),
                 );
             });
@@ -98,34 +173,61 @@ impl RecipeWindowGUI for ResourceSource {
         open
     }
 
-    fn generate_tooltip(&self) -> Result<String, std::fmt::Error> {
+    fn generate_tooltip(&self, settings: &TimeSettings) -> Result<String, std::fmt::Error> {
         let mut tooltip = String::new();
-        let flow = &self.output.created;
+        let flow = self.output.created.displayed(settings);
         write!(
             tooltip,
-            "Source of {}. {} {}",
-            flow.resource.name, flow.amount, flow.rate
+            "Source of {}. {} {} Per {}",
+            flow.resource.name,
+            flow.amount,
+            flow.resource.unit.symbol(),
+            flow.rate.name(settings)
         )?;
+        if let Some(extraction) = &self.extraction {
+            let unit = flow.resource.unit;
+            write!(
+                tooltip,
+                "\n{} × {}",
+                formatting::float_format(extraction.machines(unit, self.drawn, settings), 3),
+                extraction.machine
+            )?;
+            if let Some(hours) = extraction.depletion_hours(unit, self.drawn) {
+                write!(
+                    tooltip,
+                    ", depleted in {}",
+                    formatting::duration_format(hours, 3)
+                )?;
+            }
+        }
         Ok(tooltip)
     }
 }
 
 impl ResourceSource {
     pub fn new(resource: String) -> Self {
-        let r = ResourceDefinition {
-            name: resource.clone(),
+        Self::from_definition(ResourceDefinition {
+            name: resource,
             unit: Unit::Piece,
-        };
+        })
+    }
+
+    ///Source of a resource of the catalog
+    pub fn from_definition(r: ResourceDefinition) -> Self {
         Self {
-            id: gen_id(resource),
+            id: gen_id(r.name.clone()),
             output: RecipeOutputResource::new(
                 r.clone(),
-                ResourceFlow::new(&r, 10, 1.0f32, RatePer::Tick),
+                ResourceFlow::new(&r, 10.0, 1.0f32, RatePer::Tick),
             ),
             limited_output: false,
             limit_amount: 1.0,
             limit_rate: RatePer::Second,
             force_limited: false,
+            over_limit: false,
+            extraction: None,
+            drawn: 0.0,
+            group: None,
             window_coordinate: Default::default(),
         }
     }
@@ -139,6 +241,206 @@ impl ResourceSource {
         new
     }
 
+    ///Limit of the source in base units of its resource per hour
+    pub(crate) fn hourly_limit(&self, settings: &TimeSettings) -> f64 {
+        let unit = self.output.resource().unit;
+        f64::from(self.limit_amount * self.limit_rate.per_hour(settings) * unit.factor())
+    }
+
+    ///Rates of the output, the limit and the extracting machines
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates = self.output.rates();
+        rates.push(self.limit_rate);
+        rates.extend(
+            self.extraction
+                .iter()
+                .map(|extraction| extraction.machine_rate),
+        );
+        rates
+    }
+
+    ///Source shown in an outpost window, the user names its resource
+    pub(crate) fn in_group(group: egui::Id) -> Self {
+        let mut new = ResourceSource::new(String::new());
+        new.group = Some(group);
+        new
+    }
+
+    /// Show the resource of the source, its limit and the output arrows start from
+    ///
+    /// # Arguments
+    ///
+    /// * `ui`: where to show the row
+    /// * `commons`: gets the arrow started from the output
+    /// * `removable`: a button removes the source from its outpost window
+    ///
+    /// returns: the resource name typed if it changed, and whether the removal was asked
+    fn row(
+        &mut self,
+        ui: &mut egui::Ui,
+        commons: &mut CommonsManager,
+        removable: bool,
+    ) -> (Option<String>, bool) {
+        let resource = self.output.resource();
+        let mut renamed = None;
+        let mut removed = false;
+        ui.horizontal(|ui| {
+            renamed = recipe_window::resource_name_edit(
+                ui,
+                commons,
+                self.id.with("Resource name"),
+                &resource.name,
+            );
+
+            ui.checkbox(&mut self.limited_output, "Limited");
+
+            if self.limited_output {
+                egui::DragValue::new(&mut self.limit_amount).ui(ui);
+                ui.label(resource.unit.symbol());
+                recipe_window::rate_combo(ui, &mut self.limit_rate, &commons.time_settings);
+            }
+
+            if self.over_limit {
+                ui.colored_label(egui::Color32::RED, "⛔")
+                    .on_hover_text("The planned draw is above the limit");
+            }
+
+            if removable {
+                removed = ui
+                    .button("🗑")
+                    .on_hover_text("Remove the resource from the outpost")
+                    .clicked();
+            }
+
+            let btn_resp = ui.button("⭕");
+
+            self.window_coordinate.out_flow.push(btn_resp.rect);
+
+            if btn_resp.clicked() {
+                commons.clicked_start_arrow_info = Some((
+                    resource.clone(),
+                    self.id,
+                    ui.layer_id(),
+                    0,
+                    RecipeWindowType::Source,
+                ));
+            }
+        });
+        egui::CollapsingHeader::new("Extraction")
+            .id_source(self.id.with("Extraction"))
+            .show(ui, |ui| {
+                ui.push_id("Extraction", |ui| self.extraction_ui(ui, commons));
+            });
+        (renamed, removed)
+    }
+
+    ///Edit the machines and the deposit of the source, and show what they give at the draw
+    fn extraction_ui(&mut self, ui: &mut egui::Ui, commons: &mut CommonsManager) {
+        let unit = self.output.resource().unit;
+        let mut modelled = self.extraction.is_some();
+        if ui
+            .checkbox(&mut modelled, "Extracted by machines")
+            .changed()
+        {
+            self.extraction = modelled.then(Extraction::default);
+        }
+        let extraction = match self.extraction.as_mut() {
+            None => return,
+            Some(extraction) => extraction,
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Machine:");
+            egui::TextEdit::singleline(&mut extraction.machine)
+                .desired_width(80.0)
+                .ui(ui);
+            egui::DragValue::new(&mut extraction.machine_amount)
+                .clamp_range(0.0..=f32::MAX)
+                .ui(ui);
+            ui.label(unit.symbol());
+            recipe_window::rate_combo(ui, &mut extraction.machine_rate, &commons.time_settings);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Purity:");
+            let preset = PURITIES
+                .iter()
+                .find(|(_, purity)| *purity == extraction.purity)
+                .map_or("Custom", |(name, _)| name);
+            egui::ComboBox::from_id_source("Purity")
+                .selected_text(preset)
+                .show_ui(ui, |ui| {
+                    for (name, purity) in PURITIES {
+                        ui.selectable_value(&mut extraction.purity, purity, name);
+                    }
+                });
+            egui::DragValue::new(&mut extraction.purity)
+                .clamp_range(0.0..=f32::MAX)
+                .speed(0.1)
+                .prefix("×")
+                .ui(ui);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Machines per deposit:");
+            egui::DragValue::new(&mut extraction.machines_per_deposit)
+                .clamp_range(1..=u32::MAX)
+                .ui(ui);
+        });
+        ui.horizontal(|ui| {
+            let mut finite = extraction.deposit.is_some();
+            if ui.checkbox(&mut finite, "Finite deposit").changed() {
+                extraction.deposit = finite.then_some(100_000.0);
+            }
+            if let Some(deposit) = extraction.deposit.as_mut() {
+                egui::DragValue::new(deposit)
+                    .clamp_range(0.0..=f32::MAX)
+                    .ui(ui);
+                ui.label(unit.symbol());
+            }
+        });
+
+        let settings = &commons.time_settings;
+        let machines = extraction.machines(unit, self.drawn, settings);
+        let room = f64::from(extraction.machines_per_deposit);
+        let text = format!(
+            "{} × {} needed, room for {}",
+            formatting::float_format(machines, 3),
+            extraction.machine,
+            extraction.machines_per_deposit
+        );
+        match machines > room * (1.0 + 1e-6) {
+            true => ui.colored_label(egui::Color32::RED, text),
+            false => ui.label(text),
+        };
+        match extraction.depletion_hours(unit, self.drawn) {
+            Some(hours) => ui.label(format!(
+                "Depleted in {}",
+                formatting::duration_format(hours, 3)
+            )),
+            None => ui.label("Never depleted"),
+        };
+        let capacity = extraction.hourly_capacity(unit, settings);
+        if ui
+            .button("Limit to the deposit")
+            .on_hover_text("Limit the source to what the machines of the deposit extract")
+            .clicked()
+        {
+            self.limited_output = true;
+            let rate = self.limit_rate.per_hour(&commons.time_settings);
+            self.limit_amount = (capacity / f64::from(rate * unit.factor())) as f32;
+            commons.recalculate = true;
+        }
+    }
+
+    ///Give the source the resource of a name typed by the user
+    fn rename(&mut self, commons: &mut CommonsManager, name: &str) {
+        let resource = self.output.resource();
+        let new = commons.resources.resolve(name, resource.unit);
+        debug!("Resource of a source changed from {} to {}", resource, new);
+        self.output.set_resource(new);
+        commons.check_arrows = true;
+        commons.recalculate = true;
+    }
+
     pub fn limit_source(&mut self, amount: f32, rate: RatePer) {
         debug!("Limiting source: amount={}{}", amount, rate);
         self.limit_amount = amount;
@@ -147,3 +449,84 @@ impl ResourceSource {
         self.limited_output = true;
     }
 }
+
+/// Show the sources of a group in one outpost window. Closing the window removes all of them.
+///
+/// # Arguments
+///
+/// * `sources`: every source of the graph
+/// * `group`: id of the outpost window
+/// * `commons`: shared state of the windows
+/// * `ctx`: the context to show the window in
+/// * `enabled`: the window can be interacted with
+///
+/// returns: ()
+pub(crate) fn show_group(
+    sources: &mut Vec<ResourceSource>,
+    group: egui::Id,
+    commons: &mut CommonsManager,
+    ctx: &egui::Context,
+    enabled: bool,
+) {
+    let mut open = true;
+    let mut renamed = vec![];
+    let mut removed = vec![];
+    let mut added = false;
+
+    let response = egui::Window::new("Outpost")
+        .id(group)
+        .enabled(enabled)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            for source in sources
+                .iter_mut()
+                .filter(|source| source.group == Some(group))
+            {
+                source.window_coordinate.in_flow.clear();
+                source.window_coordinate.out_flow.clear();
+                ui.push_id(source.id, |ui| {
+                    let (name, remove) = source.row(ui, commons, true);
+                    if let Some(name) = name {
+                        renamed.push((source.id, name));
+                    }
+                    if remove {
+                        removed.push(source.id);
+                    }
+                });
+            }
+            if ui.button("➕").on_hover_text("Add a resource").clicked() {
+                added = true;
+            }
+        });
+
+    let rect = response.map(|response| response.response.rect);
+    for (id, name) in renamed {
+        if let Some(source) = sources.iter_mut().find(|source| source.id == id) {
+            source.rename(commons, &name);
+        }
+    }
+    sources.retain(|source| {
+        let kept = source.group != Some(group) || (open && !removed.contains(&source.id));
+        if !kept {
+            commons.window_coordinates.remove(&source.id);
+        }
+        kept
+    });
+    for source in sources
+        .iter_mut()
+        .filter(|source| source.group == Some(group))
+    {
+        if let Some(rect) = rect {
+            source.window_coordinate.window = rect;
+        }
+        commons
+            .window_coordinates
+            .insert(source.id, source.window_coordinate.clone());
+    }
+    if added {
+        sources.push(ResourceSource::in_group(group));
+    }
+    if !removed.is_empty() || !open {
+        commons.recalculate = true;
+    }
+}
@@ -4,6 +4,7 @@ use crate::app::recipe_window::base_recipe_window::{
     BaseRecipeWindow, ConfigFeatures, RecipeWindowUser,
 };
 use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
+use crate::app::resources::time_settings::TimeSettings;
 
 use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
 use crate::app::resources::ManageFlow;
@@ -41,7 +42,7 @@ impl RecipeWindowGUI for SimpleRecipeWindow {
         let mut open = true;
         self.inner_recipe.clean_coordinates();
 
-        let title = self.inner_recipe.gen_title_string();
+        let title = self.inner_recipe.gen_title_string(&commons.time_settings);
         let response = self
             .inner_recipe
             .window(commons, ctx, enabled, &mut open, title);
@@ -61,18 +62,14 @@ impl RecipeWindowGUI for SimpleRecipeWindow {
         open
     }
 
-    fn generate_tooltip(&self) -> Result<String, Error> {
-        self.inner_recipe.generate_tooltip()
+    fn generate_tooltip(&self, settings: &TimeSettings) -> Result<String, Error> {
+        self.inner_recipe.generate_tooltip(settings)
     }
 }
 
 impl RecipeWindowUser<'static> for SimpleRecipeWindow {
     type Gen = Self;
 
-    fn recipe(self) -> BaseRecipeWindow {
-        self.inner_recipe
-    }
-
     fn push_errors(&mut self, e: ShowError) {
         self.inner_recipe.errors.push(e);
     }
@@ -81,24 +78,26 @@ impl RecipeWindowUser<'static> for SimpleRecipeWindow {
         self.inner_recipe.gen_ids();
     }
 
-    fn internal_calculation(&mut self) {
+    fn internal_calculation(&mut self, settings: &TimeSettings) {
         trace!("[START] internal calculation for simple recipe window");
         let mut min_rate = 1.0f32;
         for input in self.inner_recipe.inputs.iter() {
             match input {
                 ManageFlow::RecipeInput(input) => {
                     debug!("Resource: {}", input.resource().name);
+                    let total_in = input.total_in(settings);
+                    let total_out = input.total_out(settings);
                     debug!(
                         "inputs! {}{}",
-                        input.total_in().amount,
-                        input.total_in().rate.to_shortened_string()
+                        total_in.amount,
+                        total_in.rate.to_shortened_string(settings)
                     );
                     debug!(
                         "outputs! {}{}",
-                        input.total_out().amount,
-                        input.total_out().rate.to_shortened_string()
+                        total_out.amount,
+                        total_out.rate.to_shortened_string(settings)
                     );
-                    let rate = (input.total_in().amount / input.total_out().amount).min(1.0);
+                    let rate = (total_in.amount / total_out.amount).min(1.0);
                     min_rate = min_rate.min(rate);
                 }
                 ManageFlow::RecipeOutput(_) => {
@@ -109,7 +108,7 @@ impl RecipeWindowUser<'static> for SimpleRecipeWindow {
             }
         }
         trace!("[END] internal calculation for simple recipe window");
-        let res = self.inner_recipe.update_flow(Io::Output);
+        let res = self.inner_recipe.update_flow(Io::Output, settings);
         if let Err(e) = res {
             self.push_errors(ShowError::new(e.str()))
         }
@@ -133,7 +132,8 @@ impl RecipeWindowUser<'static> for SimpleRecipeWindow {
     fn back_propagation_internal_calculation(
         &mut self,
         rate: f32,
-        _amount: Option<ResourceFlow<usize, f32>>,
+        _amount: Option<ResourceFlow<f32, f32>>,
+        _settings: &TimeSettings,
     ) {
         trace!("[START] back propagation internal calculation for simple recipe window");
         for input in self.inner_recipe.inputs.iter_mut() {
@@ -167,6 +167,7 @@ pub mod tests {
     };
     use crate::app::recipe_window::{RecipeWindowGUI, RecipeWindowType};
     use crate::app::resources::test::setup_resource_a;
+    use crate::app::resources::time_settings::TimeSettings;
     use crate::app::resources::{RatePer, ResourceDefinition, Unit};
     use crate::utils::test_env;
 
@@ -175,7 +176,7 @@ pub mod tests {
     use std::io::Cursor;
 
     impl SimpleRecipeWindow {
-        pub fn new_with_custom_output(flow: ResourceFlow<usize, f32>) -> Self {
+        pub fn new_with_custom_output(flow: ResourceFlow<f32, f32>) -> Self {
             let inner_recipe = BaseRecipeWindow::new_with_custom_output(
                 ConfigFeatures {
                     interactive_input: true,
@@ -209,7 +210,7 @@ pub mod tests {
                     unit: Unit::Piece,
                 },
                 amount: 1.0,
-                amount_per_cycle: 1,
+                amount_per_cycle: 1.0,
                 rate: RatePer::Second,
             }],
 
@@ -217,7 +218,6 @@ pub mod tests {
         }
     }
 
-    #[deprecated]
     pub(crate) fn setup_simple_recipe_one_to_one() -> TestInfo {
         let title = "Test Window One To One";
         let mut w = SimpleRecipeWindow::new(title.to_string());
@@ -231,7 +231,7 @@ pub mod tests {
                     unit: Unit::Piece,
                 },
                 amount: 1.0,
-                amount_per_cycle: 1,
+                amount_per_cycle: 1.0,
                 rate: RatePer::Second,
             }],
 
@@ -244,13 +244,12 @@ pub mod tests {
         }
     }
 
-    #[deprecated]
     pub(crate) fn setup_simple_recipe_one_to_one_b() -> TestInfo {
         let resource_a = setup_resource_a();
         let _title = resource_a.name.clone();
         let mut w = SimpleRecipeWindow::new_with_custom_output(ResourceFlow::new(
             &resource_a,
-            3,
+            3.0,
             3.0,
             RatePer::Second,
         ));
@@ -265,7 +264,7 @@ pub mod tests {
             output_resources: vec![RecipeResourceInfos {
                 def: resource_a,
                 amount: amount as f32,
-                amount_per_cycle: amount,
+                amount_per_cycle: amount as f32,
                 rate,
             }],
 
@@ -321,7 +320,7 @@ pub mod tests {
     fn perform_test_tooltip(window: SimpleRecipeWindow, expected: String) {
         assert_eq!(
             expected,
-            window.generate_tooltip().unwrap(),
+            window.generate_tooltip(&TimeSettings::default()).unwrap(),
             "Tooltip doesn't match",
         );
     }
@@ -1,81 +1,232 @@
-use crate::utils::Number;
+use crate::utils::{gen_id, Number};
 
 use recipe_input_resource::RecipeInputResource;
 use recipe_output_resource::RecipeOutputResource;
 use resource_flow::ManageResourceFlow;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use time_settings::TimeSettings;
 
 pub mod recipe_input_resource;
 pub mod recipe_output_resource;
 pub mod resource_flow;
-
-const TICKS_TO_SECONDS: f32 = 20.0;
-const SECONDS_TO_MINUTES: f32 = 60.0;
-const MINUTES_TO_HOURS: f32 = 60.0;
+pub mod time_settings;
+pub mod transport;
 
 ///unit of a resource
 /// * PIECES normal objects
 /// * LITER volume measurement
 /// * KG weight measurement
-#[allow(dead_code)]
+/// * JOULE energy measurement
+///
+///Every unit is a multiple of the base unit of its dimension, so the flows of a resource in
+///different units can be connected
 #[derive(
-    Debug, PartialEq, Eq, Hash, PartialOrd, Copy, Clone, serde::Deserialize, serde::Serialize,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Copy,
+    Clone,
+    serde::Deserialize,
+    serde::Serialize,
 )]
 pub enum Unit {
+    #[default]
     Piece,
     Liter,
     Kg,
+    Joule,
+    ///A stack of a number of pieces
+    Stack(u32),
+    CubicMeter,
+    ///Minecraft millibucket, a liter
+    Millibucket,
+    ///Minecraft bucket, a thousand millibuckets
+    Bucket,
+    Tonne,
+    Kilojoule,
+    Megajoule,
+}
+
+///What a unit measures, only units of the same dimension can be converted
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Dimension {
+    Count,
+    Volume,
+    Mass,
+    Energy,
+}
+
+impl Dimension {
+    ///Unit every other unit of the dimension is a multiple of
+    pub fn base_unit(self) -> Unit {
+        match self {
+            Dimension::Count => Unit::Piece,
+            Dimension::Volume => Unit::Liter,
+            Dimension::Mass => Unit::Kg,
+            Dimension::Energy => Unit::Joule,
+        }
+    }
 }
-///rate of a flow
+
+///Size of a stack when a stack unit is picked
+pub(crate) const DEFAULT_STACK_SIZE: u32 = 64;
+
+impl Unit {
+    pub fn dimension(self) -> Dimension {
+        match self {
+            Unit::Piece | Unit::Stack(_) => Dimension::Count,
+            Unit::Liter | Unit::CubicMeter | Unit::Millibucket | Unit::Bucket => Dimension::Volume,
+            Unit::Kg | Unit::Tonne => Dimension::Mass,
+            Unit::Joule | Unit::Kilojoule | Unit::Megajoule => Dimension::Energy,
+        }
+    }
+
+    ///Number of base units (piece, liter, kilogram or joule) in one of this unit
+    pub fn factor(self) -> f32 {
+        match self {
+            Unit::Piece | Unit::Liter | Unit::Millibucket | Unit::Kg | Unit::Joule => 1.0,
+            Unit::Stack(size) => size as f32,
+            Unit::CubicMeter | Unit::Bucket | Unit::Tonne | Unit::Kilojoule => 1000.0,
+            Unit::Megajoule => 1_000_000.0,
+        }
+    }
+
+    /// Convert an amount to another unit
+    ///
+    /// # Arguments
+    ///
+    /// * `amount`: amount in this unit
+    /// * `unit`: the asked unit
+    ///
+    /// returns: None if the units don't measure the same thing
+    pub fn convert(self, amount: f32, unit: Unit) -> Option<f32> {
+        if self == unit {
+            return Some(amount);
+        }
+        (self.dimension() == unit.dimension()).then(|| amount * self.factor() / unit.factor())
+    }
+
+    ///Every unit, the stacks with the default size
+    pub fn all() -> [Unit; 11] {
+        [
+            Unit::Piece,
+            Unit::Stack(DEFAULT_STACK_SIZE),
+            Unit::Liter,
+            Unit::Millibucket,
+            Unit::CubicMeter,
+            Unit::Bucket,
+            Unit::Kg,
+            Unit::Tonne,
+            Unit::Joule,
+            Unit::Kilojoule,
+            Unit::Megajoule,
+        ]
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Unit::Piece => "Piece".to_string(),
+            Unit::Stack(size) => format!("Stack of {size}"),
+            Unit::Liter => "Liter".to_string(),
+            Unit::CubicMeter => "Cubic meter".to_string(),
+            Unit::Millibucket => "Millibucket".to_string(),
+            Unit::Bucket => "Bucket".to_string(),
+            Unit::Kg => "Kilogram".to_string(),
+            Unit::Tonne => "Tonne".to_string(),
+            Unit::Joule => "Joule".to_string(),
+            Unit::Kilojoule => "Kilojoule".to_string(),
+            Unit::Megajoule => "Megajoule".to_string(),
+        }
+    }
+
+    ///Symbol written after the amounts
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Piece => "pcs",
+            Unit::Stack(_) => "stacks",
+            Unit::Liter => "L",
+            Unit::CubicMeter => "m³",
+            Unit::Millibucket => "mB",
+            Unit::Bucket => "B",
+            Unit::Kg => "kg",
+            Unit::Tonne => "t",
+            Unit::Joule => "J",
+            Unit::Kilojoule => "kJ",
+            Unit::Megajoule => "MJ",
+        }
+    }
+}
+
+///rate of a flow, the length of a tick and of the custom units are given by the `TimeSettings`
 #[allow(dead_code)]
-#[derive(Debug, PartialEq, PartialOrd, Copy, Clone, serde::Deserialize, serde::Serialize, Eq)]
+#[derive(
+    Debug, PartialEq, PartialOrd, Ord, Copy, Clone, serde::Deserialize, serde::Serialize, Eq,
+)]
 pub enum RatePer {
     Tick,
     Second,
     Minute,
     Hour,
+    ///A time unit of the project, index in its `TimeSettings`
+    Custom(usize),
 }
 
 impl RatePer {
-    pub fn next(self) -> Self {
+    ///The shortest rate longer than this one, None if it's the longest
+    pub fn next(self, settings: &TimeSettings) -> Option<Self> {
+        settings.next(self)
+    }
+
+    ///Every rate of the project, from the shortest to the longest
+    pub fn all(settings: &TimeSettings) -> Vec<Self> {
+        settings.rates()
+    }
+
+    ///Number of periods of this rate in an hour
+    pub fn per_hour(self, settings: &TimeSettings) -> f32 {
+        settings.per_hour(self)
+    }
+
+    ///Name of the time unit
+    pub fn name(self, settings: &TimeSettings) -> String {
+        match self {
+            RatePer::Custom(_) => settings
+                .unit(self)
+                .map(|unit| unit.name.clone())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            _ => self.builtin_name(),
+        }
+    }
+
+    ///Name of the time unit without the project, a custom unit is named by its index
+    fn builtin_name(self) -> String {
         match self {
-            RatePer::Tick => RatePer::Second,
-            RatePer::Second => RatePer::Minute,
-            RatePer::Minute => RatePer::Hour,
-            RatePer::Hour => {
-                panic!("Can't call next on hour")
-            }
+            RatePer::Tick => "Tick".to_string(),
+            RatePer::Second => "Second".to_string(),
+            RatePer::Minute => "Minute".to_string(),
+            RatePer::Hour => "Hour".to_string(),
+            RatePer::Custom(index) => format!("unit {}", index + 1),
         }
     }
 
-    pub fn to_shortened_string(self) -> String {
+    pub fn to_shortened_string(self, settings: &TimeSettings) -> String {
         match self {
-            RatePer::Tick => "/tick",
-            RatePer::Second => "/s",
-            RatePer::Minute => "/min",
-            RatePer::Hour => "/h",
+            RatePer::Tick => "/tick".to_string(),
+            RatePer::Second => "/s".to_string(),
+            RatePer::Minute => "/min".to_string(),
+            RatePer::Hour => "/h".to_string(),
+            RatePer::Custom(_) => format!("/{}", self.name(settings)),
         }
-        .to_string()
     }
 }
 
 impl Display for RatePer {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            RatePer::Tick => {
-                write!(f, "Per Tick")
-            }
-            RatePer::Second => {
-                write!(f, "Per Second")
-            }
-            RatePer::Minute => {
-                write!(f, "Per Minute")
-            }
-            RatePer::Hour => {
-                write!(f, "Per Hour")
-            }
-        }
+        write!(f, "Per {}", self.builtin_name())
     }
 }
 
@@ -89,6 +240,33 @@ pub struct ResourceDefinition {
     pub unit: Unit,
 }
 
+///Stable id of a new input or output of a recipe, the arrows find their flows with it
+pub(crate) fn new_flow_id() -> egui::Id {
+    gen_id("Flow".to_string())
+}
+
+///The resource used by power flows, its amounts are in joules
+pub(crate) fn power_resource() -> ResourceDefinition {
+    ResourceDefinition {
+        name: "Power".to_string(),
+        unit: Unit::Joule,
+    }
+}
+
+impl ResourceDefinition {
+    ///Indicate both definitions are the same resource, maybe counted in different units
+    pub fn is_compatible(&self, other: &ResourceDefinition) -> bool {
+        self.name == other.name && self.unit.dimension() == other.unit.dimension()
+    }
+
+    ///Take the new name if the resource is the renamed one, its unit is kept
+    pub fn rename(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        if self.name == old.name {
+            self.name = new.name.clone();
+        }
+    }
+}
+
 impl Display for ResourceDefinition {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name)?;
@@ -104,25 +282,53 @@ pub(crate) enum ManageFlow<T: Number> {
 
 impl<T: Number> ManageFlow<T> {
     #[allow(dead_code)]
-    pub fn to_string_rep(&self) -> String {
+    pub fn to_string_rep(&self, settings: &TimeSettings) -> String {
         match self {
-            ManageFlow::RecipeInput(input) => input.to_string(),
-            ManageFlow::RecipeOutput(output) => output.to_string(),
+            ManageFlow::RecipeInput(input) => input.to_string(settings),
+            ManageFlow::RecipeOutput(output) => output.to_string(settings),
         }
     }
 
-    pub fn to_split_string(&self) -> [String; 3] {
+    pub fn to_split_string(&self, settings: &TimeSettings) -> [String; 3] {
         match self {
-            ManageFlow::RecipeInput(input) => input.to_split_string(),
-            ManageFlow::RecipeOutput(output) => output.to_split_string(),
+            ManageFlow::RecipeInput(input) => input.to_split_string(settings),
+            ManageFlow::RecipeOutput(output) => output.to_split_string(settings),
+        }
+    }
+
+    pub fn resource(&self) -> ResourceDefinition {
+        match self {
+            ManageFlow::RecipeInput(input) => input.resource(),
+            ManageFlow::RecipeOutput(output) => output.resource(),
+        }
+    }
+
+    pub fn id(&self) -> egui::Id {
+        match self {
+            ManageFlow::RecipeInput(input) => input.id,
+            ManageFlow::RecipeOutput(output) => output.id,
+        }
+    }
+
+    pub fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        match self {
+            ManageFlow::RecipeInput(input) => input.rename_resource(old, new),
+            ManageFlow::RecipeOutput(output) => output.rename_resource(old, new),
+        }
+    }
+
+    pub fn rates(&self) -> Vec<RatePer> {
+        match self {
+            ManageFlow::RecipeInput(input) => input.rates(),
+            ManageFlow::RecipeOutput(output) => output.rates(),
         }
     }
 }
 
 #[derive(Copy, Clone, Debug)]
 pub enum FlowErrorType {
-    RateTooLowConversion(RatePer, RatePer),
     WrongResourceType,
+    IncompatibleUnit,
 }
 
 pub struct FlowError {
@@ -136,10 +342,8 @@ impl FlowError {
 
     pub(crate) fn str(&self) -> String {
         match self.error_type {
-            FlowErrorType::RateTooLowConversion(start, end) => {
-                format!("Rate type too low for rate conversion: from {start} to {end}")
-            }
             FlowErrorType::WrongResourceType => "Resource Flow of wrong type".to_string(),
+            FlowErrorType::IncompatibleUnit => "Units measuring different things".to_string(),
         }
     }
 }
@@ -161,7 +365,8 @@ impl Error for FlowError {}
 #[cfg(test)]
 pub mod test {
 
-    use crate::app::resources::{ResourceDefinition, Unit};
+    use crate::app::resources::{Dimension, ResourceDefinition, Unit};
+    use crate::utils::test_env;
 
     pub(crate) fn setup_resource_a() -> ResourceDefinition {
         ResourceDefinition {
@@ -185,4 +390,25 @@ pub mod test {
     }
 
     //-------------------Tests-------------------
+
+    #[test]
+    fn test_unit_conversion() {
+        test_env::setup();
+        assert_eq!(Unit::Bucket.convert(2.0, Unit::Millibucket), Some(2000.0));
+        assert_eq!(Unit::Liter.convert(500.0, Unit::CubicMeter), Some(0.5));
+        assert_eq!(Unit::Stack(64).convert(2.0, Unit::Piece), Some(128.0));
+        assert_eq!(Unit::Stack(16).convert(2.0, Unit::Stack(64)), Some(0.5));
+        assert_eq!(Unit::Kg.convert(1.0, Unit::Liter), None);
+        assert_eq!(Unit::Megajoule.dimension(), Dimension::Energy);
+
+        let water = ResourceDefinition {
+            name: "Water".to_string(),
+            unit: Unit::Bucket,
+        };
+        let mut in_liters = water.clone();
+        in_liters.unit = Unit::Liter;
+        assert!(water.is_compatible(&in_liters));
+        in_liters.unit = Unit::Kg;
+        assert!(!water.is_compatible(&in_liters));
+    }
 }
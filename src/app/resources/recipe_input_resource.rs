@@ -1,12 +1,19 @@
+use crate::app::resources::recipe_output_resource::RecipeOutputResource;
 use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
-use crate::app::resources::ResourceDefinition;
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{new_flow_id, RatePer, ResourceDefinition};
 use log::debug;
+use std::cmp::Ordering;
 
 use crate::utils::{formatting, Number};
 
 ///an input resource for a recipe
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
 pub(crate) struct RecipeInputResource<T: Number> {
+    ///stable id of the flow in its recipe, saves without it get a new one
+    #[serde(default = "new_flow_id")]
+    pub(crate) id: egui::Id,
+
     ///the type of resource this considers
     resource: ResourceDefinition,
 
@@ -22,6 +29,7 @@ impl<T: Number> Eq for RecipeInputResource<T> {}
 impl<T: Number> RecipeInputResource<T> {
     pub(crate) fn new(resource: ResourceDefinition, needed: ResourceFlow<T, f32>) -> Self {
         Self {
+            id: new_flow_id(),
             resource,
             inputs: vec![],
             needed,
@@ -29,23 +37,26 @@ impl<T: Number> RecipeInputResource<T> {
     }
 }
 
+///An output made an input keeps its id and its designed flow, the flows it sent are dropped
+impl<T: Number> From<RecipeOutputResource<T>> for RecipeInputResource<T> {
+    fn from(output: RecipeOutputResource<T>) -> Self {
+        Self {
+            id: output.id,
+            resource: output.resource(),
+            inputs: vec![],
+            needed: output.created,
+        }
+    }
+}
+
 impl<T: Number> ManageResourceFlow<T> for RecipeInputResource<T> {
     fn add_in_flow(&mut self, flow: ResourceFlow<T, f32>) -> bool {
-        if flow.resource != self.resource {
+        if !flow.resource.is_compatible(&self.resource) {
             return false;
         }
         debug!("Add flow to an input {}", flow);
-        debug!(
-            "inputs! {}{}",
-            self.total_in().amount,
-            self.total_in().rate.to_shortened_string()
-        );
         self.inputs.push(flow);
-        debug!(
-            "inputs! {}{}",
-            self.total_in().amount,
-            self.total_in().rate.to_shortened_string()
-        );
+        debug!("{} flows in the input", self.inputs.len());
         true
     }
 
@@ -53,36 +64,34 @@ impl<T: Number> ManageResourceFlow<T> for RecipeInputResource<T> {
         false
     }
 
-    fn total_in(&self) -> ResourceFlow<T, f32> {
+    fn total_in(&self, settings: &TimeSettings) -> ResourceFlow<T, f32> {
         let rate = self.needed.rate;
         let definition = &self.resource;
         let mut flow = ResourceFlow::empty(definition, rate);
         for input in self.inputs.iter() {
-            flow.add(input);
+            flow.add(input, settings);
         }
 
         flow
     }
 
-    fn total_out(&self) -> ResourceFlow<T, f32> {
+    fn total_out(&self, _settings: &TimeSettings) -> ResourceFlow<T, f32> {
         self.needed.clone()
     }
 
-    fn is_enough(&self) -> bool {
-        self.total_in() >= self.needed
+    fn is_enough(&self, settings: &TimeSettings) -> bool {
+        self.total_in(settings)
+            .compare(&self.needed, settings)
+            .map_or(false, |order| order != Ordering::Less)
     }
 
-    fn is_more_than_enough(&self) -> bool {
-        let total_in = self.total_in();
+    fn is_more_than_enough(&self, settings: &TimeSettings) -> bool {
+        let total_in = self.total_in(settings);
         debug!(
             "Is more than enough total_in={}{} needed={}{}",
             total_in.amount, total_in.rate, self.needed.amount, self.needed.rate
         );
-        total_in > self.needed
-    }
-
-    fn is_connected(&self) -> bool {
-        !self.inputs.is_empty()
+        total_in.compare(&self.needed, settings) == Some(Ordering::Greater)
     }
 
     fn resource(&self) -> ResourceDefinition {
@@ -93,19 +102,42 @@ impl<T: Number> ManageResourceFlow<T> for RecipeInputResource<T> {
         self.needed.amount_per_cycle = amount;
     }
 
-    fn to_string(&self) -> String {
-        let strings = self.to_split_string();
+    fn set_resource(&mut self, resource: ResourceDefinition) {
+        self.needed.resource = resource.clone();
+        self.resource = resource;
+        self.inputs.clear();
+    }
+
+    fn to_string(&self, settings: &TimeSettings) -> String {
+        let strings = self.to_split_string(settings);
         format!("{}: {}||{}", strings[0], strings[1], strings[2])
     }
 
-    fn to_split_string(&self) -> [String; 3] {
+    fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        self.resource.rename(old, new);
+        self.needed.rename_resource(old, new);
+        for input in self.inputs.iter_mut() {
+            input.rename_resource(old, new);
+        }
+    }
+
+    fn rates(&self) -> Vec<RatePer> {
+        std::iter::once(&self.needed)
+            .chain(self.inputs.iter())
+            .map(|flow| flow.rate)
+            .collect()
+    }
+
+    fn to_split_string(&self, settings: &TimeSettings) -> [String; 3] {
+        let needed = self.needed.displayed(settings);
         [
             self.resource.name.clone(),
-            format!("{}", self.needed.amount_per_cycle),
+            format!("{}", needed.amount_per_cycle),
             format!(
-                "{}{}",
-                formatting::float_format(self.needed.amount, 3),
-                self.needed.rate.to_shortened_string()
+                "{} {}{}",
+                formatting::float_format(needed.amount, 3),
+                needed.resource.unit.symbol(),
+                needed.rate.to_shortened_string(settings)
             ),
         ]
     }
@@ -1,11 +1,18 @@
+use crate::app::resources::recipe_input_resource::RecipeInputResource;
 use crate::app::resources::resource_flow::{ManageResourceFlow, ResourceFlow};
-use crate::app::resources::ResourceDefinition;
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{new_flow_id, RatePer, ResourceDefinition};
 
 use crate::utils::{formatting, Number};
+use std::cmp::Ordering;
 
 ///an input resource for a recipe
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
 pub(crate) struct RecipeOutputResource<T: Number> {
+    ///stable id of the flow in its recipe, saves without it get a new one
+    #[serde(default = "new_flow_id")]
+    pub(crate) id: egui::Id,
+
     ///the type of resource this considers
     resource: ResourceDefinition,
 
@@ -14,14 +21,64 @@ pub(crate) struct RecipeOutputResource<T: Number> {
 
     ///amount created per recipe cycle
     pub(crate) created: ResourceFlow<T, f32>,
+
+    ///chance of the amount to be created by a cycle, from 0 to 1
+    #[serde(default = "default_probability")]
+    pub(crate) probability: f32,
+}
+
+fn default_probability() -> f32 {
+    1.0
 }
 
+///Part of the created amount under which an excess is considered a rounding error
+const EXCESS_TOLERANCE: f32 = 1e-4;
+
 impl<T: Number> RecipeOutputResource<T> {
     pub(crate) fn new(resource: ResourceDefinition, created: ResourceFlow<T, f32>) -> Self {
         Self {
+            id: new_flow_id(),
             resource,
             outputs: vec![],
             created,
+            probability: default_probability(),
+        }
+    }
+
+    ///Part of the created flow that no output flow takes
+    pub(crate) fn excess(&self, settings: &TimeSettings) -> ResourceFlow<T, f32> {
+        let mut excess = self.created.clone();
+        let taken = self.total_out(settings);
+        if taken.rate.per_hour(settings) < excess.rate.per_hour(settings) {
+            let _ = excess.convert(taken.rate, settings);
+        }
+        let taken = taken
+            .convert_amount(excess.rate, settings)
+            .unwrap_or(taken.amount);
+        excess.amount = (excess.amount - taken).max(0.0);
+        excess
+    }
+
+    ///Indicate some of the created flow has nowhere to go
+    pub(crate) fn has_excess(&self, settings: &TimeSettings) -> bool {
+        let excess = self.excess(settings);
+        let created = self
+            .created
+            .convert_amount(excess.rate, settings)
+            .unwrap_or(self.created.amount);
+        excess.amount > created * EXCESS_TOLERANCE
+    }
+}
+
+///An input made an output keeps its id and its designed flow, the flows it received are dropped
+impl<T: Number> From<RecipeInputResource<T>> for RecipeOutputResource<T> {
+    fn from(input: RecipeInputResource<T>) -> Self {
+        Self {
+            id: input.id,
+            resource: input.resource(),
+            outputs: vec![],
+            created: input.needed,
+            probability: default_probability(),
         }
     }
 }
@@ -32,38 +89,36 @@ impl<T: Number> ManageResourceFlow<T> for RecipeOutputResource<T> {
     }
 
     fn add_out_flow(&mut self, flow: ResourceFlow<T, f32>) -> bool {
-        if flow.resource != self.resource {
+        if !flow.resource.is_compatible(&self.resource) {
             return false;
         }
         self.outputs.push(flow);
         true
     }
 
-    fn total_in(&self) -> ResourceFlow<T, f32> {
+    fn total_in(&self, _settings: &TimeSettings) -> ResourceFlow<T, f32> {
         self.created.clone()
     }
 
-    fn total_out(&self) -> ResourceFlow<T, f32> {
+    fn total_out(&self, settings: &TimeSettings) -> ResourceFlow<T, f32> {
         let rate = self.created.rate;
         let definition = &self.resource;
         let mut flow = ResourceFlow::empty(definition, rate);
         for output in self.outputs.iter() {
-            flow.add(output);
+            flow.add(output, settings);
         }
 
         flow
     }
 
-    fn is_enough(&self) -> bool {
-        self.total_out() <= self.created
-    }
-
-    fn is_more_than_enough(&self) -> bool {
-        self.total_out() < self.created
+    fn is_enough(&self, settings: &TimeSettings) -> bool {
+        self.total_out(settings)
+            .compare(&self.created, settings)
+            .map_or(false, |order| order != Ordering::Greater)
     }
 
-    fn is_connected(&self) -> bool {
-        !self.outputs.is_empty()
+    fn is_more_than_enough(&self, settings: &TimeSettings) -> bool {
+        self.total_out(settings).compare(&self.created, settings) == Some(Ordering::Less)
     }
 
     fn resource(&self) -> ResourceDefinition {
@@ -74,19 +129,49 @@ impl<T: Number> ManageResourceFlow<T> for RecipeOutputResource<T> {
         self.created.amount_per_cycle = amount;
     }
 
-    fn to_string(&self) -> String {
-        let strings = self.to_split_string();
+    fn set_resource(&mut self, resource: ResourceDefinition) {
+        self.created.resource = resource.clone();
+        self.resource = resource;
+        self.outputs.clear();
+    }
+
+    fn to_string(&self, settings: &TimeSettings) -> String {
+        let strings = self.to_split_string(settings);
         format!("{}: {}||{}", strings[0], strings[1], strings[2])
     }
 
-    fn to_split_string(&self) -> [String; 3] {
+    fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        self.resource.rename(old, new);
+        self.created.rename_resource(old, new);
+        for output in self.outputs.iter_mut() {
+            output.rename_resource(old, new);
+        }
+    }
+
+    fn rates(&self) -> Vec<RatePer> {
+        std::iter::once(&self.created)
+            .chain(self.outputs.iter())
+            .map(|flow| flow.rate)
+            .collect()
+    }
+
+    fn to_split_string(&self, settings: &TimeSettings) -> [String; 3] {
+        let chance = match self.probability < 1.0 {
+            true => format!(
+                " ({}%)",
+                formatting::float_format(self.probability * 100.0, 3)
+            ),
+            false => "".to_string(),
+        };
+        let created = self.created.displayed(settings);
         [
             self.resource.name.clone(),
-            format!("{}", self.created.amount_per_cycle),
+            format!("{}{chance}", created.amount_per_cycle),
             format!(
-                "{}{}",
-                formatting::float_format(self.created.amount, 3),
-                self.created.rate.to_shortened_string()
+                "{} {}{}",
+                formatting::float_format(created.amount, 3),
+                created.resource.unit.symbol(),
+                created.rate.to_shortened_string(settings)
             ),
         ]
     }
@@ -1,13 +1,11 @@
-use crate::app::resources::{
-    FlowError, FlowErrorType, RatePer, ResourceDefinition, MINUTES_TO_HOURS, SECONDS_TO_MINUTES,
-    TICKS_TO_SECONDS,
-};
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{FlowError, FlowErrorType, RatePer, ResourceDefinition, Unit};
 use crate::utils::{FloatingNumber, Number};
 
 use num_traits::NumCast;
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
-use std::ops::{Div, Mul};
+use std::ops::Mul;
 
 ///A flow of resource
 #[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
@@ -18,31 +16,15 @@ pub struct ResourceFlow<T: Number, F: FloatingNumber> {
     pub rate: RatePer,
 }
 
-impl<T: Number, F: FloatingNumber> PartialOrd for ResourceFlow<T, F> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.resource != other.resource {
-            return None;
-        }
-
-        if self.rate > other.rate {
-            let amount = other.convert_amount(self.rate).unwrap();
-            self.amount.partial_cmp(&amount)
-        } else {
-            self.convert_amount(other.rate)
-                .unwrap()
-                .partial_cmp(&other.amount)
-        }
-    }
-}
-
 impl<T: Number, F: FloatingNumber> Display for ResourceFlow<T, F> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "\t{}: {}{}",
+            "\t{}: {} {} {}",
             self.resource,
             self.amount,
-            self.rate.to_shortened_string()
+            self.resource.unit.symbol(),
+            self.rate
         )?;
         Ok(())
     }
@@ -67,59 +49,109 @@ impl<T: Number, F: FloatingNumber> ResourceFlow<T, F> {
         Self::new(resource, T::zero(), F::zero(), rate)
     }
 
-    /// Return the amount that flow has for a different longer term rate, return an error if the
-    /// rate asked is lower than the current, to prevent int division
+    /// Return the amount that flow has for a different rate
     ///
     /// # Arguments
     ///
     /// * `rate`: the asked rate
+    /// * `settings`: the time settings of the project
     ///
     /// returns: Result<T, FlowError>
-    pub fn convert_amount(&self, rate: RatePer) -> Result<F, FlowError> {
-        match rate {
-            RatePer::Tick => match self.rate {
-                RatePer::Tick => Ok(self.amount),
-                _ => Err(FlowError::new(FlowErrorType::RateTooLowConversion(
-                    self.rate, rate,
-                ))),
-            },
-            RatePer::Second => match self.rate {
-                RatePer::Tick => Ok(self.amount * TICKS_TO_SECONDS.into()),
-                RatePer::Second => Ok(self.amount),
-                _ => Err(FlowError::new(FlowErrorType::RateTooLowConversion(
-                    self.rate, rate,
-                ))),
-            },
-            RatePer::Minute => match self.rate {
-                RatePer::Tick => Ok(self.amount * (TICKS_TO_SECONDS * SECONDS_TO_MINUTES).into()),
-                RatePer::Second => Ok(self.amount * SECONDS_TO_MINUTES.into()),
-                RatePer::Minute => Ok(self.amount),
-                _ => Err(FlowError::new(FlowErrorType::RateTooLowConversion(
-                    self.rate, rate,
-                ))),
-            },
-            RatePer::Hour => match self.rate {
-                RatePer::Tick => {
-                    Ok(self.amount
-                        * (TICKS_TO_SECONDS * SECONDS_TO_MINUTES * MINUTES_TO_HOURS).into())
-                }
-                RatePer::Second => Ok(self.amount * (SECONDS_TO_MINUTES * MINUTES_TO_HOURS).into()),
-                RatePer::Minute => Ok(self.amount * MINUTES_TO_HOURS.into()),
-                RatePer::Hour => Ok(self.amount),
-            },
+    pub fn convert_amount(&self, rate: RatePer, settings: &TimeSettings) -> Result<F, FlowError> {
+        if rate == self.rate {
+            return Ok(self.amount);
+        }
+        Ok(self.amount * (self.rate.per_hour(settings) / rate.per_hour(settings)).into())
+    }
+
+    /// Return the amount that flow has for a different unit and rate
+    ///
+    /// # Arguments
+    ///
+    /// * `unit`: the asked unit, of the same dimension as the unit of the flow
+    /// * `rate`: the asked rate
+    /// * `settings`: the time settings of the project
+    ///
+    /// returns: Result<F, FlowError>
+    pub fn amount_in(
+        &self,
+        unit: Unit,
+        rate: RatePer,
+        settings: &TimeSettings,
+    ) -> Result<F, FlowError> {
+        let amount = self.convert_amount(rate, settings)?;
+        let factor = self
+            .resource
+            .unit
+            .convert(1.0, unit)
+            .ok_or_else(|| FlowError::new(FlowErrorType::IncompatibleUnit))?;
+        Ok(amount * factor.into())
+    }
+
+    ///Replace the resource of the flow if it's the old one
+    pub fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition) {
+        self.resource.rename(old, new);
+    }
+
+    ///The flow in the display rate of the project
+    pub fn displayed(&self, settings: &TimeSettings) -> Self {
+        let mut flow = self.clone();
+        if let Some(rate) = settings.display_rate {
+            let _ = flow.convert(rate, settings);
+        }
+        flow
+    }
+
+    /// Compare two flows of compatible resources
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: the compared flow
+    /// * `settings`: the time settings of the project
+    ///
+    /// returns: the ordering, None if the resources aren't compatible
+    pub fn compare(&self, other: &Self, settings: &TimeSettings) -> Option<Ordering> {
+        if !self.resource.is_compatible(&other.resource) {
+            return None;
+        }
+        if self.rate.per_hour(settings) < other.rate.per_hour(settings) {
+            let amount = other
+                .amount_in(self.resource.unit, self.rate, settings)
+                .ok()?;
+            self.amount.partial_cmp(&amount)
+        } else {
+            self.amount_in(other.resource.unit, other.rate, settings)
+                .ok()?
+                .partial_cmp(&other.amount)
+        }
+    }
+
+    /// Ratio of two flows of compatible resources
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: the dividing flow
+    /// * `settings`: the time settings of the project
+    ///
+    /// returns: Result<F, FlowError>
+    pub fn ratio(&self, other: &Self, settings: &TimeSettings) -> Result<F, FlowError> {
+        if !self.resource.is_compatible(&other.resource) {
+            return Err(FlowError::new(FlowErrorType::WrongResourceType));
         }
+        let amount = other.amount_in(self.resource.unit, self.rate, settings)?;
+        Ok(self.amount / amount)
     }
 
-    /// Convert a flow flow has for a different longer term rate, return an error if the
-    /// rate asked is lower than the current, to prevent int division
+    /// Convert a flow to a different rate
     ///
     /// # Arguments
     ///
-    /// * `rate`:
+    /// * `rate`: the new rate
+    /// * `settings`: the time settings of the project
     ///
     /// returns: Result<_, FlowError>
-    pub fn convert(&mut self, rate: RatePer) -> Result<(), FlowError> {
-        match self.convert_amount(rate) {
+    pub fn convert(&mut self, rate: RatePer, settings: &TimeSettings) -> Result<(), FlowError> {
+        match self.convert_amount(rate, settings) {
             Ok(amount) => {
                 self.rate = rate;
                 self.amount = amount;
@@ -134,33 +166,41 @@ impl<T: Number, F: FloatingNumber> ResourceFlow<T, F> {
     /// # Arguments
     ///
     /// * `other`: the other flow to add.
+    /// * `settings`: the time settings of the project
     ///
     /// returns: ()
-    pub fn add(&mut self, other: &Self) {
-        if self.resource != other.resource {
+    pub fn add(&mut self, other: &Self, settings: &TimeSettings) {
+        if !self.resource.is_compatible(&other.resource) {
             // doesn't add if the resource doesn't match
             return;
         }
 
-        if self.rate == other.rate {
-            self.amount += other.amount;
-        } else if self.rate > other.rate {
-            self.amount += other.convert_amount(self.rate).unwrap();
-        } else {
-            let _ = self.convert(other.rate);
-            self.amount += other.amount;
+        //the sum is kept in the longer period of both flows
+        if self.rate.per_hour(settings) > other.rate.per_hour(settings) {
+            let _ = self.convert(other.rate, settings);
+        }
+        if let Ok(amount) = other.amount_in(self.resource.unit, self.rate, settings) {
+            self.amount += amount;
         }
     }
 
     ///Convert new time base
-    pub fn convert_time_base(&mut self, cycle_length: T, time: RatePer) -> Result<(), FlowError> {
+    pub fn convert_time_base(
+        &mut self,
+        cycle_length: T,
+        time: RatePer,
+        settings: &TimeSettings,
+    ) -> Result<(), FlowError> {
         let amount_per_cycle: F = NumCast::from(self.amount_per_cycle).unwrap();
         let new_amount: F = amount_per_cycle / NumCast::from(cycle_length).unwrap();
         self.rate = time;
         self.amount = new_amount;
 
-        while self.amount < F::one() && self.rate < RatePer::Hour {
-            self.convert(self.rate.next())?;
+        while self.amount < F::one() {
+            match self.rate.next(settings) {
+                Some(next) => self.convert(next, settings)?,
+                None => break,
+            }
         }
         Ok(())
     }
@@ -180,31 +220,6 @@ where
     }
 }
 
-impl<T, F> Div for ResourceFlow<T, F>
-where
-    T: Number,
-    F: FloatingNumber,
-{
-    type Output = Result<F, FlowError>;
-
-    fn div(self, rhs: Self) -> Self::Output {
-        if self.resource != rhs.resource {
-            // doesn't add if the resource doesn't match
-            return Err(FlowError::new(FlowErrorType::WrongResourceType));
-        }
-
-        if self.rate == rhs.rate {
-            Ok(self.amount / rhs.amount)
-        } else if self.rate > rhs.rate {
-            let amount = rhs.convert_amount(self.rate)?;
-            Ok(self.amount / amount)
-        } else {
-            let amount = self.convert_amount(rhs.rate)?;
-            Ok(amount / rhs.amount)
-        }
-    }
-}
-
 ///generic trait for any structure that manage a resource flow
 pub(crate) trait ManageResourceFlow<T: Number> {
     /// Add a amount to flow inside the resource container
@@ -225,31 +240,52 @@ pub(crate) trait ManageResourceFlow<T: Number> {
     /// returns: `bool` flag indicating if the flow has been added correctly
     fn add_out_flow(&mut self, flow: ResourceFlow<T, f32>) -> bool;
 
-    ///return the total flow in
-    fn total_in(&self) -> ResourceFlow<T, f32>;
+    ///return the total flow in, the flows are converted with the time settings of the project
+    fn total_in(&self, settings: &TimeSettings) -> ResourceFlow<T, f32>;
 
-    /// return the total flow out
-    fn total_out(&self) -> ResourceFlow<T, f32>;
+    /// return the total flow out, the flows are converted with the time settings of the project
+    fn total_out(&self, settings: &TimeSettings) -> ResourceFlow<T, f32>;
 
     ///indicate the flow is enough
-    fn is_enough(&self) -> bool;
+    fn is_enough(&self, settings: &TimeSettings) -> bool;
 
     /// indicate the flow is more than enough
-    fn is_more_than_enough(&self) -> bool;
+    fn is_more_than_enough(&self, settings: &TimeSettings) -> bool;
 
-    ///indicate the flow is connected to the outside
-    fn is_connected(&self) -> bool;
     ///the ``ResourceDefinition`` representing the flow
     fn resource(&self) -> ResourceDefinition;
 
     fn set_designed_amount_per_cycle(&mut self, amount: T);
 
+    /// Change the resource of the container, the connected flows are dropped until the next
+    /// calculation
+    ///
+    /// # Arguments
+    ///
+    /// * `resource`: the new resource
+    ///
+    /// returns: ()
+    fn set_resource(&mut self, resource: ResourceDefinition);
+
+    /// Replace a resource by another in every flow, when a resource is renamed
+    ///
+    /// # Arguments
+    ///
+    /// * `old`: the resource replaced
+    /// * `new`: the replacing resource
+    ///
+    /// returns: ()
+    fn rename_resource(&mut self, old: &ResourceDefinition, new: &ResourceDefinition);
+
+    ///Rates of the designed flow and of the connected flows
+    fn rates(&self) -> Vec<RatePer>;
+
     ///Give a string representation
-    fn to_string(&self) -> String;
+    fn to_string(&self, settings: &TimeSettings) -> String;
 
     ///Give a string representation split in three strings for custom formatting first the name,
-    ///then the amount per cycle then the amount of per time, with unit
-    fn to_split_string(&self) -> [String; 3];
+    ///then the amount per cycle then the amount of per time, with unit in the display rate
+    fn to_split_string(&self, settings: &TimeSettings) -> [String; 3];
 
     ///reset the flows
     fn reset(&mut self);
@@ -259,20 +295,23 @@ pub(crate) trait ManageResourceFlow<T: Number> {
 pub mod test {
     use crate::app::resources::resource_flow::ResourceFlow;
     use crate::app::resources::test::{setup_resource_a, setup_resource_b};
-    use crate::app::resources::{RatePer, ResourceDefinition};
+    use crate::app::resources::time_settings::{TimeSettings, TimeUnit};
+    use crate::app::resources::{RatePer, ResourceDefinition, Unit};
+    use crate::utils::test_env;
+    use std::cmp::Ordering;
 
     pub(crate) struct TestInfo {
-        pub flow: ResourceFlow<usize, f32>,
+        pub flow: ResourceFlow<f32, f32>,
         pub resource: ResourceDefinition,
-        pub amount_per_cycle: usize,
+        pub amount_per_cycle: f32,
         pub amount: f32,
         pub rate: RatePer,
     }
 
     pub(crate) fn setup_flow_resource_a(amount: Option<usize>) -> TestInfo {
         let resource = setup_resource_a();
-        let amount_per_cycle = amount.unwrap_or(2);
-        let amount = amount_per_cycle as f32;
+        let amount_per_cycle = amount.unwrap_or(2) as f32;
+        let amount = amount_per_cycle;
         let rate = RatePer::Minute;
 
         TestInfo {
@@ -286,9 +325,9 @@ pub mod test {
 
     pub(crate) fn setup_flow_resource_b(amount: Option<usize>, rate: Option<RatePer>) -> TestInfo {
         let resource = setup_resource_b();
-        let amount_per_cycle = amount.unwrap_or(1);
+        let amount_per_cycle = amount.unwrap_or(1) as f32;
         let rate = rate.unwrap_or(RatePer::Minute);
-        let amount = amount_per_cycle as f32;
+        let amount = amount_per_cycle;
 
         TestInfo {
             flow: ResourceFlow::new(&resource, amount_per_cycle, amount, rate),
@@ -304,8 +343,8 @@ pub mod test {
         amount: usize,
         rate: RatePer,
     ) -> TestInfo {
-        let amount_per_cycle = amount;
-        let amount = amount_per_cycle as f32;
+        let amount_per_cycle = amount as f32;
+        let amount = amount_per_cycle;
 
         TestInfo {
             flow: ResourceFlow::new(&resource, amount_per_cycle, amount, rate),
@@ -317,4 +356,86 @@ pub mod test {
     }
 
     //-------------------Tests-------------------
+
+    #[test]
+    fn test_convert_both_ways() {
+        test_env::setup();
+        let settings = TimeSettings::default();
+        let info = setup_flow_resource(setup_resource_a(), 90, RatePer::Minute);
+        let mut flow = info.flow;
+
+        let per_second = flow.convert_amount(RatePer::Second, &settings).unwrap();
+        assert!((per_second - 1.5).abs() < 1e-6);
+        let per_hour = flow.convert_amount(RatePer::Hour, &settings).unwrap();
+        assert!((per_hour - 5400.0).abs() < 1e-3);
+
+        flow.convert(RatePer::Hour, &settings).unwrap();
+        flow.convert(RatePer::Second, &settings).unwrap();
+        assert_eq!(flow.rate, RatePer::Second);
+        assert!((flow.amount - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_convert_other_tick_rate() {
+        test_env::setup();
+        let settings = TimeSettings {
+            ticks_per_second: 60.0,
+            units: vec![TimeUnit {
+                name: "day".to_string(),
+                seconds: 1200.0,
+            }],
+            display_rate: None,
+        };
+        let info = setup_flow_resource(setup_resource_a(), 3, RatePer::Second);
+        let per_tick = info.flow.convert_amount(RatePer::Tick, &settings).unwrap();
+        assert!((per_tick - 0.05).abs() < 1e-6);
+        let per_tick = info
+            .flow
+            .convert_amount(RatePer::Tick, &TimeSettings::default())
+            .unwrap();
+        assert!((per_tick - 0.15).abs() < 1e-6);
+        let per_day = info
+            .flow
+            .convert_amount(RatePer::Custom(0), &settings)
+            .unwrap();
+        assert!((per_day - 3600.0).abs() < 1e-2);
+
+        //a cycle of 30 ticks creating 2 pieces is 4 pieces per second at 60 ticks per second
+        let mut flow = ResourceFlow::<f32, f32>::new(&info.resource, 2.0, 0.0, RatePer::Tick);
+        flow.convert_time_base(30.0, RatePer::Tick, &settings)
+            .unwrap();
+        assert_eq!(flow.rate, RatePer::Second);
+        assert!((flow.amount - 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_add_other_unit() {
+        test_env::setup();
+        let mut buckets = setup_resource_a();
+        buckets.unit = Unit::Bucket;
+        let mut millibuckets = setup_resource_a();
+        millibuckets.unit = Unit::Millibucket;
+
+        let settings = TimeSettings::default();
+        let mut flow = ResourceFlow::<f32, f32>::new(&buckets, 1.0, 2.0, RatePer::Minute);
+        flow.add(
+            &ResourceFlow::new(&millibuckets, 1.0, 10.0, RatePer::Second),
+            &settings,
+        );
+        assert_eq!(flow.resource.unit, Unit::Bucket);
+        assert_eq!(flow.rate, RatePer::Minute);
+        assert!((flow.amount - 2.6).abs() < 1e-5);
+
+        let needed = ResourceFlow::<f32, f32>::new(&millibuckets, 1.0, 2600.0, RatePer::Minute);
+        assert_ne!(flow.compare(&needed, &settings), Some(Ordering::Less));
+        assert!((needed.ratio(&flow, &settings).unwrap() - 1.0).abs() < 1e-5);
+
+        let mut pieces = setup_resource_a();
+        pieces.unit = Unit::Piece;
+        flow.add(
+            &ResourceFlow::new(&pieces, 1.0, 10.0, RatePer::Minute),
+            &settings,
+        );
+        assert!((flow.amount - 2.6).abs() < 1e-5);
+    }
 }
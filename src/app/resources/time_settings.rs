@@ -0,0 +1,136 @@
+use crate::app::resources::RatePer;
+use log::warn;
+
+const DEFAULT_TICKS_PER_SECOND: f32 = 20.0;
+const SECONDS_PER_HOUR: f32 = 3600.0;
+
+///A time unit defined by the user, like a day or a game hour
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct TimeUnit {
+    ///name shown in the rates
+    pub name: String,
+
+    ///length of the unit in seconds
+    pub seconds: f32,
+}
+
+///Length of a tick and the custom time units of a project, every rate conversion is given them
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TimeSettings {
+    ///number of game ticks in a second, 20 for Minecraft or 60 for Factorio
+    pub ticks_per_second: f32,
+
+    ///custom time units, a `RatePer::Custom` is an index in this list
+    pub units: Vec<TimeUnit>,
+
+    ///rate every flow is shown in, None to keep the rate of each flow
+    pub display_rate: Option<RatePer>,
+}
+
+impl Default for TimeSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSettings {
+    pub const fn new() -> Self {
+        Self {
+            ticks_per_second: DEFAULT_TICKS_PER_SECOND,
+            units: Vec::new(),
+            display_rate: None,
+        }
+    }
+
+    ///Custom unit of a rate, None if the rate isn't custom or the unit has been removed
+    pub(crate) fn unit(&self, rate: RatePer) -> Option<&TimeUnit> {
+        match rate {
+            RatePer::Custom(index) => self.units.get(index),
+            _ => None,
+        }
+    }
+
+    ///Number of periods of a rate in an hour
+    pub(crate) fn per_hour(&self, rate: RatePer) -> f32 {
+        match rate {
+            RatePer::Tick => self.ticks_per_second * SECONDS_PER_HOUR,
+            RatePer::Second => SECONDS_PER_HOUR,
+            RatePer::Minute => SECONDS_PER_HOUR / 60.0,
+            RatePer::Hour => 1.0,
+            RatePer::Custom(_) => match self.unit(rate) {
+                Some(unit) if unit.seconds > 0.0 => SECONDS_PER_HOUR / unit.seconds,
+                _ => {
+                    warn!("Unknown time unit {rate:?}, an hour is used");
+                    1.0
+                }
+            },
+        }
+    }
+
+    ///Every rate, from the shortest to the longest
+    pub(crate) fn rates(&self) -> Vec<RatePer> {
+        let mut rates = vec![
+            RatePer::Tick,
+            RatePer::Second,
+            RatePer::Minute,
+            RatePer::Hour,
+        ];
+        rates.extend((0..self.units.len()).map(RatePer::Custom));
+        rates.sort_by(|a, b| self.per_hour(*b).total_cmp(&self.per_hour(*a)));
+        rates
+    }
+
+    ///The shortest rate longer than the given one, None if it's the longest
+    pub(crate) fn next(&self, rate: RatePer) -> Option<RatePer> {
+        let per_hour = self.per_hour(rate);
+        self.rates()
+            .into_iter()
+            .find(|other| self.per_hour(*other) < per_hour)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::resources::time_settings::{TimeSettings, TimeUnit};
+    use crate::app::resources::RatePer;
+    use crate::utils::test_env;
+
+    //-------------------Tests-------------------
+
+    #[test]
+    fn test_custom_units() {
+        test_env::setup();
+        let settings = TimeSettings {
+            ticks_per_second: 60.0,
+            units: vec![
+                TimeUnit {
+                    name: "day".to_string(),
+                    seconds: 86400.0,
+                },
+                TimeUnit {
+                    name: "game hour".to_string(),
+                    seconds: 50.0,
+                },
+            ],
+            display_rate: None,
+        };
+        assert_eq!(settings.per_hour(RatePer::Tick), 216000.0);
+        assert_eq!(settings.per_hour(RatePer::Custom(1)), 72.0);
+        assert_eq!(
+            settings.rates(),
+            vec![
+                RatePer::Tick,
+                RatePer::Second,
+                RatePer::Custom(1),
+                RatePer::Minute,
+                RatePer::Hour,
+                RatePer::Custom(0),
+            ]
+        );
+        assert_eq!(settings.next(RatePer::Second), Some(RatePer::Custom(1)));
+        assert_eq!(settings.next(RatePer::Hour), Some(RatePer::Custom(0)));
+        assert_eq!(settings.next(RatePer::Custom(0)), None);
+        assert_eq!(TimeSettings::new().next(RatePer::Hour), None);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::app::resources::time_settings::TimeSettings;
+use crate::app::resources::{RatePer, Unit};
+use log::debug;
+
+///Stable identifier of a transport tier, it survives renaming and reordering
+#[derive(serde::Deserialize, serde::Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TierId(u64);
+
+///A way to carry a resource, like a belt or a pipe, with the most one lane of it can carry
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct TransportTier {
+    pub(crate) id: TierId,
+
+    pub(crate) name: String,
+
+    ///most carried by one lane, in `unit` per `rate`
+    pub(crate) capacity: f32,
+
+    pub(crate) unit: Unit,
+
+    pub(crate) rate: RatePer,
+}
+
+impl TransportTier {
+    ///Most carried by one lane per hour in base units
+    pub(crate) fn hourly_capacity(&self, settings: &TimeSettings) -> f64 {
+        f64::from(self.capacity * self.rate.per_hour(settings) * self.unit.factor())
+    }
+
+    ///Indicate the tier can carry a resource measured in a unit
+    pub(crate) fn carries(&self, unit: Unit) -> bool {
+        self.unit.dimension() == unit.dimension()
+    }
+
+    /// Number of parallel lanes of the tier needed to carry a flow
+    ///
+    /// # Arguments
+    ///
+    /// * `amount`: the flow per hour in base units
+    /// * `settings`: the time settings the capacity is converted with
+    ///
+    /// returns: the number of lanes, at least one
+    pub(crate) fn lanes(&self, amount: f64, settings: &TimeSettings) -> u32 {
+        let capacity = self.hourly_capacity(settings);
+        if capacity <= 0.0 {
+            return u32::MAX;
+        }
+        //a flow right at the capacity fits in one lane despite the rounding
+        ((amount / capacity) * (1.0 - 1e-6)).ceil().max(1.0) as u32
+    }
+}
+
+///Games whose transport tiers can be loaded in a project
+#[derive(serde::Deserialize, serde::Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Game {
+    Factorio,
+    Satisfactory,
+}
+
+impl Game {
+    pub(crate) fn all() -> [Game; 2] {
+        [Game::Factorio, Game::Satisfactory]
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Game::Factorio => "Factorio",
+            Game::Satisfactory => "Satisfactory",
+        }
+    }
+
+    ///Name, capacity, unit and rate of the tiers of the game
+    fn tiers(self) -> Vec<(&'static str, f32, Unit, RatePer)> {
+        match self {
+            Game::Factorio => vec![
+                ("Transport belt", 15.0, Unit::Piece, RatePer::Second),
+                ("Fast transport belt", 30.0, Unit::Piece, RatePer::Second),
+                ("Express transport belt", 45.0, Unit::Piece, RatePer::Second),
+                ("Turbo transport belt", 60.0, Unit::Piece, RatePer::Second),
+                ("Pipe", 1000.0, Unit::Liter, RatePer::Second),
+            ],
+            Game::Satisfactory => vec![
+                ("Conveyor Belt Mk.1", 60.0, Unit::Piece, RatePer::Minute),
+                ("Conveyor Belt Mk.2", 120.0, Unit::Piece, RatePer::Minute),
+                ("Conveyor Belt Mk.3", 270.0, Unit::Piece, RatePer::Minute),
+                ("Conveyor Belt Mk.4", 480.0, Unit::Piece, RatePer::Minute),
+                ("Conveyor Belt Mk.5", 780.0, Unit::Piece, RatePer::Minute),
+                ("Conveyor Belt Mk.6", 1200.0, Unit::Piece, RatePer::Minute),
+                ("Pipeline Mk.1", 300.0, Unit::CubicMeter, RatePer::Minute),
+                ("Pipeline Mk.2", 600.0, Unit::CubicMeter, RatePer::Minute),
+            ],
+        }
+    }
+}
+
+///Transport tiers of the project, the arrows are limited by the tier they use
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TransportLibrary {
+    ///game the tiers were loaded from, None when the user made them
+    pub(crate) game: Option<Game>,
+
+    tiers: Vec<TransportTier>,
+    next_id: u64,
+}
+
+impl TransportLibrary {
+    pub(crate) fn tiers(&self) -> &[TransportTier] {
+        &self.tiers
+    }
+
+    pub(crate) fn tiers_mut(&mut self) -> &mut [TransportTier] {
+        &mut self.tiers
+    }
+
+    ///Tier of an id, None if it has been removed
+    pub(crate) fn get(&self, id: TierId) -> Option<&TransportTier> {
+        self.tiers.iter().find(|tier| tier.id == id)
+    }
+
+    /// Add a tier to the library
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: name of the tier
+    /// * `capacity`: most carried by one lane, in `unit` per `rate`
+    /// * `unit`: unit of the capacity
+    /// * `rate`: rate of the capacity
+    ///
+    /// returns: the id of the new tier
+    pub(crate) fn add(&mut self, name: &str, capacity: f32, unit: Unit, rate: RatePer) -> TierId {
+        let id = TierId(self.next_id);
+        self.next_id += 1;
+        self.tiers.push(TransportTier {
+            id,
+            name: name.to_string(),
+            capacity,
+            unit,
+            rate,
+        });
+        id
+    }
+
+    ///Remove a tier, the arrows using it aren't limited anymore
+    pub(crate) fn remove(&mut self, id: TierId) {
+        self.tiers.retain(|tier| tier.id != id);
+    }
+
+    ///Replace the tiers by the ones of a game
+    pub(crate) fn load_game(&mut self, game: Game) {
+        debug!("Transport tiers of {} loaded", game.name());
+        self.tiers.clear();
+        for (name, capacity, unit, rate) in game.tiers() {
+            self.add(name, capacity, unit, rate);
+        }
+        self.game = Some(game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::resources::time_settings::TimeSettings;
+    use crate::app::resources::transport::{Game, TransportLibrary};
+    use crate::app::resources::{RatePer, Unit};
+    use crate::utils::test_env;
+
+    //-------------------Tests-------------------
+
+    #[test]
+    fn test_lanes() {
+        test_env::setup();
+        let settings = TimeSettings::default();
+        let mut library = TransportLibrary::default();
+        let belt = library.add("Belt", 15.0, Unit::Piece, RatePer::Second);
+        let belt = library.get(belt).unwrap();
+        assert_eq!(belt.hourly_capacity(&settings), 54000.0);
+        assert_eq!(belt.lanes(0.0, &settings), 1);
+        assert_eq!(belt.lanes(54000.0, &settings), 1);
+        assert_eq!(belt.lanes(54001.0, &settings), 2);
+        assert_eq!(belt.lanes(150000.0, &settings), 3);
+        assert!(belt.carries(Unit::Stack(64)));
+        assert!(!belt.carries(Unit::Liter));
+
+        library.load_game(Game::Satisfactory);
+        assert_eq!(library.game, Some(Game::Satisfactory));
+        assert_eq!(library.tiers().len(), 8);
+        assert!(library.get(library.tiers()[0].id).is_some());
+        let removed = library.tiers()[0].id;
+        library.remove(removed);
+        assert!(library.get(removed).is_none());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod formatting;
 pub mod log;
+pub mod rational;
 
 use num_traits::{Float, Num, NumCast, One, ToPrimitive};
 use std::fmt::Display;
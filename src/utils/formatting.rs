@@ -14,11 +14,7 @@ pub fn float_format<F: FloatingNumber>(float: F, precision: usize) -> String {
                 );
                 0
             });
-        if n <= precision {
-            precision - n
-        } else {
-            0
-        }
+        precision.saturating_sub(n)
     } else if a > F::zero() {
         let n = (-(F::one() + a.log10().floor()))
             .to_usize()
@@ -38,9 +34,37 @@ pub fn float_format<F: FloatingNumber>(float: F, precision: usize) -> String {
     format!("{float:.precision$}")
 }
 
+///Format a power given in watts with the SI prefix fitting its size
+pub fn power_format(watts: f32, precision: usize) -> String {
+    const PREFIXES: [(&str, f32); 4] = [("", 1.0), ("k", 1e3), ("M", 1e6), ("G", 1e9)];
+    let (prefix, factor) = PREFIXES
+        .iter()
+        .rev()
+        .find(|(_, factor)| watts.abs() >= *factor)
+        .unwrap_or(&PREFIXES[0]);
+    format!("{} {prefix}W", float_format(watts / factor, precision))
+}
+
+///Format a duration given in hours with the largest unit it holds, from seconds to years
+pub fn duration_format(hours: f64, precision: usize) -> String {
+    const UNITS: [(&str, f64); 5] = [
+        ("s", 1.0 / 3600.0),
+        ("min", 1.0 / 60.0),
+        ("h", 1.0),
+        ("days", 24.0),
+        ("years", 24.0 * 365.25),
+    ];
+    let (name, factor) = UNITS
+        .iter()
+        .rev()
+        .find(|(_, factor)| hours.abs() >= *factor)
+        .unwrap_or(&UNITS[0]);
+    format!("{} {name}", float_format(hours / factor, precision))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::formatting::float_format;
+    use crate::utils::formatting::{duration_format, float_format, power_format};
     use crate::utils::test_env;
 
     const TESTS_FORMATS: &[(f64, usize, &str)] = &[
@@ -70,4 +94,22 @@ mod tests {
             assert_eq!(result, format!("-{}", *expected));
         }
     }
+
+    #[test]
+    fn test_power_formatting() {
+        test_env::setup();
+        assert_eq!(power_format(0.0, 3), "0 W");
+        assert_eq!(power_format(250.0, 3), "250 W");
+        assert_eq!(power_format(1500.0, 3), "1.50 kW");
+        assert_eq!(power_format(-2.5e6, 2), "-2.5 MW");
+    }
+
+    #[test]
+    fn test_duration_formatting() {
+        test_env::setup();
+        assert_eq!(duration_format(0.5, 3), "30.0 min");
+        assert_eq!(duration_format(2.0, 3), "2.00 h");
+        assert_eq!(duration_format(36.0, 2), "1.5 days");
+        assert_eq!(duration_format(24.0 * 365.25 * 10.0, 3), "10.0 years");
+    }
 }
@@ -0,0 +1,481 @@
+use num_traits::{Num, NumCast, One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Rem, Sub};
+
+///Biggest denominator used when a float is turned into a rational
+const MAX_DENOMINATOR: i128 = 1_000_000;
+
+///Relative tolerance of the approximation of a float by a rational
+const APPROXIMATION_TOLERANCE: f64 = 1e-9;
+
+///An exact fraction, always reduced with a positive denominator. Like the NaN of the floats, a
+/// zero denominator marks a value an operation couldn't represent, see [Rational::is_valid].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+///Why an exact operation failed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RationalError {
+    ///a term of the result doesn't fit in 128 bits
+    Overflow,
+
+    ///the divisor is zero
+    DivisionByZero,
+
+    ///an operand is already invalid
+    Invalid,
+}
+
+impl Display for RationalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RationalError::Overflow => write!(f, "an exact value overflowed"),
+            RationalError::DivisionByZero => write!(f, "a division by zero"),
+            RationalError::Invalid => write!(f, "an invalid exact value"),
+        }
+    }
+}
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.abs()
+}
+
+impl Rational {
+    ///Result of the operators that failed, it stays invalid through the next operations
+    pub const INVALID: Self = Self { num: 0, den: 0 };
+
+    /// Create a reduced fraction
+    ///
+    /// # Arguments
+    ///
+    /// * `num`: numerator
+    /// * `den`: denominator
+    ///
+    /// returns: Rational, invalid if the denominator is zero
+    pub fn new(num: i128, den: i128) -> Self {
+        Self::checked_new(num, den).unwrap_or(Self::INVALID)
+    }
+
+    /// Create a reduced fraction
+    ///
+    /// # Arguments
+    ///
+    /// * `num`: numerator
+    /// * `den`: denominator
+    ///
+    /// returns: Result<Rational, RationalError>
+    pub fn checked_new(num: i128, den: i128) -> Result<Self, RationalError> {
+        if den == 0 {
+            return Err(RationalError::DivisionByZero);
+        }
+        let divisor = gcd(num, den).max(1);
+        let sign = den.signum();
+        Ok(Self {
+            num: (num / divisor)
+                .checked_mul(sign)
+                .ok_or(RationalError::Overflow)?,
+            den: (den / divisor)
+                .checked_mul(sign)
+                .ok_or(RationalError::Overflow)?,
+        })
+    }
+
+    ///Indicate the rational holds a value, and not the result of a failed operation
+    pub fn is_valid(&self) -> bool {
+        self.den != 0
+    }
+
+    pub fn from_integer(value: i128) -> Self {
+        Self { num: value, den: 1 }
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.num
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.den
+    }
+
+    ///Indicate the rational is a whole number
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    ///Smallest whole number greater or equal to the rational, None if it's invalid
+    pub fn ceil(&self) -> Option<i128> {
+        if !self.is_valid() {
+            return None;
+        }
+        let quotient = self.num.div_euclid(self.den);
+        match self.num.rem_euclid(self.den) {
+            0 => Some(quotient),
+            _ => quotient.checked_add(1),
+        }
+    }
+
+    ///The value as a float, NaN if it's invalid
+    pub fn as_f64(self) -> f64 {
+        match self.is_valid() {
+            true => self.num as f64 / self.den as f64,
+            false => f64::NAN,
+        }
+    }
+
+    /// Find the simplest rational close to a float, with continued fractions. Fractions typed as
+    /// decimals, like 0.333333 for a third, get back their exact value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: the float to approximate
+    ///
+    /// returns: None if the value isn't finite or too big
+    pub fn approximate(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        let tolerance = APPROXIMATION_TOLERANCE * value.abs().max(1.0);
+        let (mut h, mut h_previous) = (value.floor() as i128, 1i128);
+        let (mut k, mut k_previous) = (1i128, 0i128);
+        let mut remainder = value - value.floor();
+        while (value - h as f64 / k as f64).abs() > tolerance && remainder > f64::EPSILON {
+            let inverse = 1.0 / remainder;
+            let a = inverse.floor() as i128;
+            let k_next = a * k + k_previous;
+            if k_next > MAX_DENOMINATOR {
+                break;
+            }
+            (h, h_previous) = (a * h + h_previous, h);
+            (k, k_previous) = (k_next, k);
+            remainder = inverse - inverse.floor();
+        }
+        Some(Self::new(h, k))
+    }
+
+    ///Exact sum
+    pub fn checked_add(self, other: Self) -> Result<Self, RationalError> {
+        if !self.is_valid() || !other.is_valid() {
+            return Err(RationalError::Invalid);
+        }
+        let overflow = || RationalError::Overflow;
+        let den = self
+            .den
+            .checked_mul(other.den / gcd(self.den, other.den))
+            .ok_or_else(overflow)?;
+        let left = self.num.checked_mul(den / self.den).ok_or_else(overflow)?;
+        let right = other
+            .num
+            .checked_mul(den / other.den)
+            .ok_or_else(overflow)?;
+        Self::checked_new(left.checked_add(right).ok_or_else(overflow)?, den)
+    }
+
+    ///Exact product, the factors are reduced crosswise first to keep the terms small
+    pub fn checked_mul(self, other: Self) -> Result<Self, RationalError> {
+        if !self.is_valid() || !other.is_valid() {
+            return Err(RationalError::Invalid);
+        }
+        let a = gcd(self.num, other.den).max(1);
+        let b = gcd(other.num, self.den).max(1);
+        let num = (self.num / a)
+            .checked_mul(other.num / b)
+            .ok_or(RationalError::Overflow)?;
+        let den = (self.den / b)
+            .checked_mul(other.den / a)
+            .ok_or(RationalError::Overflow)?;
+        Self::checked_new(num, den)
+    }
+
+    ///Exact quotient
+    pub fn checked_div(self, other: Self) -> Result<Self, RationalError> {
+        self.checked_mul(other.recip()?)
+    }
+
+    ///Inverse of the rational, an error for zero
+    pub fn recip(self) -> Result<Self, RationalError> {
+        if !self.is_valid() {
+            return Err(RationalError::Invalid);
+        }
+        Self::checked_new(self.den, self.num)
+    }
+
+    ///Result of an operator, a failed operation gives an invalid rational
+    fn or_invalid(result: Result<Self, RationalError>) -> Self {
+        result.unwrap_or_else(|error| {
+            log::error!("Exact operation failed: {error}");
+            Self::INVALID
+        })
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.den {
+            0 => write!(f, "NaN"),
+            1 => write!(f, "{}", self.num),
+            _ => write!(f, "{}/{}", self.num, self.den),
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (
+            self.num.checked_mul(other.den),
+            other.num.checked_mul(self.den),
+        ) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => self.as_f64().total_cmp(&other.as_f64()),
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::or_invalid(self.checked_add(rhs))
+    }
+}
+
+impl AddAssign for Rational {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::or_invalid(self.checked_mul(rhs))
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        Self::or_invalid(self.checked_div(rhs))
+    }
+}
+
+impl Rem for Rational {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        let quotient = self / rhs;
+        if !quotient.is_valid() {
+            return Self::INVALID;
+        }
+        self - rhs * Self::from_integer(quotient.num / quotient.den)
+    }
+}
+
+impl Zero for Rational {
+    fn zero() -> Self {
+        Self::from_integer(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.num == 0 && self.is_valid()
+    }
+}
+
+impl One for Rational {
+    fn one() -> Self {
+        Self::from_integer(1)
+    }
+}
+
+impl Num for Rational {
+    type FromStrRadixErr = std::num::ParseIntError;
+
+    ///Parse "a" or "a/b"
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        match str.split_once('/') {
+            None => Ok(Self::from_integer(i128::from_str_radix(str.trim(), radix)?)),
+            Some((num, den)) => Ok(Self::new(
+                i128::from_str_radix(num.trim(), radix)?,
+                i128::from_str_radix(den.trim(), radix)?,
+            )),
+        }
+    }
+}
+
+impl Signed for Rational {
+    fn abs(&self) -> Self {
+        Self {
+            num: self.num.abs(),
+            den: self.den,
+        }
+    }
+
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            Self::zero()
+        } else {
+            *self - *other
+        }
+    }
+
+    fn signum(&self) -> Self {
+        Self::from_integer(self.num.signum())
+    }
+
+    fn is_positive(&self) -> bool {
+        self.num > 0
+    }
+
+    fn is_negative(&self) -> bool {
+        self.num < 0
+    }
+}
+
+impl ToPrimitive for Rational {
+    fn to_i64(&self) -> Option<i64> {
+        self.num.checked_div(self.den)?.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.num.checked_div(self.den)?.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(self.as_f64())
+    }
+}
+
+impl NumCast for Rational {
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        Self::approximate(n.to_f64()?)
+    }
+}
+
+impl eframe::emath::Numeric for Rational {
+    const INTEGRAL: bool = false;
+    const MIN: Self = Self {
+        num: i64::MIN as i128,
+        den: 1,
+    };
+    const MAX: Self = Self {
+        num: i64::MAX as i128,
+        den: 1,
+    };
+
+    fn to_f64(self) -> f64 {
+        self.as_f64()
+    }
+
+    fn from_f64(num: f64) -> Self {
+        Self::approximate(num).unwrap_or(Self::INVALID)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::rational::{Rational, RationalError};
+    use crate::utils::test_env;
+    use num_traits::{Num, Zero};
+
+    //-------------------Tests-------------------
+
+    #[test]
+    fn test_arithmetic() {
+        test_env::setup();
+        let third = Rational::new(1, 3);
+        let half = Rational::new(2, 4);
+        assert_eq!(half, Rational::new(1, 2));
+        assert_eq!(third + half, Rational::new(5, 6));
+        assert_eq!(third - half, Rational::new(-1, 6));
+        assert_eq!(third * half, Rational::new(1, 6));
+        assert_eq!(third / half, Rational::new(2, 3));
+        assert_eq!(Rational::new(7, 3) % Rational::from_integer(1), third);
+        assert!((third * Rational::from_integer(3) - Rational::from_integer(1)).is_zero());
+        assert!(third < half);
+        assert_eq!(Rational::new(7, 3).ceil(), Some(3));
+        assert_eq!(Rational::new(-7, 3).ceil(), Some(-2));
+        assert_eq!(Rational::new(6, 3).ceil(), Some(2));
+        assert_eq!(Rational::new(7, -3).to_string(), "-7/3");
+        assert_eq!(
+            Rational::from_str_radix("14/6", 10),
+            Ok(Rational::new(7, 3))
+        );
+    }
+
+    #[test]
+    fn test_approximate() {
+        test_env::setup();
+        assert_eq!(Rational::approximate(0.5), Some(Rational::new(1, 2)));
+        assert_eq!(Rational::approximate(2.5), Some(Rational::new(5, 2)));
+        assert_eq!(
+            Rational::approximate(f64::from(1.0f32 / 3.0)),
+            Some(Rational::new(1, 3))
+        );
+        assert_eq!(Rational::approximate(-0.75), Some(Rational::new(-3, 4)));
+        assert_eq!(Rational::approximate(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_failed_operations() {
+        test_env::setup();
+        let third = Rational::new(1, 3);
+        let zero = Rational::zero();
+        assert_eq!(third.checked_div(zero), Err(RationalError::DivisionByZero));
+        assert_eq!(zero.recip(), Err(RationalError::DivisionByZero));
+        assert_eq!(
+            Rational::checked_new(1, 0),
+            Err(RationalError::DivisionByZero)
+        );
+
+        let huge = Rational::from_integer(i128::MAX / 2 + 1);
+        assert_eq!(huge.checked_mul(huge), Err(RationalError::Overflow));
+        assert_eq!(huge.checked_add(huge), Err(RationalError::Overflow));
+
+        let failed = third / zero;
+        assert!(!failed.is_valid());
+        assert!(!(failed + third).is_valid());
+        assert!(!(huge * huge).is_valid());
+        assert!(failed.as_f64().is_nan());
+        assert_eq!(failed.ceil(), None);
+        assert_eq!(third.checked_add(failed), Err(RationalError::Invalid));
+    }
+}